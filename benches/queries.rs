@@ -0,0 +1,134 @@
+//! Construction and query benchmarks
+//!
+//! Run with `cargo bench`, and with `cargo bench --features simd` to compare the leaf scans.
+//! The number of points can be passed as argument, e.g. `cargo bench -- 100000`.
+
+use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Deterministic pseudo-random numbers in [0, 1)
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn report(name: &str, runs: usize, elapsed: Duration) {
+    println!(
+        "{:<32} {:>10.3} ms total {:>12.3} us/run",
+        name,
+        elapsed.as_secs_f64() * 1e3,
+        elapsed.as_secs_f64() * 1e6 / runs as f64
+    );
+}
+
+fn bench<F: FnMut(usize) -> usize>(name: &str, runs: usize, mut f: F) {
+    let start = Instant::now();
+    let mut found = 0;
+    for i in 0..runs {
+        found += f(i);
+    }
+    report(name, runs, start.elapsed());
+    black_box(found);
+}
+
+fn main() {
+    let n = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(1_000_000);
+    let mut rng = Lcg(42);
+    let points: Vec<(f64, f64)> = (0..n)
+        .map(|_| (rng.next() * 1000.0, rng.next() * 1000.0))
+        .collect();
+    let queries: Vec<(f64, f64)> = (0..10_000)
+        .map(|_| (rng.next() * 1000.0, rng.next() * 1000.0))
+        .collect();
+
+    println!("{} points", n);
+    let start = Instant::now();
+    let index = KDBush::create(points.clone(), DEFAULT_NODE_SIZE);
+    report("create", 1, start.elapsed());
+    for &node_size in &[16, 32] {
+        let start = Instant::now();
+        black_box(KDBush::create(points.clone(), node_size));
+        report(
+            &format!("create (node size {})", node_size),
+            1,
+            start.elapsed(),
+        );
+    }
+
+    for &size in &[1.0, 10.0, 50.0] {
+        bench(&format!("range {}x{}", size, size), queries.len(), |i| {
+            let (x, y) = queries[i];
+            let mut found = 0;
+            index.range(x, y, x + size, y + size, |_| found += 1);
+            found
+        });
+    }
+    for &r in &[1.0, 10.0, 50.0] {
+        bench(&format!("within {}", r), queries.len(), |i| {
+            let (x, y) = queries[i];
+            let mut found = 0;
+            index.within(x, y, r, |_| found += 1);
+            found
+        });
+    }
+    let boxes: Vec<[f64; 4]> = queries
+        .iter()
+        .map(|&(x, y)| [x, y, x + 10.0, y + 10.0])
+        .collect();
+    bench("range 10x10, one by one", 1, |_| {
+        let mut found = 0;
+        for b in &boxes {
+            index.range(b[0], b[1], b[2], b[3], |_| found += 1);
+        }
+        found
+    });
+    bench("range_batch 10x10", 1, |_| {
+        let mut found = 0;
+        index.range_batch(&boxes, |_, _| found += 1);
+        found
+    });
+    // Adjacent tiles, like the requests of a tile server
+    let tiles: Vec<[f64; 4]> = (0..10_000)
+        .map(|i| {
+            let (x, y) = ((i % 100) as f64 * 10.0, (i / 100) as f64 * 10.0);
+            [x, y, x + 10.0, y + 10.0]
+        })
+        .collect();
+    bench("range tiles, one by one", 1, |_| {
+        let mut found = 0;
+        for b in &tiles {
+            index.range(b[0], b[1], b[2], b[3], |_| found += 1);
+        }
+        found
+    });
+    bench("range_batch tiles", 1, |_| {
+        let mut found = 0;
+        index.range_batch(&tiles, |_, _| found += 1);
+        found
+    });
+    for &size in &[10.0, 50.0] {
+        bench(
+            &format!("count_range {}x{}", size, size),
+            queries.len(),
+            |i| {
+                let (x, y) = queries[i];
+                index.count_range(x, y, x + size, y + size)
+            },
+        );
+    }
+    bench("nearest 10", queries.len(), |i| {
+        let (x, y) = queries[i];
+        index.nearest(x, y, 10).len()
+    });
+}
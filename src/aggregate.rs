@@ -0,0 +1,298 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, KDBush, KdId, KdNum, TIndex};
+use alloc::vec;
+use core::ops::Range;
+
+/// Query area of the counting and aggregating traversal
+enum Area<T: KdNum> {
+    Range([T; 4]),
+    Within([T; 2], T::Dist),
+}
+
+impl<T: KdNum> Area<T> {
+    fn contains(&self, p: [T; 2]) -> bool {
+        match *self {
+            Area::Range([minx, miny, maxx, maxy]) => {
+                p[0] >= minx && p[0] <= maxx && p[1] >= miny && p[1] <= maxy
+            }
+            Area::Within(q, r2) => T::sq_diff(p[0], q[0]) + T::sq_diff(p[1], q[1]) <= r2,
+        }
+    }
+
+    /// Whether the area contains the whole node extent, `None` bounds are unbounded
+    fn covers(&self, bounds: &[Option<T>; 4]) -> bool {
+        let [Some(minx), Some(miny), Some(maxx), Some(maxy)] = *bounds else {
+            return false;
+        };
+        match *self {
+            Area::Range(bbox) => {
+                minx >= bbox[0] && miny >= bbox[1] && maxx <= bbox[2] && maxy <= bbox[3]
+            }
+            Area::Within(q, r2) => {
+                let farthest = |a: T, b: T, q: T| {
+                    let (da, db) = (T::sq_diff(a, q), T::sq_diff(b, q));
+                    if da > db {
+                        da
+                    } else {
+                        db
+                    }
+                };
+                farthest(minx, maxx, q[0]) + farthest(miny, maxy, q[1]) <= r2
+            }
+        }
+    }
+
+    /// Whether the area reaches below and above the split coordinate
+    fn reaches(&self, split: T, axis: usize) -> (bool, bool) {
+        match *self {
+            Area::Range(bbox) => (bbox[axis] <= split, bbox[2 + axis] >= split),
+            Area::Within(q, r2) => {
+                let near = T::sq_diff(q[axis], split) <= r2;
+                (q[axis] <= split || near, q[axis] >= split || near)
+            }
+        }
+    }
+}
+
+impl<T: KdNum, I: KdId> KDBush<T, I> {
+    /// Counts the items within the given bounding box, without visiting them
+    ///
+    /// Subtrees whose extent, bounded by the split values of their ancestors, lies within the
+    /// box are counted as a whole, so large boxes cost about as much as their boundary.
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::KDBush;
+    ///
+    /// let points: Vec<(f64, f64)> = (0..100).map(|i| ((i % 10) as f64, (i / 10) as f64)).collect();
+    /// let index = KDBush::create(points, 4);
+    /// assert_eq!(index.count_range(2.0, 2.0, 5.0, 3.0), 8);
+    /// ```
+    pub fn count_range(&self, minx: T, miny: T, maxx: T, maxy: T) -> usize {
+        if !is_valid_bbox(minx, miny, maxx, maxy) {
+            return 0;
+        }
+        let mut count = 0;
+        self.aggregate_idx(&Area::Range([minx, miny, maxx, maxy]), &mut |items| {
+            count += items.len()
+        });
+        count
+    }
+
+    /// Counts the items within a given radius from the query point, without visiting them
+    ///
+    /// Like with [`KDBush::within`], negative or NaN radii match no items.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    pub fn count_within(&self, qx: T, qy: T, r: T) -> usize {
+        if !is_valid_radius(r) {
+            return 0;
+        }
+        if r.is_infinite() {
+            return self.ids.len();
+        }
+        let mut count = 0;
+        let area = Area::Within([qx, qy], T::sq_diff(r, T::ZERO));
+        self.aggregate_idx(&area, &mut |items| count += items.len());
+        count
+    }
+
+    /// Folds the items within the given bounding box into a single value
+    ///
+    /// Items of subtrees within the box are folded without testing their coordinates.
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `init` - Initial value
+    /// * `fold` - Combines the value with the id and the coordinates of an item
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::KDBush;
+    ///
+    /// let points = vec![(1.0, 1.0), (2.0, 4.0), (8.0, 8.0)];
+    /// let index = KDBush::create(points, 8);
+    /// let sum_y = index.aggregate_range(0.0, 0.0, 5.0, 5.0, 0.0, |sum, _, p| sum + p[1]);
+    /// assert_eq!(sum_y, 5.0);
+    /// ```
+    pub fn aggregate_range<A, F>(&self, minx: T, miny: T, maxx: T, maxy: T, init: A, fold: F) -> A
+    where
+        F: FnMut(A, TIndex, [T; 2]) -> A,
+    {
+        if !is_valid_bbox(minx, miny, maxx, maxy) {
+            return init;
+        }
+        self.fold_area(&Area::Range([minx, miny, maxx, maxy]), init, fold)
+    }
+
+    /// Folds the items within a given radius from the query point into a single value
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `init` - Initial value
+    /// * `fold` - Combines the value with the id and the coordinates of an item
+    pub fn aggregate_within<A, F>(&self, qx: T, qy: T, r: T, init: A, fold: F) -> A
+    where
+        F: FnMut(A, TIndex, [T; 2]) -> A,
+    {
+        if !is_valid_radius(r) {
+            return init;
+        }
+        self.fold_area(&Area::Within([qx, qy], T::sq_diff(r, T::ZERO)), init, fold)
+    }
+
+    fn fold_area<A, F>(&self, area: &Area<T>, init: A, mut fold: F) -> A
+    where
+        F: FnMut(A, TIndex, [T; 2]) -> A,
+    {
+        let mut acc = Some(init);
+        self.aggregate_idx(area, &mut |items| {
+            for i in items {
+                let value = acc.take().unwrap();
+                acc = Some(fold(value, self.ids[i].to_usize(), self.points[i]));
+            }
+        });
+        acc.unwrap()
+    }
+
+    /// Visits the positions of the items within the area, in ranges of matching positions
+    fn aggregate_idx<F>(&self, area: &Area<T>, visitor: &mut F)
+    where
+        F: FnMut(Range<TIndex>),
+    {
+        if self.ids.is_empty() {
+            return;
+        }
+        let mut stack = vec![(0, self.ids.len() - 1, 0, [None; 4])];
+        while let Some((left, right, axis, bounds)) = stack.pop() {
+            if area.covers(&bounds) {
+                visitor(left..right + 1);
+                continue;
+            }
+            if right - left <= self.node_size as usize {
+                for i in left..right + 1 {
+                    if area.contains(self.points[i]) {
+                        visitor(i..i + 1);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let split = self.points[m][axis];
+            if area.contains(self.points[m]) {
+                visitor(m..m + 1);
+            }
+
+            let (below, above) = area.reaches(split, axis);
+            if above {
+                let mut upper = bounds;
+                upper[axis] = Some(split);
+                stack.push((m + 1, right, 1 - axis, upper));
+            }
+            if below {
+                let mut lower = bounds;
+                lower[2 + axis] = Some(split);
+                stack.push((left, m - 1, 1 - axis, lower));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn points() -> Vec<(f64, f64)> {
+        (0..2000)
+            .map(|i| {
+                (
+                    (i * 7919 % 1000) as f64 / 10.0,
+                    (i * 104_729 % 997) as f64 / 10.0,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_count_range() {
+        let points = points();
+        for node_size in [1, 8, 64] {
+            let index = KDBush::create(points.clone(), node_size);
+            for i in 0..200 {
+                let (x, y) = ((i * 37 % 100) as f64, (i * 53 % 100) as f64);
+                let size = (i % 7 * 9) as f64;
+                let mut expected = 0;
+                index.range(x, y, x + size, y + size, |_| expected += 1);
+                assert_eq!(index.count_range(x, y, x + size, y + size), expected);
+
+                let mut ids = Vec::new();
+                index.range(x, y, x + size, y + size, |id| ids.push(id));
+                let mut folded =
+                    index.aggregate_range(x, y, x + size, y + size, Vec::new(), |mut v, id, p| {
+                        assert_eq!((p[0], p[1]), points[id]);
+                        v.push(id);
+                        v
+                    });
+                ids.sort();
+                folded.sort();
+                assert_eq!(folded, ids);
+            }
+            assert_eq!(index.count_range(-1.0, -1.0, 101.0, 101.0), points.len());
+            assert_eq!(index.count_range(5.0, 5.0, 4.0, 6.0), 0);
+            assert_eq!(index.count_range(f64::NAN, 0.0, 10.0, 10.0), 0);
+            assert_eq!(
+                index.aggregate_range(5.0, 5.0, 4.0, 6.0, 7, |n, _, _| n + 1),
+                7
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_within() {
+        let points = points();
+        let index = KDBush::create(points.clone(), 16);
+        for i in 0..200 {
+            let (x, y) = ((i * 37 % 100) as f64, (i * 53 % 100) as f64);
+            let r = (i % 7 * 6) as f64;
+            let mut expected = 0;
+            index.within(x, y, r, |_| expected += 1);
+            assert_eq!(index.count_within(x, y, r), expected);
+            assert_eq!(
+                index.aggregate_within(x, y, r, 0, |n, _, _| n + 1),
+                expected
+            );
+        }
+        assert_eq!(index.count_within(50.0, 50.0, 200.0), points.len());
+        assert_eq!(index.count_within(50.0, 50.0, f64::INFINITY), points.len());
+        assert_eq!(index.count_within(50.0, 50.0, -1.0), 0);
+        assert_eq!(index.count_within(50.0, 50.0, f64::NAN), 0);
+    }
+
+    #[test]
+    fn test_count_types_and_empty() {
+        let points: Vec<(i32, i32)> = (0..500).map(|i| (i % 25, i / 25)).collect();
+        let index = KDBush::<i32>::create(points, 4);
+        assert_eq!(index.count_range(5, 5, 9, 9), 25);
+        assert_eq!(index.count_within(10, 10, 1), 5);
+        let sum = index.aggregate_range(0, 0, 24, 0, 0, |sum, _, p| sum + p[0]);
+        assert_eq!(sum, 300);
+
+        let empty = KDBush::<f64>::create(vec![], 8);
+        assert_eq!(empty.count_range(0.0, 0.0, 1.0, 1.0), 0);
+        assert_eq!(empty.count_within(0.0, 0.0, f64::INFINITY), 0);
+        assert_eq!(empty.aggregate_within(0.0, 0.0, 1.0, 3, |n, _, _| n + 1), 3);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, Bbox, KDBush, TIndex, TNumber};
+#[cfg(feature = "parallel")]
+use crate::order::{spatial_order, SpatialOrder};
+use crate::stepped::Shape;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Minimal number of queries reaching a leaf for scanning its points once for all of them
+const SHARED_SCAN_QUERIES: usize = 4;
+
+impl KDBush {
+    /// Finds the items within each of many bounding boxes in a single tree traversal
+    ///
+    /// The tree is descended once for the whole batch. Each node is visited with the queries
+    /// which reach it, so the nodes shared by nearby queries are loaded only once and leaves
+    /// reached by several queries are scanned once for all of them. This pays off for many
+    /// adjacent or overlapping queries, e.g. the tiles of a map view. Scattered queries which
+    /// share few nodes are faster one by one. Results of different queries are interleaved.
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - Bounding boxes `[minx, miny, maxx, maxy]`
+    /// * `visitor` - Result reader, called with the position of the query in `queries` and the item id
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let tiles = [[0.0, 0.0, 60.0, 60.0], [60.0, 0.0, 120.0, 60.0]];
+    /// let mut counts = [0; 2];
+    /// index.range_batch(&tiles, |tile, _id| counts[tile] += 1);
+    /// assert_eq!(counts, [1, 2]);
+    /// ```
+    pub fn range_batch<F>(&self, queries: &[Bbox], mut visitor: F)
+    where
+        F: FnMut(usize, TIndex),
+    {
+        let shapes = range_shapes(queries);
+        self.batch_idx(&shapes, valid_queries(&shapes), &mut visitor);
+    }
+
+    /// Finds the items within a given radius from each of many query points in a single tree traversal
+    ///
+    /// Works like [`KDBush::range_batch`].
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - Query points with radius `(x, y, r)`
+    /// * `visitor` - Result reader, called with the position of the query in `queries` and the item id
+    pub fn within_batch<F>(&self, queries: &[(TNumber, TNumber, TNumber)], mut visitor: F)
+    where
+        F: FnMut(usize, TIndex),
+    {
+        let shapes = within_shapes(queries);
+        self.batch_idx(&shapes, valid_queries(&shapes), &mut visitor);
+    }
+
+    /// Finds the items within each of many bounding boxes, using multiple threads
+    ///
+    /// The queries are ordered along a Hilbert curve and split into one batch per thread,
+    /// so each thread traverses a compact part of the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - Bounding boxes `[minx, miny, maxx, maxy]`
+    ///
+    /// Returns the ids found by each query, in the order of `queries`.
+    #[cfg(feature = "parallel")]
+    pub fn range_batch_parallel(&self, queries: &[Bbox]) -> Vec<Vec<TIndex>> {
+        self.batch_parallel(&range_shapes(queries))
+    }
+
+    /// Finds the items within a given radius from each of many query points, using multiple threads
+    ///
+    /// Works like [`KDBush::range_batch_parallel`].
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - Query points with radius `(x, y, r)`
+    ///
+    /// Returns the ids found by each query, in the order of `queries`.
+    #[cfg(feature = "parallel")]
+    pub fn within_batch_parallel(
+        &self,
+        queries: &[(TNumber, TNumber, TNumber)],
+    ) -> Vec<Vec<TIndex>> {
+        self.batch_parallel(&within_shapes(queries))
+    }
+
+    #[cfg(feature = "parallel")]
+    fn batch_parallel(&self, shapes: &[Shape]) -> Vec<Vec<TIndex>> {
+        let mut results = vec![Vec::new(); shapes.len()];
+        let order = spatially_ordered(shapes);
+        if order.is_empty() {
+            return results;
+        }
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = order.len().div_ceil(threads);
+        let batches: Vec<Vec<(usize, TIndex)>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = order
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut found = Vec::new();
+                        self.batch_idx(shapes, chunk.to_vec(), &mut |q, id| found.push((q, id)));
+                        found
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for (q, id) in batches.into_iter().flatten() {
+            results[q].push(id);
+        }
+        results
+    }
+
+    /// Traverses the tree once for all `active` queries
+    ///
+    /// The query lists of the nodes still to visit are kept in one buffer. A node's list is
+    /// above the lists of all nodes pushed before it, so the buffer can be truncated to the
+    /// end of the list of the node popped next.
+    fn batch_idx<F>(&self, shapes: &[Shape], active: Vec<usize>, visitor: &mut F)
+    where
+        F: FnMut(usize, TIndex),
+    {
+        if self.ids.is_empty() || active.is_empty() {
+            return;
+        }
+        let mut stack = vec![(0, self.ids.len() - 1, 0, 0, active.len())];
+        let mut lists = active;
+        let mut below = Vec::new();
+        while let Some((left, right, axis, start, end)) = stack.pop() {
+            lists.truncate(end);
+            let leaf = right - left <= self.node_size as usize;
+            if end - start == 1 || (leaf && end - start < SHARED_SCAN_QUERIES) {
+                // Nothing worth sharing
+                for &q in &lists[start..end] {
+                    let visitor = &mut |i| visitor(q, self.ids[i]);
+                    self.query_idx(&shapes[q], visitor, left, right, axis);
+                }
+                continue;
+            }
+            if leaf {
+                for i in left..right + 1 {
+                    let p = self.points[i];
+                    for &q in &lists[start..end] {
+                        if shapes[q].contains(p[0], p[1]) {
+                            visitor(q, self.ids[i]);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let p = self.points[m];
+            for &q in &lists[start..end] {
+                if shapes[q].contains(p[0], p[1]) {
+                    visitor(q, self.ids[m]);
+                }
+            }
+            // Right child first, so the left child is visited next and its list is on top
+            let right_start = lists.len();
+            below.clear();
+            for k in start..end {
+                let q = lists[k];
+                let (reaches_below, reaches_above) = shapes[q].reaches(p[axis], axis);
+                if reaches_above {
+                    lists.push(q);
+                }
+                if reaches_below {
+                    below.push(q);
+                }
+            }
+            let left_start = lists.len();
+            lists.extend_from_slice(&below);
+            if left_start > right_start {
+                stack.push((m + 1, right, (axis + 1) % 2, right_start, left_start));
+            }
+            if lists.len() > left_start {
+                stack.push((left, m - 1, (axis + 1) % 2, left_start, lists.len()));
+            }
+        }
+    }
+
+    /// Runs a single query of a batch on the subtree from `left` to `right`
+    fn query_idx<F>(&self, shape: &Shape, visitor: &mut F, left: TIndex, right: TIndex, axis: usize)
+    where
+        F: FnMut(TIndex),
+    {
+        match *shape {
+            Shape::Range(b) => self.range_idx(b[0], b[1], b[2], b[3], visitor, left, right, axis),
+            Shape::Within { qx, qy, r2 } => self.within_idx(qx, qy, r2, visitor, left, right, axis),
+        }
+    }
+}
+
+fn range_shapes(queries: &[Bbox]) -> Vec<Shape> {
+    queries.iter().map(|&b| Shape::Range(b)).collect()
+}
+
+fn within_shapes(queries: &[(TNumber, TNumber, TNumber)]) -> Vec<Shape> {
+    queries
+        .iter()
+        .map(|&(qx, qy, r)| Shape::Within {
+            qx,
+            qy,
+            r2: if is_valid_radius(r) { r * r } else { -1.0 },
+        })
+        .collect()
+}
+
+/// Positions of the queries which may contain items
+fn valid_queries(shapes: &[Shape]) -> Vec<usize> {
+    (0..shapes.len())
+        .filter(|&q| is_valid(&shapes[q]))
+        .collect()
+}
+
+/// Positions of the queries which may contain items, ordered along a Hilbert curve
+#[cfg(feature = "parallel")]
+fn spatially_ordered(shapes: &[Shape]) -> Vec<usize> {
+    let centers: Vec<(TNumber, TNumber)> = shapes.iter().map(Shape::center).collect();
+    spatial_order(centers, SpatialOrder::Hilbert)
+        .into_iter()
+        .filter(|&q| is_valid(&shapes[q]))
+        .collect()
+}
+
+fn is_valid(shape: &Shape) -> bool {
+    match *shape {
+        Shape::Range(b) => is_valid_bbox(b[0], b[1], b[2], b[3]),
+        Shape::Within { r2, .. } => r2 >= 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    fn index() -> KDBush {
+        let points: Vec<(f64, f64)> = (0..5000)
+            .map(|i| ((i * 37 % 1001) as f64, (i * 53 % 997) as f64))
+            .collect();
+        KDBush::create(points, 8)
+    }
+
+    fn boxes() -> Vec<Bbox> {
+        let mut boxes: Vec<Bbox> = (0..200)
+            .map(|i| {
+                let x = (i * 71 % 950) as f64;
+                let y = (i * 89 % 950) as f64;
+                [x, y, x + 40.0, y + 30.0]
+            })
+            .collect();
+        // Overlapping boxes, scanning shared leaves once
+        for i in 0..6 {
+            boxes.push([100.0 + i as f64, 100.0, 400.0, 350.0 - i as f64]);
+        }
+        boxes.push([10.0, 10.0, 0.0, 20.0]);
+        boxes.push([f64::NAN, 0.0, 10.0, 10.0]);
+        boxes
+    }
+
+    fn circles() -> Vec<(f64, f64, f64)> {
+        let mut circles: Vec<(f64, f64, f64)> = (0..200)
+            .map(|i| {
+                (
+                    (i * 71 % 1000) as f64,
+                    (i * 89 % 1000) as f64,
+                    (i % 30) as f64,
+                )
+            })
+            .collect();
+        circles.push((10.0, 10.0, -1.0));
+        circles.push((10.0, 10.0, f64::NAN));
+        circles
+    }
+
+    fn sorted(mut results: Vec<Vec<TIndex>>) -> Vec<Vec<TIndex>> {
+        results.iter_mut().for_each(|r| r.sort());
+        results
+    }
+
+    #[test]
+    fn test_range_batch() {
+        let index = index();
+        let boxes = boxes();
+        let mut results = vec![Vec::new(); boxes.len()];
+        index.range_batch(&boxes, |q, id| results[q].push(id));
+        let expected: Vec<Vec<TIndex>> = boxes
+            .iter()
+            .map(|b| {
+                let mut found = Vec::new();
+                index.range(b[0], b[1], b[2], b[3], |id| found.push(id));
+                found
+            })
+            .collect();
+        assert!(expected.iter().any(|r| !r.is_empty()));
+        assert_eq!(sorted(results), sorted(expected));
+    }
+
+    #[test]
+    fn test_within_batch() {
+        let index = index();
+        let circles = circles();
+        let mut results = vec![Vec::new(); circles.len()];
+        index.within_batch(&circles, |q, id| results[q].push(id));
+        let expected: Vec<Vec<TIndex>> = circles
+            .iter()
+            .map(|&(x, y, r)| {
+                let mut found = Vec::new();
+                index.within(x, y, r, |id| found.push(id));
+                found
+            })
+            .collect();
+        assert!(expected.iter().any(|r| !r.is_empty()));
+        assert_eq!(sorted(results), sorted(expected));
+
+        KDBush::create(vec![], 8).within_batch(&circles, |_, _| panic!());
+        index.within_batch(&[], |_, _| panic!());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_batch_parallel() {
+        let index = index();
+        let boxes = boxes();
+        let mut expected = vec![Vec::new(); boxes.len()];
+        index.range_batch(&boxes, |q, id| expected[q].push(id));
+        assert_eq!(sorted(index.range_batch_parallel(&boxes)), sorted(expected));
+
+        let circles = circles();
+        let mut expected = vec![Vec::new(); circles.len()];
+        index.within_batch(&circles, |q, id| expected[q].push(id));
+        assert_eq!(
+            sorted(index.within_batch_parallel(&circles)),
+            sorted(expected)
+        );
+        assert!(index.within_batch_parallel(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_batch_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids = Vec::new();
+            index.range_batch(&[[-1.0, -1.0, 5.0, 5.0]], |_, id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+            let mut ids = Vec::new();
+            index.within_batch(&[(0.0, 0.0, 5.0)], |_, id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
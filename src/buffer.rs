@@ -0,0 +1,663 @@
+//! Flat buffer format of the JavaScript kdbush (v4)
+//!
+//! Layout, all values little endian:
+//!
+//! * Header (8 bytes): magic `0xdb`, format version (high nibble) and coordinate type
+//!   (low nibble), node size (`u16`), number of items (`u32`)
+//! * Ids: `u16` if there are less than 65536 items, `u32` otherwise
+//! * Padding to a multiple of 8 bytes
+//! * Coordinates: interleaved `x`, `y` pairs
+//! * Optional CRS trailer (8 bytes): tag `EPSG` and EPSG code (`u32`), ignored by the
+//!   JavaScript kdbush, which reads no further than the coordinates
+
+use crate::kdbush::{KDBush, TIndex, TNumber};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::error::Error;
+use core::fmt;
+
+pub(crate) const MAGIC: u8 = 0xdb;
+pub(crate) const VERSION: u8 = 1;
+pub(crate) const HEADER_SIZE: usize = 8;
+const CRS_TAG: [u8; 4] = *b"EPSG";
+const CRS_TRAILER_SIZE: usize = 8;
+
+/// Coordinate type of a flat buffer, matching the JavaScript typed arrays
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoordType {
+    Int8,
+    Uint8,
+    Uint8Clamped,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl CoordType {
+    const ALL: [CoordType; 9] = [
+        CoordType::Int8,
+        CoordType::Uint8,
+        CoordType::Uint8Clamped,
+        CoordType::Int16,
+        CoordType::Uint16,
+        CoordType::Int32,
+        CoordType::Uint32,
+        CoordType::Float32,
+        CoordType::Float64,
+    ];
+
+    /// Size of one coordinate in bytes
+    pub fn size(self) -> usize {
+        match self {
+            CoordType::Int8 | CoordType::Uint8 | CoordType::Uint8Clamped => 1,
+            CoordType::Int16 | CoordType::Uint16 => 2,
+            CoordType::Int32 | CoordType::Uint32 | CoordType::Float32 => 4,
+            CoordType::Float64 => 8,
+        }
+    }
+
+    pub(crate) fn from_index(index: u8) -> Option<CoordType> {
+        CoordType::ALL.get(index as usize).cloned()
+    }
+
+    fn index(self) -> u8 {
+        CoordType::ALL.iter().position(|&t| t == self).unwrap() as u8
+    }
+
+    /// Reads a little endian coordinate of this type
+    pub(crate) fn read(self, b: &[u8]) -> TNumber {
+        match self {
+            CoordType::Int8 => f64::from(b[0] as i8),
+            CoordType::Uint8 | CoordType::Uint8Clamped => f64::from(b[0]),
+            CoordType::Int16 => f64::from(i16::from_le_bytes([b[0], b[1]])),
+            CoordType::Uint16 => f64::from(u16::from_le_bytes([b[0], b[1]])),
+            CoordType::Int32 => f64::from(i32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            CoordType::Uint32 => f64::from(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            CoordType::Float32 => f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            CoordType::Float64 => {
+                f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+            }
+        }
+    }
+}
+
+/// Properties of a verified flat buffer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BufferInfo {
+    pub num_items: usize,
+    pub node_size: u16,
+    pub coord_type: CoordType,
+    /// Size of one id in bytes
+    pub id_size: usize,
+    /// Byte offset of the ids
+    pub ids_offset: usize,
+    /// Byte offset of the coordinates
+    pub coords_offset: usize,
+    /// EPSG code of the coordinate reference system, stored in the optional trailer
+    pub crs: Option<u32>,
+}
+
+impl BufferInfo {
+    /// Buffer layout for the given number of items and coordinate type
+    pub(crate) fn layout(num_items: usize, node_size: u16, coord_type: CoordType) -> BufferInfo {
+        let id_size = if num_items < 65536 { 2 } else { 4 };
+        let ids_size = num_items * id_size;
+        BufferInfo {
+            num_items,
+            node_size,
+            coord_type,
+            id_size,
+            ids_offset: HEADER_SIZE,
+            coords_offset: HEADER_SIZE + ids_size + (8 - ids_size % 8) % 8,
+            crs: None,
+        }
+    }
+
+    /// Total size of the buffer in bytes, including the CRS trailer
+    pub fn byte_size(&self) -> usize {
+        let trailer = if self.crs.is_some() {
+            CRS_TRAILER_SIZE
+        } else {
+            0
+        };
+        self.coords_end() + trailer
+    }
+
+    /// Byte offset of the end of the coordinates
+    fn coords_end(&self) -> usize {
+        self.coords_offset + self.num_items * 2 * self.coord_type.size()
+    }
+}
+
+/// Reasons for rejecting a flat buffer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// Buffer is smaller than the header
+    TooShort,
+    /// First byte is not the kdbush magic number
+    BadMagic(u8),
+    UnsupportedVersion(u8),
+    UnknownCoordType(u8),
+    NodeSizeZero,
+    /// Node size doesn't fit the node size of [`KDBush`]
+    NodeSizeTooLarge(u16),
+    /// Buffer size doesn't match the size given by the header
+    LengthMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// Buffer start is not aligned for reading the coordinates in place
+    Misaligned {
+        required: usize,
+    },
+    /// Id is not smaller than the number of items
+    IdOutOfRange(usize),
+    /// Id occurs more than once
+    DuplicateId(usize),
+    /// Number of items doesn't fit the 32-bit item count of the header
+    TooManyItems(usize),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::TooShort => write!(f, "buffer is smaller than the kdbush header"),
+            VerifyError::BadMagic(magic) => {
+                write!(
+                    f,
+                    "data does not appear to be in kdbush format (magic {:#x})",
+                    magic
+                )
+            }
+            VerifyError::UnsupportedVersion(v) => {
+                write!(f, "got v{} data when expected v{}", v, VERSION)
+            }
+            VerifyError::UnknownCoordType(t) => write!(f, "unrecognized array type {}", t),
+            VerifyError::NodeSizeZero => write!(f, "node size is zero"),
+            VerifyError::NodeSizeTooLarge(size) => {
+                write!(f, "node size {} is larger than {}", size, u8::MAX)
+            }
+            VerifyError::LengthMismatch { expected, actual } => write!(
+                f,
+                "buffer has {} bytes, header requires {}",
+                actual, expected
+            ),
+            VerifyError::Misaligned { required } => {
+                write!(f, "buffer is not aligned to {} bytes", required)
+            }
+            VerifyError::IdOutOfRange(id) => write!(f, "id {} is out of range", id),
+            VerifyError::DuplicateId(id) => write!(f, "id {} is not unique", id),
+            VerifyError::TooManyItems(n) => {
+                write!(f, "{} items do not fit the 32-bit item count", n)
+            }
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+impl KDBush {
+    /// Checks whether a buffer is a valid flat kdbush index, as created by the JavaScript kdbush v4
+    ///
+    /// Verifies the header, the buffer size, the alignment of the buffer start for in-place
+    /// reading and that the ids are a permutation of `0..num_items`. The buffer may end with
+    /// the CRS trailer written by [`KDBush::to_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Buffer contents
+    pub fn verify_buffer(data: &[u8]) -> Result<BufferInfo, VerifyError> {
+        let info = KDBush::verify_layout(data)?;
+        let align = info.coord_type.size().max(info.id_size);
+        if data.as_ptr() as usize % align != 0 {
+            return Err(VerifyError::Misaligned { required: align });
+        }
+        Ok(info)
+    }
+
+    /// Creates an index from a flat buffer, as created by the JavaScript kdbush v4
+    ///
+    /// The buffer is verified like with [`KDBush::verify_buffer`], except for its
+    /// alignment, and copied. Coordinates of all types are converted to `f64`, the ids are
+    /// the item indices of the JavaScript index. The buffer must contain a finished index.
+    /// [`KDBushBuf`](crate::KDBushBuf) and [`KDBushRef`](crate::KDBushRef) query a buffer
+    /// in place instead.
+    /// The CRS is restored from the trailer, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Buffer contents
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let mut index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// index.set_crs(Some(3857));
+    /// let bytes = index.to_bytes()?;
+    /// let copy = KDBush::from_bytes(&bytes)?;
+    /// assert_eq!(copy.crs(), Some(3857));
+    /// copy.within(60.0, 30.0, 10.0, |id| assert_eq!(id, 2));
+    /// # Ok::<(), kdbush::VerifyError>(())
+    /// ```
+    pub fn from_bytes(data: &[u8]) -> Result<KDBush, VerifyError> {
+        let info = KDBush::verify_layout(data)?;
+        if info.node_size > u16::from(u8::MAX) {
+            return Err(VerifyError::NodeSizeTooLarge(info.node_size));
+        }
+        let mut kdbush = KDBush::new(info.num_items, info.node_size as u8);
+        let size = info.coord_type.size();
+        let coords = &data[info.coords_offset..];
+        for (i, id) in info.ids(data).enumerate() {
+            let x = info.coord_type.read(&coords[2 * i * size..]);
+            let y = info.coord_type.read(&coords[(2 * i + 1) * size..]);
+            kdbush.add_point(id, x, y);
+        }
+        kdbush.crs = info.crs;
+        Ok(kdbush)
+    }
+
+    /// Returns the index as flat buffer in the format of the JavaScript kdbush v4
+    ///
+    /// Coordinates are stored as `Float64`. The format requires the ids to be the item
+    /// indices, i.e. a permutation of `0..len`, otherwise an `IdOutOfRange` or `DuplicateId`
+    /// error is returned. Indexes with more than `u32::MAX` items are rejected with
+    /// `TooManyItems`.
+    ///
+    /// The CRS of a tagged index is stored in a trailer after the coordinates, which the
+    /// JavaScript kdbush ignores. Untagged indexes are written without trailer.
+    ///
+    /// The buffer is a copy, [`KDBush`] keeps its ids and coordinates in separate arrays.
+    /// [`KDBush::to_buf`] returns it as [`KDBushBuf`](crate::KDBushBuf), which is queried in
+    /// place and returns the buffer from `as_bytes` without serializing it again.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, VerifyError> {
+        let info = self.output_layout()?;
+        let mut bytes = Vec::with_capacity(info.byte_size());
+        self.encode(&info, |b| {
+            bytes.extend_from_slice(b);
+            Ok::<(), VerifyError>(())
+        })?;
+        Ok(bytes)
+    }
+
+    /// Writes the index as flat buffer in the format of the JavaScript kdbush v4
+    ///
+    /// Writes the same bytes as [`KDBush::to_bytes`], including the CRS trailer, without
+    /// building them in memory first.
+    /// The output can be opened in place with [`KDBushRef::from_slice`](crate::KDBushRef::from_slice),
+    /// e.g. from a memory-mapped file shared by several processes.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Output, preferably buffered
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, KDBushRef, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let mut bytes = Vec::new();
+    /// index.write_to(&mut bytes)?;
+    /// let view = KDBushRef::from_slice(&bytes).unwrap();
+    /// view.within(60.0, 30.0, 10.0, |id| assert_eq!(id, 2));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let info = self
+            .output_layout()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        self.encode(&info, |b| writer.write_all(b))
+    }
+
+    /// Layout of the flat buffer of this index, if the index can be written in the format
+    fn output_layout(&self) -> Result<BufferInfo, VerifyError> {
+        let num_items = self.ids.len();
+        check_num_items(num_items)?;
+        check_ids(self.ids.iter().cloned(), num_items)?;
+        let mut info = BufferInfo::layout(num_items, u16::from(self.node_size), CoordType::Float64);
+        info.crs = self.crs;
+        Ok(info)
+    }
+
+    /// Passes the bytes of the flat buffer with the given layout to `write` in order
+    fn encode<E, W>(&self, info: &BufferInfo, mut write: W) -> Result<(), E>
+    where
+        W: FnMut(&[u8]) -> Result<(), E>,
+    {
+        let mut header = [
+            MAGIC,
+            (VERSION << 4) | CoordType::Float64.index(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        header[2..4].copy_from_slice(&u16::from(self.node_size).to_le_bytes());
+        // Checked by output_layout
+        header[4..8].copy_from_slice(&(info.num_items as u32).to_le_bytes());
+        write(&header)?;
+        for &id in &self.ids {
+            if info.id_size == 2 {
+                write(&(id as u16).to_le_bytes())?;
+            } else {
+                write(&(id as u32).to_le_bytes())?;
+            }
+        }
+        let padding = info.coords_offset - info.ids_offset - info.num_items * info.id_size;
+        write(&[0; 8][..padding])?;
+        for p in &self.points {
+            write(&p[0].to_le_bytes())?;
+            write(&p[1].to_le_bytes())?;
+        }
+        if let Some(epsg) = info.crs {
+            write(&CRS_TAG)?;
+            write(&epsg.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Verifies a flat buffer, except for its alignment
+    pub(crate) fn verify_layout(data: &[u8]) -> Result<BufferInfo, VerifyError> {
+        let info = KDBush::verify_header(data)?;
+        check_ids(info.ids(data), info.num_items)?;
+        Ok(info)
+    }
+
+    /// Verifies the header and the length of a flat buffer and reads the CRS trailer, in
+    /// constant time
+    pub(crate) fn verify_header(data: &[u8]) -> Result<BufferInfo, VerifyError> {
+        if data.len() < HEADER_SIZE {
+            return Err(VerifyError::TooShort);
+        }
+        if data[0] != MAGIC {
+            return Err(VerifyError::BadMagic(data[0]));
+        }
+        let version = data[1] >> 4;
+        if version != VERSION {
+            return Err(VerifyError::UnsupportedVersion(version));
+        }
+        let coord_type = CoordType::from_index(data[1] & 0x0f)
+            .ok_or(VerifyError::UnknownCoordType(data[1] & 0x0f))?;
+        let node_size = u16::from_le_bytes([data[2], data[3]]);
+        if node_size == 0 {
+            return Err(VerifyError::NodeSizeZero);
+        }
+        let num_items = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+
+        let mut info = BufferInfo::layout(num_items, node_size, coord_type);
+        let end = info.coords_end();
+        match data.get(end..) {
+            Some([]) => {}
+            Some([t0, t1, t2, t3, e0, e1, e2, e3]) if [*t0, *t1, *t2, *t3] == CRS_TAG => {
+                info.crs = Some(u32::from_le_bytes([*e0, *e1, *e2, *e3]));
+            }
+            _ => {
+                return Err(VerifyError::LengthMismatch {
+                    expected: end,
+                    actual: data.len(),
+                })
+            }
+        }
+        Ok(info)
+    }
+}
+
+impl BufferInfo {
+    /// Ids stored in a buffer with this layout
+    pub(crate) fn ids<'a>(&self, data: &'a [u8]) -> impl Iterator<Item = TIndex> + 'a {
+        data[self.ids_offset..self.ids_offset + self.num_items * self.id_size]
+            .chunks_exact(self.id_size)
+            .map(|b| match *b {
+                [b0, b1] => u16::from_le_bytes([b0, b1]) as usize,
+                [b0, b1, b2, b3] => u32::from_le_bytes([b0, b1, b2, b3]) as usize,
+                _ => unreachable!(),
+            })
+    }
+}
+
+/// Checks that the number of items fits the header
+fn check_num_items(num_items: usize) -> Result<(), VerifyError> {
+    u32::try_from(num_items)
+        .map(|_| ())
+        .map_err(|_| VerifyError::TooManyItems(num_items))
+}
+
+/// Checks that the ids are a permutation of `0..num_items`
+pub(crate) fn check_ids<I: Iterator<Item = TIndex>>(
+    ids: I,
+    num_items: usize,
+) -> Result<(), VerifyError> {
+    let mut seen = vec![false; num_items];
+    for id in ids {
+        match seen.get_mut(id) {
+            None => return Err(VerifyError::IdOutOfRange(id)),
+            Some(true) => return Err(VerifyError::DuplicateId(id)),
+            Some(seen) => *seen = true,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    /// Buffer as created by `new KDBush(3)` in JavaScript, with 8-byte aligned start
+    fn js_buffer(ids: &[u16]) -> Vec<u64> {
+        let info = BufferInfo::layout(ids.len(), 64, CoordType::Float64);
+        let mut bytes = vec![MAGIC, (VERSION << 4) + 8, 64, 0];
+        bytes.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+        for id in ids {
+            bytes.extend_from_slice(&id.to_le_bytes());
+        }
+        bytes.resize(info.coords_offset, 0);
+        for i in 0..ids.len() * 2 {
+            bytes.extend_from_slice(&(i as f64).to_le_bytes());
+        }
+        bytes
+            .chunks(8)
+            .map(|c| u64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+            .collect()
+    }
+
+    fn as_bytes(words: &[u64]) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 8) }
+    }
+
+    #[test]
+    fn test_verify_buffer() {
+        let buffer = js_buffer(&[2, 0, 1]);
+        let info = KDBush::verify_buffer(as_bytes(&buffer)).unwrap();
+        assert_eq!(info.num_items, 3);
+        assert_eq!(info.node_size, 64);
+        assert_eq!(info.coord_type, CoordType::Float64);
+        assert_eq!(info.coords_offset, 16);
+        assert_eq!(info.byte_size(), 64);
+    }
+
+    #[test]
+    fn test_verify_buffer_errors() {
+        let buffer = js_buffer(&[2, 0, 1]);
+        let bytes = as_bytes(&buffer);
+        assert_eq!(
+            KDBush::verify_buffer(&bytes[..4]),
+            Err(VerifyError::TooShort)
+        );
+        assert_eq!(
+            KDBush::verify_buffer(&bytes[..60]),
+            Err(VerifyError::LengthMismatch {
+                expected: 64,
+                actual: 60
+            })
+        );
+        assert_eq!(
+            KDBush::verify_buffer(&bytes[1..]),
+            Err(VerifyError::BadMagic(0x18))
+        );
+
+        let mut invalid = bytes.to_vec();
+        invalid[1] = (2 << 4) + 8;
+        assert_eq!(
+            KDBush::verify_buffer(&invalid),
+            Err(VerifyError::UnsupportedVersion(2))
+        );
+        invalid[1] = (VERSION << 4) + 9;
+        assert_eq!(
+            KDBush::verify_buffer(&invalid),
+            Err(VerifyError::UnknownCoordType(9))
+        );
+
+        let buffer = js_buffer(&[2, 0, 2]);
+        assert_eq!(
+            KDBush::verify_buffer(as_bytes(&buffer)),
+            Err(VerifyError::DuplicateId(2))
+        );
+        let buffer = js_buffer(&[3, 0, 1]);
+        assert_eq!(
+            KDBush::verify_buffer(as_bytes(&buffer)),
+            Err(VerifyError::IdOutOfRange(3))
+        );
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let points: Vec<(f64, f64)> = (0..70000)
+            .map(|i| ((i * 37 % 1001) as f64, (i * 53 % 997) as f64 + 0.5))
+            .collect();
+        for &n in &[0, 3, 1000, 70000] {
+            let index = KDBush::create(points[..n].to_vec(), 16);
+            let bytes = index.to_bytes().unwrap();
+            assert_eq!(bytes[1], (VERSION << 4) + 8);
+            let info = KDBush::verify_layout(&bytes).unwrap();
+            assert_eq!(info.id_size, if n < 65536 { 2 } else { 4 });
+            assert_eq!(info.byte_size(), bytes.len());
+            let copy = KDBush::from_bytes(&bytes).unwrap();
+            assert_eq!(copy.node_size, 16);
+            assert_eq!(copy.ids, index.ids);
+            assert_eq!(copy.points, index.points);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_to() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let mut index = KDBush::create(points, 8);
+        let mut written = Vec::new();
+        index.write_to(&mut written).unwrap();
+        assert_eq!(written, index.to_bytes().unwrap());
+        index.set_crs(Some(4326));
+        let mut written = Vec::new();
+        index.write_to(&mut written).unwrap();
+        assert_eq!(written, index.to_bytes().unwrap());
+
+        let mut invalid = KDBush::new(1, 8);
+        invalid.add_point(3, 1.0, 1.0);
+        invalid.build_index();
+        let err = invalid.write_to(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_crs_trailer() {
+        let mut index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], 8);
+        let untagged = index.to_bytes().unwrap();
+        index.set_crs(Some(3857));
+        let bytes = index.to_bytes().unwrap();
+        assert_eq!(bytes.len(), untagged.len() + 8);
+        assert_eq!(bytes[..untagged.len()], untagged[..]);
+        assert_eq!(&bytes[untagged.len()..], b"EPSG\x11\x0f\0\0");
+
+        let info = KDBush::verify_layout(&bytes).unwrap();
+        assert_eq!(info.crs, Some(3857));
+        assert_eq!(info.byte_size(), bytes.len());
+        let copy = KDBush::from_bytes(&bytes).unwrap();
+        assert_eq!(copy.crs(), Some(3857));
+        assert_eq!(copy.points, index.points);
+        assert_eq!(KDBush::from_bytes(&untagged).unwrap().crs(), None);
+
+        // Trailing bytes other than a CRS trailer are rejected
+        let mut other = untagged.clone();
+        other.extend_from_slice(b"ABCD\x11\x0f\0\0");
+        let expected = VerifyError::LengthMismatch {
+            expected: untagged.len(),
+            actual: untagged.len() + 8,
+        };
+        assert_eq!(KDBush::verify_layout(&other), Err(expected));
+        assert!(KDBush::verify_layout(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_from_js_bytes() {
+        // Int16 coordinates, as created by `new KDBush(2, 8, Int16Array)`
+        let mut bytes = vec![MAGIC, (VERSION << 4) + 3, 8, 0, 2, 0, 0, 0];
+        bytes.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        for v in &[-5i16, 7, 300, -2] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        let index = KDBush::from_bytes(&bytes).unwrap();
+        assert_eq!(index.ids, vec![1, 0]);
+        assert_eq!(index.points, vec![[-5.0, 7.0], [300.0, -2.0]]);
+
+        bytes[2] = 0;
+        bytes[3] = 1;
+        assert_eq!(
+            KDBush::from_bytes(&bytes).err(),
+            Some(VerifyError::NodeSizeTooLarge(256))
+        );
+    }
+
+    #[test]
+    fn test_to_bytes_ids() {
+        let mut index = KDBush::new(2, 8);
+        index.add_point(0, 1.0, 1.0);
+        index.add_point(5, 2.0, 2.0);
+        index.build_index();
+        assert_eq!(index.to_bytes(), Err(VerifyError::IdOutOfRange(5)));
+
+        assert_eq!(check_num_items(u32::MAX as usize), Ok(()));
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(
+            check_num_items(u32::MAX as usize + 1),
+            Err(VerifyError::TooManyItems(u32::MAX as usize + 1))
+        );
+    }
+
+    #[test]
+    fn test_verify_buffer_alignment() {
+        let buffer = js_buffer(&[2, 0, 1]);
+        let mut shifted = vec![0u64; buffer.len() + 1];
+        let bytes = as_bytes(&buffer);
+        let shifted_bytes = unsafe {
+            core::slice::from_raw_parts_mut(shifted.as_mut_ptr() as *mut u8, shifted.len() * 8)
+        };
+        shifted_bytes[4..4 + bytes.len()].copy_from_slice(bytes);
+        assert_eq!(
+            KDBush::verify_buffer(&shifted_bytes[4..4 + bytes.len()]),
+            Err(VerifyError::Misaligned { required: 8 })
+        );
+    }
+
+    #[test]
+    fn test_bytes_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let index = KDBush::create(points, node_size);
+            let bytes = index.to_bytes().unwrap();
+            let copy = KDBush::from_bytes(&bytes).unwrap();
+            assert_eq!(copy.ids, index.ids);
+            assert_eq!(copy.points, index.points);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::kdbush::{KDBush, PointReader, TIndex, TNumber, DEFAULT_NODE_SIZE};
+use crate::validate::{BuildError, InvalidPolicy};
+
+/// Builder for an index with construction options
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{InvalidPolicy, KDBushBuilder};
+///
+/// let mut builder = KDBushBuilder::new()
+///     .node_size(16)
+///     .validate(InvalidPolicy::Skip)
+///     .bboxes(true);
+/// builder.add_point(0, 54.0, 1.0);
+/// builder.add_point(1, f64::NAN, 21.0);
+/// builder.add_points(vec![(65.0, 35.0)]);
+/// let index = builder.finish()?;
+/// let mut result = Vec::new();
+/// index.range(0.0, 0.0, 100.0, 100.0, |id| result.push(id));
+/// assert_eq!(result.len(), 2);
+/// # Ok::<(), kdbush::BuildError>(())
+/// ```
+pub struct KDBushBuilder {
+    kdbush: KDBush,
+    invalid: Option<InvalidPolicy>,
+    error: Option<BuildError>,
+    bboxes: bool,
+    lookup: bool,
+    parallel: bool,
+    stable: bool,
+}
+
+impl Default for KDBushBuilder {
+    fn default() -> KDBushBuilder {
+        KDBushBuilder::new()
+    }
+}
+
+impl KDBushBuilder {
+    /// Creates a builder with default options and no points
+    pub fn new() -> KDBushBuilder {
+        KDBushBuilder {
+            kdbush: KDBush::new(0, DEFAULT_NODE_SIZE),
+            invalid: None,
+            error: None,
+            bboxes: false,
+            lookup: false,
+            parallel: cfg!(feature = "parallel"),
+            stable: false,
+        }
+    }
+
+    /// Sets the size of the KD-tree node, 64 by default. A node size of 0 is treated as 1.
+    pub fn node_size(mut self, node_size: u8) -> KDBushBuilder {
+        self.kdbush.node_size = node_size.max(1);
+        self
+    }
+
+    /// Reserves capacity for the given number of points
+    pub fn capacity(mut self, size_hint: usize) -> KDBushBuilder {
+        self.kdbush.ids.reserve(size_hint);
+        self.kdbush.points.reserve(size_hint);
+        self
+    }
+
+    /// Checks for NaN or infinite coordinates, which aren't checked by default
+    pub fn validate(mut self, policy: InvalidPolicy) -> KDBushBuilder {
+        self.invalid = Some(policy);
+        self
+    }
+
+    /// Records the bounding box of each node, see [`KDBush::create_with_bboxes`]
+    pub fn bboxes(mut self, enabled: bool) -> KDBushBuilder {
+        self.bboxes = enabled;
+        self
+    }
+
+    /// Builds the coordinate lookup, see [`KDBush::create_with_lookup`]
+    pub fn lookup(mut self, enabled: bool) -> KDBushBuilder {
+        self.lookup = enabled;
+        self
+    }
+
+    /// Sorts large subtrees on multiple threads, enabled by default with the `parallel` feature
+    #[cfg(feature = "parallel")]
+    pub fn parallel(mut self, enabled: bool) -> KDBushBuilder {
+        self.parallel = enabled;
+        self
+    }
+
+    /// Orders points with equal coordinates by id, so the index layout and the order of
+    /// query results are reproducible on all platforms
+    ///
+    /// Construction is slower with stable ordering.
+    pub fn stable(mut self, enabled: bool) -> KDBushBuilder {
+        self.stable = enabled;
+        self
+    }
+
+    /// Tags the index with the EPSG code of its coordinate reference system
+    pub fn crs(mut self, epsg: u32) -> KDBushBuilder {
+        self.kdbush.crs = Some(epsg);
+        self
+    }
+
+    /// Adds a point
+    pub fn add_point(&mut self, id: TIndex, x: TNumber, y: TNumber) {
+        if x.is_finite() && y.is_finite() {
+            self.kdbush.add_point(id, x, y);
+            return;
+        }
+        match self.invalid {
+            None => self.kdbush.add_point(id, x, y),
+            Some(InvalidPolicy::Skip) => {}
+            Some(InvalidPolicy::Reject) => {
+                self.error.get_or_insert(BuildError::NonFinite { id, x, y });
+            }
+        }
+    }
+
+    /// Adds all points of a reader
+    pub fn add_points<R: PointReader>(&mut self, points: R) {
+        self.kdbush.ids.reserve(points.size_hint());
+        self.kdbush.points.reserve(points.size_hint());
+        points.visit_all(|id, x, y| self.add_point(id, x, y));
+    }
+
+    /// Builds the index
+    ///
+    /// Fails with the first invalid point if the points are validated with
+    /// [`InvalidPolicy::Reject`].
+    pub fn finish(self) -> Result<KDBush, BuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut kdbush = self.kdbush;
+        kdbush.build_index_with(self.parallel, self.stable);
+        if self.bboxes {
+            kdbush.build_bboxes();
+        }
+        if self.lookup {
+            kdbush.build_lookup();
+        }
+        Ok(kdbush)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let mut builder = KDBushBuilder::new()
+            .node_size(4)
+            .capacity(500)
+            .bboxes(true)
+            .lookup(true)
+            .crs(3857);
+        builder.add_points(points.clone());
+        let index = builder.finish().unwrap();
+        assert_eq!(index.node_size, 4);
+        assert!(index.bboxes.is_some());
+        assert_eq!(index.crs(), Some(3857));
+        assert_eq!(index.ids_at(points[7].0, points[7].1), vec![7]);
+
+        let reference = KDBush::create(points, 4);
+        assert_eq!(index.ids, reference.ids);
+        assert_eq!(index.points, reference.points);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let mut builder = KDBushBuilder::new().validate(InvalidPolicy::Reject);
+        builder.add_point(3, 1.0, 1.0);
+        builder.add_point(4, 1.0, f64::NAN);
+        builder.add_point(5, f64::NAN, 1.0);
+        assert!(matches!(
+            builder.finish(),
+            Err(BuildError::NonFinite { id: 4, .. })
+        ));
+
+        let mut builder = KDBushBuilder::new();
+        builder.add_point(4, 1.0, f64::NAN);
+        assert_eq!(builder.finish().unwrap().ids, vec![4]);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::kdbush::{KDBush, TNumber};
+use crate::math;
+use alloc::vec::Vec;
+
+/// Voting scheme for [`KDBush::classify`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vote {
+    /// Each neighbor has one vote
+    Majority,
+    /// Each neighbor votes with the inverse of its distance
+    DistanceWeighted,
+}
+
+impl KDBush {
+    /// Returns the class with most votes among the `k` items nearest to the query point
+    ///
+    /// Ties are resolved in favor of the class of the nearer neighbor. With distance
+    /// weighting, items located exactly at the query point outvote all other items.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Number of neighbors
+    /// * `labels` - Class label of each item, indexed by item id
+    /// * `vote` - Voting scheme
+    pub fn classify<L>(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        k: usize,
+        labels: &[L],
+        vote: Vote,
+    ) -> Option<L>
+    where
+        L: Clone + PartialEq,
+    {
+        let neighbors = self.knn_idx(qx, qy, k);
+        let exact = neighbors.iter().take_while(|n| n.1 == 0.0).count();
+        let voters = if vote == Vote::DistanceWeighted && exact > 0 {
+            &neighbors[..exact]
+        } else {
+            &neighbors[..]
+        };
+
+        // Accumulated votes per class, ordered by the nearest neighbor of each class
+        let mut votes: Vec<(&L, TNumber)> = Vec::new();
+        for &(i, d2) in voters {
+            let weight = match vote {
+                Vote::DistanceWeighted if exact == 0 => 1.0 / math::sqrt(d2),
+                _ => 1.0,
+            };
+            let label = &labels[self.ids[i]];
+            match votes.iter_mut().find(|v| v.0 == label) {
+                Some(v) => v.1 += weight,
+                None => votes.push((label, weight)),
+            }
+        }
+        let mut winner: Option<&(&L, TNumber)> = None;
+        for v in &votes {
+            if winner.map_or(true, |w| v.1 > w.1) {
+                winner = Some(v);
+            }
+        }
+        winner.map(|w| w.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_classify() {
+        let points = vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (5.0, 5.0), (5.5, 5.0)];
+        let labels = vec!["a", "b", "b", "c", "c"];
+        let index = KDBush::create(points, 2);
+        assert_eq!(
+            index.classify(0.1, 0.1, 3, &labels, Vote::Majority),
+            Some("b")
+        );
+        assert_eq!(
+            index.classify(0.1, 0.1, 3, &labels, Vote::DistanceWeighted),
+            Some("a")
+        );
+        assert_eq!(
+            index.classify(4.0, 4.0, 2, &labels, Vote::Majority),
+            Some("c")
+        );
+        assert_eq!(
+            index.classify(1.0, 0.0, 5, &labels, Vote::DistanceWeighted),
+            Some("b")
+        );
+        let empty = KDBush::create(vec![], 2);
+        assert_eq!(empty.classify(0.0, 0.0, 3, &labels, Vote::Majority), None);
+    }
+
+    #[test]
+    fn test_classify_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let index = KDBush::create(points, node_size);
+            let labels = vec!["a"; n];
+            let label = index.classify(0.0, 0.0, 3, &labels, Vote::Majority);
+            assert_eq!(label.is_some(), n > 0);
+        }
+    }
+}
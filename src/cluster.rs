@@ -0,0 +1,546 @@
+//! Point clustering for web maps, like the JavaScript supercluster
+//!
+//! Points are `(longitude, latitude)` in degrees. They are projected to Web Mercator and
+//! greedily clustered at each zoom level, from the highest to the lowest, so each level is
+//! a KD-tree of the clusters of the level above.
+
+use crate::kdbush::{KDBush, Point, PointReader, TIndex, TNumber};
+use std::f64::consts::PI;
+
+/// Settings of a [`Supercluster`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClusterOptions {
+    /// Lowest zoom level with clusters
+    pub min_zoom: u8,
+    /// Highest zoom level with clusters, at most 30
+    pub max_zoom: u8,
+    /// Minimal number of points forming a cluster
+    pub min_points: usize,
+    /// Cluster radius in pixels
+    pub radius: TNumber,
+    /// Tile extent in pixels, the radius is relative to it
+    pub extent: TNumber,
+    /// Size of the KD-tree node of each zoom level
+    pub node_size: u8,
+}
+
+impl Default for ClusterOptions {
+    fn default() -> ClusterOptions {
+        ClusterOptions {
+            min_zoom: 0,
+            max_zoom: 16,
+            min_points: 2,
+            radius: 40.0,
+            extent: 512.0,
+            node_size: 64,
+        }
+    }
+}
+
+/// Cluster or single point at a zoom level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClusterItem {
+    /// Input point which is not part of a cluster at this zoom level
+    Point {
+        id: TIndex,
+        lng: TNumber,
+        lat: TNumber,
+    },
+    /// Cluster at the weighted center of its points
+    Cluster {
+        /// Id for [`Supercluster::get_children`] and related methods
+        id: usize,
+        lng: TNumber,
+        lat: TNumber,
+        /// Number of input points in the cluster
+        count: usize,
+    },
+}
+
+/// Cluster or point of a zoom level
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    /// Projected coordinates
+    x: TNumber,
+    y: TNumber,
+    /// Last zoom level this node was processed at, `u8::MAX` if not yet processed
+    zoom: u8,
+    /// Position of the input point for single points, cluster id otherwise
+    id: usize,
+    /// Id of the cluster containing this node at the next lower zoom level
+    parent: Option<usize>,
+    count: usize,
+}
+
+/// Clusters and single points of a zoom level, indexed by their position
+struct Level {
+    tree: KDBush,
+    nodes: Vec<Node>,
+}
+
+impl Level {
+    fn new(nodes: Vec<Node>, node_size: u8) -> Level {
+        let mut tree = KDBush::new(nodes.len(), node_size);
+        for (i, node) in nodes.iter().enumerate() {
+            tree.add_point(i, node.x, node.y);
+        }
+        tree.build_index();
+        Level { tree, nodes }
+    }
+}
+
+/// Hierarchy of point clusters for all zoom levels of a web map
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{ClusterItem, ClusterOptions, Supercluster};
+///
+/// let points = vec![(13.40, 52.52), (13.41, 52.52), (2.35, 48.86)];
+/// let index = Supercluster::create(points, ClusterOptions::default());
+/// let clusters = index.get_clusters([-180.0, -85.0, 180.0, 85.0], 4.0);
+/// assert_eq!(clusters.len(), 2);
+/// for item in clusters {
+///     if let ClusterItem::Cluster { id, count, .. } = item {
+///         assert_eq!(count, 2);
+///         assert!(index.get_cluster_expansion_zoom(id).unwrap() > 4);
+///     }
+/// }
+/// ```
+pub struct Supercluster {
+    options: ClusterOptions,
+    /// Input points as `(id, [lng, lat])`
+    points: Vec<(TIndex, Point)>,
+    /// Levels from `min_zoom` to `max_zoom + 1`, the highest one contains all points
+    levels: Vec<Level>,
+}
+
+impl Supercluster {
+    /// Clusters the given points at all zoom levels
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points reader with `(longitude, latitude)` in degrees
+    /// * `options` - Cluster settings
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_zoom` is larger than 30 or smaller than `min_zoom`.
+    pub fn create<R: PointReader>(points: R, options: ClusterOptions) -> Supercluster {
+        assert!(options.max_zoom <= 30, "max_zoom is larger than 30");
+        assert!(
+            options.min_zoom <= options.max_zoom,
+            "min_zoom is larger than max_zoom"
+        );
+
+        let mut input = Vec::with_capacity(points.size_hint());
+        points.visit_all(|id, lng, lat| input.push((id, [lng, lat])));
+        let nodes: Vec<Node> = input
+            .iter()
+            .enumerate()
+            .map(|(i, &(_, p))| Node {
+                x: lng_x(p[0]),
+                y: lat_y(p[1]),
+                zoom: u8::MAX,
+                id: i,
+                parent: None,
+                count: 1,
+            })
+            .collect();
+
+        let mut levels = vec![Level::new(nodes, options.node_size)];
+        for zoom in (options.min_zoom..=options.max_zoom).rev() {
+            let previous = levels.last_mut().unwrap();
+            let nodes = cluster(previous, zoom, &options);
+            levels.push(Level::new(nodes, options.node_size));
+        }
+        levels.reverse();
+        Supercluster {
+            options,
+            points: input,
+            levels,
+        }
+    }
+
+    /// Returns the clusters and single points within a bounding box at a zoom level
+    ///
+    /// Bounding boxes crossing the antimeridian are supported, e.g. `[170, -10, -170, 10]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bbox` - Bounding box `[west, south, east, north]` in degrees
+    /// * `zoom` - Zoom level, rounded down to an integer and limited to the clustered levels,
+    ///   NaN is treated as `min_zoom`
+    pub fn get_clusters(&self, bbox: [TNumber; 4], zoom: TNumber) -> Vec<ClusterItem> {
+        let mut min_lng = (bbox[0] + 180.0).rem_euclid(360.0) - 180.0;
+        let min_lat = bbox[1].clamp(-90.0, 90.0);
+        let mut max_lng = if bbox[2] == 180.0 {
+            180.0
+        } else {
+            (bbox[2] + 180.0).rem_euclid(360.0) - 180.0
+        };
+        let max_lat = bbox[3].clamp(-90.0, 90.0);
+
+        if bbox[2] - bbox[0] >= 360.0 {
+            min_lng = -180.0;
+            max_lng = 180.0;
+        } else if min_lng > max_lng {
+            let mut items = self.get_clusters([min_lng, min_lat, 180.0, max_lat], zoom);
+            items.extend(self.get_clusters([-180.0, min_lat, max_lng, max_lat], zoom));
+            return items;
+        }
+
+        let level = self.level(self.limit_zoom(zoom));
+        let mut items = Vec::new();
+        level.tree.range(
+            lng_x(min_lng),
+            lat_y(max_lat),
+            lng_x(max_lng),
+            lat_y(min_lat),
+            |i| items.push(self.item(&level.nodes[i])),
+        );
+        items
+    }
+
+    /// Returns the clusters and points a cluster splits into at the next zoom level
+    ///
+    /// Returns `None` for an unknown cluster id.
+    pub fn get_children(&self, cluster_id: usize) -> Option<Vec<ClusterItem>> {
+        let (origin, zoom) = (cluster_id >> 5, (cluster_id & 31) as u8);
+        if zoom <= self.options.min_zoom || zoom > self.options.max_zoom + 1 {
+            return None;
+        }
+        let level = self.level(zoom);
+        let node = level.nodes.get(origin)?;
+        let r = self.options.radius / (self.options.extent * 2f64.powi(i32::from(zoom) - 1));
+        let mut children = Vec::new();
+        level.tree.within(node.x, node.y, r, |i| {
+            let child = &level.nodes[i];
+            if child.parent == Some(cluster_id) {
+                children.push(self.item(child));
+            }
+        });
+        if children.is_empty() {
+            None
+        } else {
+            Some(children)
+        }
+    }
+
+    /// Returns the ids of all input points of a cluster
+    ///
+    /// Returns `None` for an unknown cluster id.
+    pub fn get_leaves(&self, cluster_id: usize) -> Option<Vec<TIndex>> {
+        let mut leaves = Vec::new();
+        let mut stack = vec![cluster_id];
+        while let Some(id) = stack.pop() {
+            for child in self.get_children(id)? {
+                match child {
+                    ClusterItem::Point { id, .. } => leaves.push(id),
+                    ClusterItem::Cluster { id, .. } => stack.push(id),
+                }
+            }
+        }
+        Some(leaves)
+    }
+
+    /// Returns the zoom level at which a cluster splits into several children
+    ///
+    /// Returns `None` for an unknown cluster id.
+    pub fn get_cluster_expansion_zoom(&self, cluster_id: usize) -> Option<u8> {
+        let mut cluster_id = cluster_id;
+        let mut zoom = ((cluster_id & 31) as u8).checked_sub(1)?;
+        loop {
+            let children = self.get_children(cluster_id)?;
+            zoom += 1;
+            match children[..] {
+                [ClusterItem::Cluster { id, .. }] if zoom <= self.options.max_zoom => {
+                    cluster_id = id;
+                }
+                _ => return Some(zoom),
+            }
+        }
+    }
+
+    /// Zoom level of the nearest level, `min_zoom` for NaN
+    fn limit_zoom(&self, zoom: TNumber) -> u8 {
+        if zoom.is_nan() {
+            return self.options.min_zoom;
+        }
+        let max = TNumber::from(self.options.max_zoom) + 1.0;
+        zoom.floor()
+            .clamp(TNumber::from(self.options.min_zoom), max) as u8
+    }
+
+    fn level(&self, zoom: u8) -> &Level {
+        &self.levels[usize::from(zoom - self.options.min_zoom)]
+    }
+
+    fn item(&self, node: &Node) -> ClusterItem {
+        if node.count > 1 {
+            ClusterItem::Cluster {
+                id: node.id,
+                lng: x_lng(node.x),
+                lat: y_lat(node.y),
+                count: node.count,
+            }
+        } else {
+            let (id, p) = self.points[node.id];
+            ClusterItem::Point {
+                id,
+                lng: p[0],
+                lat: p[1],
+            }
+        }
+    }
+}
+
+/// Clusters the nodes of the level above `zoom`, returning the nodes of level `zoom`
+///
+/// Cluster ids encode the position of the cluster's first node and the zoom level of that
+/// node, so the children of a cluster can be found again.
+fn cluster(above: &mut Level, zoom: u8, options: &ClusterOptions) -> Vec<Node> {
+    let r = options.radius / (options.extent * 2f64.powi(i32::from(zoom)));
+    let mut next = Vec::new();
+    let mut neighbors = Vec::new();
+    for i in 0..above.nodes.len() {
+        if above.nodes[i].zoom <= zoom {
+            continue;
+        }
+        above.nodes[i].zoom = zoom;
+        let Node { x, y, count, .. } = above.nodes[i];
+        neighbors.clear();
+        above.tree.within(x, y, r, |j| neighbors.push(j));
+        let total: usize = count
+            + neighbors
+                .iter()
+                .filter(|&&j| above.nodes[j].zoom > zoom)
+                .map(|&j| above.nodes[j].count)
+                .sum::<usize>();
+
+        if total > count && total >= options.min_points {
+            let id = (i << 5) + usize::from(zoom) + 1;
+            let (mut wx, mut wy) = (x * count as TNumber, y * count as TNumber);
+            for &j in &neighbors {
+                let neighbor = &mut above.nodes[j];
+                if neighbor.zoom <= zoom {
+                    continue;
+                }
+                neighbor.zoom = zoom;
+                neighbor.parent = Some(id);
+                wx += neighbor.x * neighbor.count as TNumber;
+                wy += neighbor.y * neighbor.count as TNumber;
+            }
+            above.nodes[i].parent = Some(id);
+            next.push(Node {
+                x: wx / total as TNumber,
+                y: wy / total as TNumber,
+                zoom: u8::MAX,
+                id,
+                parent: None,
+                count: total,
+            });
+        } else {
+            next.push(unprocessed(&above.nodes[i]));
+            if total > 1 {
+                // Too few points for a cluster, keep the neighbors as they are
+                for &j in &neighbors {
+                    let neighbor = &mut above.nodes[j];
+                    if neighbor.zoom <= zoom {
+                        continue;
+                    }
+                    neighbor.zoom = zoom;
+                    next.push(unprocessed(neighbor));
+                }
+            }
+        }
+    }
+    next
+}
+
+/// Copy of a node for the next lower zoom level
+fn unprocessed(node: &Node) -> Node {
+    Node {
+        zoom: u8::MAX,
+        parent: None,
+        ..*node
+    }
+}
+
+/// Web Mercator x in `[0, 1]` of a longitude
+fn lng_x(lng: TNumber) -> TNumber {
+    lng / 360.0 + 0.5
+}
+
+/// Web Mercator y in `[0, 1]` of a latitude, from north to south
+fn lat_y(lat: TNumber) -> TNumber {
+    let sin = (lat * PI / 180.0).sin();
+    let y = 0.5 - 0.25 * ((1.0 + sin) / (1.0 - sin)).ln() / PI;
+    y.clamp(0.0, 1.0)
+}
+
+fn x_lng(x: TNumber) -> TNumber {
+    (x - 0.5) * 360.0
+}
+
+fn y_lat(y: TNumber) -> TNumber {
+    let y2 = (180.0 - y * 360.0) * PI / 180.0;
+    360.0 * y2.exp().atan() / PI - 90.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<(f64, f64)> {
+        (0..1000)
+            .map(|i| {
+                (
+                    (i * 7919 % 3600) as f64 / 10.0 - 180.0,
+                    (i * 104_729 % 1600) as f64 / 10.0 - 80.0,
+                )
+            })
+            .collect()
+    }
+
+    fn count(items: &[ClusterItem]) -> usize {
+        items
+            .iter()
+            .map(|item| match *item {
+                ClusterItem::Point { .. } => 1,
+                ClusterItem::Cluster { count, .. } => count,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_clusters() {
+        let points = points();
+        let index = Supercluster::create(points.clone(), ClusterOptions::default());
+        let world = [-180.0, -90.0, 180.0, 90.0];
+        let mut previous = 0;
+        for zoom in 0..=17 {
+            let items = index.get_clusters(world, zoom as f64);
+            assert_eq!(count(&items), points.len());
+            assert!(items.len() >= previous);
+            previous = items.len();
+        }
+        assert!(index.get_clusters(world, 0.0).len() < 100);
+        let top = index.get_clusters(world, 17.0);
+        assert_eq!(top.len(), points.len());
+        assert!(top
+            .iter()
+            .all(|item| matches!(item, ClusterItem::Point { .. })));
+    }
+
+    #[test]
+    fn test_children_and_leaves() {
+        let index = Supercluster::create(points(), ClusterOptions::default());
+        let world = [-180.0, -90.0, 180.0, 90.0];
+        for item in index.get_clusters(world, 2.0) {
+            if let ClusterItem::Cluster { id, count, .. } = item {
+                let children = index.get_children(id).unwrap();
+                assert_eq!(super::tests::count(&children), count);
+                let mut leaves = index.get_leaves(id).unwrap();
+                leaves.sort();
+                leaves.dedup();
+                assert_eq!(leaves.len(), count);
+
+                // No cluster of the expansion zoom contains all points of this one
+                let zoom = index.get_cluster_expansion_zoom(id).unwrap();
+                assert!(zoom > 2);
+                for split in index.get_clusters(world, zoom as f64) {
+                    if let ClusterItem::Cluster { id, .. } = split {
+                        let mut split_leaves = index.get_leaves(id).unwrap();
+                        split_leaves.sort();
+                        assert_ne!(split_leaves, leaves);
+                    }
+                }
+            }
+        }
+        assert_eq!(index.get_children(12345 << 5), None);
+        assert_eq!(index.get_cluster_expansion_zoom(12345 << 5), None);
+    }
+
+    #[test]
+    fn test_cluster_position() {
+        let points = vec![(10.0, 20.0), (10.001, 20.001), (-100.0, -40.0)];
+        let index = Supercluster::create(points, ClusterOptions::default());
+        let items = index.get_clusters([-180.0, -90.0, 180.0, 90.0], 5.0);
+        assert_eq!(items.len(), 2);
+        for item in items {
+            match item {
+                ClusterItem::Cluster {
+                    lng, lat, count, ..
+                } => {
+                    assert_eq!(count, 2);
+                    assert!((lng - 10.0005).abs() < 1e-6);
+                    assert!((lat - 20.0005).abs() < 1e-3);
+                }
+                ClusterItem::Point { id, lng, lat } => {
+                    assert_eq!((id, lng, lat), (2, -100.0, -40.0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_antimeridian() {
+        let points = vec![(179.5, 0.0), (-179.5, 0.0), (0.0, 0.0)];
+        let options = ClusterOptions {
+            max_zoom: 5,
+            ..ClusterOptions::default()
+        };
+        let index = Supercluster::create(points, options);
+        let items = index.get_clusters([179.0, -10.0, -179.0, 10.0], 6.0);
+        let mut ids: Vec<TIndex> = items
+            .iter()
+            .map(|item| match *item {
+                ClusterItem::Point { id, .. } => id,
+                ClusterItem::Cluster { .. } => unreachable!(),
+            })
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(
+            count(&index.get_clusters([-190.0, -90.0, 190.0, 90.0], 0.0)),
+            3
+        );
+    }
+
+    #[test]
+    fn test_min_points_and_empty() {
+        let points = vec![(10.0, 20.0), (10.001, 20.001)];
+        let options = ClusterOptions {
+            min_points: 3,
+            ..ClusterOptions::default()
+        };
+        let index = Supercluster::create(points, options);
+        let items = index.get_clusters([-180.0, -90.0, 180.0, 90.0], 0.0);
+        assert_eq!(items.len(), 2);
+
+        let empty = Supercluster::create(Vec::new(), ClusterOptions::default());
+        assert!(empty
+            .get_clusters([-180.0, -90.0, 180.0, 90.0], 3.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_zoom_limits() {
+        let points = vec![(10.0, 20.0), (10.001, 20.001), (-100.0, -40.0)];
+        let options = ClusterOptions {
+            min_zoom: 3,
+            max_zoom: 10,
+            ..ClusterOptions::default()
+        };
+        let index = Supercluster::create(points, options);
+        let world = [-180.0, -90.0, 180.0, 90.0];
+        let lowest = index.get_clusters(world, 3.0);
+        assert_eq!(lowest.len(), 2);
+        assert_eq!(index.get_clusters(world, f64::NAN), lowest);
+        assert_eq!(index.get_clusters(world, f64::NEG_INFINITY), lowest);
+        assert_eq!(index.get_clusters(world, 0.0), lowest);
+        assert_eq!(index.get_clusters(world, f64::INFINITY).len(), 3);
+        assert!(index.get_clusters([f64::NAN; 4], 5.0).is_empty());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, Bbox, KDBush, TIndex, TNumber};
+use crate::math;
+use alloc::vec::Vec;
+
+/// Index federating several sub-indices, e.g. one per region or data source
+///
+/// Results are reported with namespaced ids: the number of the sub-index (in the order
+/// of [`CompositeIndex::push`]) and the item id within it. Sub-indices whose extent doesn't
+/// touch a query are skipped.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{CompositeIndex, KDBush, DEFAULT_NODE_SIZE};
+///
+/// let mut index = CompositeIndex::new();
+/// index.push(KDBush::create(vec![(54.0, 1.0), (97.0, 21.0)], DEFAULT_NODE_SIZE));
+/// index.push(KDBush::create(vec![(65.0, 35.0)], DEFAULT_NODE_SIZE));
+/// index.within(60.0, 30.0, 10.0, |part, id| assert_eq!((part, id), (1, 0)));
+/// assert_eq!(index.knn(90.0, 20.0, 1)[0].0, (0, 1));
+/// ```
+#[derive(Default)]
+pub struct CompositeIndex {
+    parts: Vec<KDBush>,
+    /// Extent of each sub-index, `None` if it is empty
+    extents: Vec<Option<Bbox>>,
+}
+
+impl CompositeIndex {
+    /// Creates an index without sub-indices
+    pub fn new() -> CompositeIndex {
+        CompositeIndex::default()
+    }
+
+    /// Adds a sub-index and returns its number
+    pub fn push(&mut self, index: KDBush) -> usize {
+        let extent = index.points.iter().fold(None, |extent: Option<Bbox>, p| {
+            Some(match extent {
+                None => [p[0], p[1], p[0], p[1]],
+                Some(b) => [
+                    b[0].min(p[0]),
+                    b[1].min(p[1]),
+                    b[2].max(p[0]),
+                    b[3].max(p[1]),
+                ],
+            })
+        });
+        self.parts.push(index);
+        self.extents.push(extent);
+        self.parts.len() - 1
+    }
+
+    /// Sub-indices, by number
+    pub fn parts(&self) -> &[KDBush] {
+        &self.parts
+    }
+
+    /// Finds all items within the given bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader, called with the number of the sub-index and the item id
+    pub fn range<F>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        mut visitor: F,
+    ) where
+        F: FnMut(usize, TIndex),
+    {
+        if !is_valid_bbox(minx, miny, maxx, maxy) {
+            return;
+        }
+        for (part, (index, extent)) in self.parts.iter().zip(&self.extents).enumerate() {
+            let touches = extent
+                .is_some_and(|b| b[0] <= maxx && b[2] >= minx && b[1] <= maxy && b[3] >= miny);
+            if touches {
+                index.range(minx, miny, maxx, maxy, |id| visitor(part, id));
+            }
+        }
+    }
+
+    /// Finds all items within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader, called with the number of the sub-index and the item id
+    pub fn within<F>(&self, qx: TNumber, qy: TNumber, r: TNumber, mut visitor: F)
+    where
+        F: FnMut(usize, TIndex),
+    {
+        if !is_valid_radius(r) {
+            return;
+        }
+        for (part, (index, extent)) in self.parts.iter().zip(&self.extents).enumerate() {
+            if extent.is_some_and(|b| sq_extent_dist(&b, qx, qy) <= r * r) {
+                index.within(qx, qy, r, |id| visitor(part, id));
+            }
+        }
+    }
+
+    /// Finds the `k` items nearest to the query point over all sub-indices
+    ///
+    /// Returns `((part, id), distance)` pairs, ordered by increasing distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Maximal number of results
+    pub fn knn(&self, qx: TNumber, qy: TNumber, k: usize) -> Vec<((usize, TIndex), TNumber)> {
+        // Sub-indices by distance of their extent, so farther ones can be skipped
+        let mut order: Vec<(TNumber, usize)> = self
+            .extents
+            .iter()
+            .enumerate()
+            .filter_map(|(part, extent)| extent.map(|b| (sq_extent_dist(&b, qx, qy), part)))
+            .collect();
+        order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut best: Vec<(TNumber, (usize, TIndex))> = Vec::new();
+        for (d2, part) in order {
+            if best.len() == k && best.last().is_some_and(|worst| d2 > worst.0) {
+                break;
+            }
+            let index = &self.parts[part];
+            best.extend(
+                index
+                    .knn_idx(qx, qy, k)
+                    .into_iter()
+                    .map(|(i, d2)| (d2, (part, index.ids[i]))),
+            );
+            best.sort_by(|a, b| a.0.total_cmp(&b.0));
+            best.truncate(k);
+        }
+        best.into_iter()
+            .map(|(d2, id)| (id, math::sqrt(d2)))
+            .collect()
+    }
+}
+
+/// Squared distance of a point from a bounding box, zero inside
+fn sq_extent_dist(b: &Bbox, qx: TNumber, qy: TNumber) -> TNumber {
+    let dx = (b[0] - qx).max(0.0).max(qx - b[2]);
+    let dy = (b[1] - qy).max(0.0).max(qy - b[3]);
+    dx * dx + dy * dy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_composite() {
+        let points: Vec<(f64, f64)> = (0..600)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let mut composite = CompositeIndex::new();
+        // Partitioned by x, with an empty part in between
+        for chunk in &[(0.0, 30.0), (30.0, 30.0), (30.0, 70.0), (70.0, 200.0)] {
+            let part: Vec<(f64, f64)> = points
+                .iter()
+                .filter(|p| p.0 >= chunk.0 && p.0 < chunk.1)
+                .cloned()
+                .collect();
+            composite.push(KDBush::create(part, 8));
+        }
+        assert_eq!(composite.parts().len(), 4);
+        let coords = |part: usize, id: usize| {
+            let p = composite.parts()[part]
+                .points
+                .iter()
+                .zip(&composite.parts()[part].ids);
+            p.filter(|(_, &i)| i == id).map(|(p, _)| *p).next().unwrap()
+        };
+
+        let mut result = Vec::new();
+        composite.range(20.0, 10.0, 80.0, 40.0, |part, id| {
+            result.push(coords(part, id))
+        });
+        let expected = points
+            .iter()
+            .filter(|p| p.0 >= 20.0 && p.0 <= 80.0 && p.1 >= 10.0 && p.1 <= 40.0)
+            .count();
+        assert_eq!(result.len(), expected);
+        assert!(result.iter().all(|p| p[0] >= 20.0 && p[0] <= 80.0));
+
+        let mut result = 0;
+        composite.within(50.0, 50.0, 12.0, |_, _| result += 1);
+        let expected = points
+            .iter()
+            .filter(|p| KDBush::sq_dist(p.0, p.1, 50.0, 50.0) <= 144.0)
+            .count();
+        assert_eq!(result, expected);
+
+        let knn = composite.knn(29.5, 50.0, 20);
+        let mut expected: Vec<f64> = points
+            .iter()
+            .map(|p| KDBush::sq_dist(p.0, p.1, 29.5, 50.0).sqrt())
+            .collect();
+        expected.sort_by(|a, b| a.total_cmp(b));
+        expected.truncate(20);
+        assert_eq!(knn.iter().map(|r| r.1).collect::<Vec<_>>(), expected);
+        for ((part, id), d) in knn {
+            let p = coords(part, id);
+            assert_eq!(KDBush::sq_dist(p[0], p[1], 29.5, 50.0).sqrt(), d);
+        }
+        assert!(CompositeIndex::new().knn(0.0, 0.0, 3).is_empty());
+    }
+
+    #[test]
+    fn test_composite_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let mut composite = CompositeIndex::new();
+            composite.push(KDBush::create(points, node_size));
+            assert_eq!(composite.knn(0.0, 0.0, 5).len(), n);
+        }
+    }
+}
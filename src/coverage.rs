@@ -0,0 +1,109 @@
+use crate::kdbush::{is_valid_radius, KDBush, Point, TNumber};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl KDBush {
+    /// Checks for each probe point whether any item lies within a given radius
+    ///
+    /// The search for a probe stops at the first item found, so no results are collected.
+    ///
+    /// # Arguments
+    ///
+    /// * `probes` - Query points
+    /// * `r` - Radius
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let hydrants = KDBush::create(vec![(0.0, 0.0), (1000.0, 0.0)], DEFAULT_NODE_SIZE);
+    /// let houses = [[100.0, 200.0], [500.0, 0.0], [1000.0, 299.0]];
+    /// assert_eq!(hydrants.covered(&houses, 300.0), vec![true, false, true]);
+    /// ```
+    pub fn covered(&self, probes: &[Point], r: TNumber) -> Vec<bool> {
+        if self.ids.is_empty() || !is_valid_radius(r) {
+            return vec![false; probes.len()];
+        }
+        probes
+            .iter()
+            .map(|p| self.any_within_idx(p[0], p[1], r * r))
+            .collect()
+    }
+
+    /// Checks whether any position lies within a squared radius, searching the side of the query point first
+    fn any_within_idx(&self, qx: TNumber, qy: TNumber, r2: TNumber) -> bool {
+        let mut stack = vec![(0, self.ids.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                if self.points[left..right + 1]
+                    .iter()
+                    .any(|p| KDBush::sq_dist(p[0], p[1], qx, qy) <= r2)
+                {
+                    return true;
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let x = self.points[m][0];
+            let y = self.points[m][1];
+            if KDBush::sq_dist(x, y, qx, qy) <= r2 {
+                return true;
+            }
+
+            let d = if axis == 0 { qx - x } else { qy - y };
+            let (near, far) = if d <= 0.0 {
+                ((left, m - 1), (m + 1, right))
+            } else {
+                ((m + 1, right), (left, m - 1))
+            };
+            // Far child first, so the near child is searched next
+            if d * d <= r2 {
+                stack.push((far.0, far.1, (axis + 1) % 2));
+            }
+            stack.push((near.0, near.1, (axis + 1) % 2));
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_covered() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64 * 2.0, (i * 53 % 97) as f64 * 2.0))
+            .collect();
+        let index = KDBush::create(points.clone(), 8);
+        let probes: Vec<Point> = (0..200)
+            .map(|i| [(i * 7 % 211) as f64 + 0.3, (i * 11 % 199) as f64 + 0.6])
+            .collect();
+        for &r in &[0.0, 0.5, 1.0, 3.0] {
+            let expected: Vec<bool> = probes
+                .iter()
+                .map(|q| {
+                    points
+                        .iter()
+                        .any(|p| KDBush::sq_dist(p.0, p.1, q[0], q[1]) <= r * r)
+                })
+                .collect();
+            assert_eq!(expected, index.covered(&probes, r));
+        }
+        assert_eq!(index.covered(&probes[..2], -1.0), vec![false, false]);
+        let empty = KDBush::create(vec![], 8);
+        assert_eq!(empty.covered(&probes[..1], 10.0), vec![false]);
+    }
+
+    #[test]
+    fn test_covered_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let expected = vec![!points.is_empty()];
+            let index = KDBush::create(points, node_size);
+            assert_eq!(index.covered(&[[0.0, 0.0]], 5.0), expected);
+        }
+    }
+}
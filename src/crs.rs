@@ -0,0 +1,118 @@
+use crate::kdbush::{KDBush, Point};
+use core::error::Error;
+use core::fmt;
+
+/// Coordinate reference system of an index doesn't match the expected one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrsMismatch {
+    /// Expected EPSG code
+    pub expected: u32,
+    /// EPSG code of the index, `None` if it is untagged
+    pub actual: Option<u32>,
+}
+
+impl fmt::Display for CrsMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.actual {
+            Some(actual) => write!(
+                f,
+                "index is in EPSG:{}, expected EPSG:{}",
+                actual, self.expected
+            ),
+            None => write!(f, "index has no CRS, expected EPSG:{}", self.expected),
+        }
+    }
+}
+
+impl Error for CrsMismatch {}
+
+impl<T, I> KDBush<T, I> {
+    /// EPSG code of the coordinate reference system, if tagged
+    pub fn crs(&self) -> Option<u32> {
+        self.crs
+    }
+
+    /// Tags the index with the EPSG code of its coordinate reference system
+    ///
+    /// The coordinates are not changed. Flat buffers store the tag in a trailer that the
+    /// JavaScript kdbush ignores.
+    pub fn set_crs(&mut self, epsg: Option<u32>) {
+        self.crs = epsg;
+    }
+
+    /// Checks that the index is tagged with the expected coordinate reference system
+    ///
+    /// Untagged indices are rejected, so mixing coordinates of different systems fails early.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let mut index = KDBush::create(vec![(8.54, 47.37)], DEFAULT_NODE_SIZE);
+    /// assert!(index.check_crs(4326).is_err());
+    /// index.set_crs(Some(4326));
+    /// assert!(index.check_crs(4326).is_ok());
+    /// assert!(index.check_crs(2056).is_err());
+    /// ```
+    pub fn check_crs(&self, epsg: u32) -> Result<(), CrsMismatch> {
+        if self.crs == Some(epsg) {
+            Ok(())
+        } else {
+            Err(CrsMismatch {
+                expected: epsg,
+                actual: self.crs,
+            })
+        }
+    }
+}
+
+impl KDBush {
+    /// Creates a new index with coordinates transformed into another coordinate reference system
+    ///
+    /// Like [`KDBush::map_points`], but the new index is tagged with `epsg`.
+    ///
+    /// # Arguments
+    ///
+    /// * `epsg` - EPSG code of the target CRS
+    /// * `f` - Transformation of the item coordinates from the CRS of this index into the target CRS
+    pub fn reproject<M>(&self, epsg: u32, f: M) -> KDBush
+    where
+        M: Fn(Point) -> Point,
+    {
+        let mut kdbush = self.map_points(f);
+        kdbush.crs = Some(epsg);
+        kdbush
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crs() {
+        let mut index = KDBush::create(vec![(1.0, 2.0), (3.0, 4.0)], 8);
+        assert_eq!(index.crs(), None);
+        assert_eq!(
+            index.check_crs(3857),
+            Err(CrsMismatch {
+                expected: 3857,
+                actual: None
+            })
+        );
+        index.set_crs(Some(4326));
+        assert_eq!(index.retain(|id, _| id == 0).crs(), Some(4326));
+
+        let projected = index.reproject(3857, |p| [p[0] * 1000.0, p[1] * 1000.0]);
+        assert_eq!(projected.crs(), Some(3857));
+        assert!(projected.check_crs(3857).is_ok());
+        assert_eq!(
+            projected.check_crs(4326).unwrap_err().to_string(),
+            "index is in EPSG:3857, expected EPSG:4326"
+        );
+        let mut result = Vec::new();
+        projected.range(2500.0, 3500.0, 3500.0, 4500.0, |id| result.push(id));
+        assert_eq!(result, vec![1]);
+    }
+}
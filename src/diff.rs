@@ -0,0 +1,142 @@
+use crate::kdbush::{KDBush, TIndex, TNumber};
+use crate::matching::MatchStrategy;
+use crate::math;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Changes between two versions of a point set, see [`KDBush::diff`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointDiff {
+    /// Ids of new items without a counterpart in the old version
+    pub added: Vec<TIndex>,
+    /// Ids of old items without a counterpart in the new version
+    pub removed: Vec<TIndex>,
+    /// Matched items which changed their location, as `(old id, new id, distance)`
+    pub moved: Vec<(TIndex, TIndex, TNumber)>,
+    /// Matched items at the same location, as `(old id, new id)`
+    pub unchanged: Vec<(TIndex, TIndex)>,
+}
+
+impl KDBush {
+    /// Compares this index with a newer version of the point set
+    ///
+    /// Items are paired one-to-one with the closest pairs first (see
+    /// [`MatchStrategy::Greedy`]). Pairs within `tolerance` are reported as moved or
+    /// unchanged, all other items as removed or added. All lists are ordered by id.
+    ///
+    /// # Arguments
+    ///
+    /// * `new` - Index of the new version
+    /// * `tolerance` - Maximal distance of a moved item
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let old = KDBush::create(vec![(0.0, 0.0), (5.0, 5.0), (9.0, 9.0)], DEFAULT_NODE_SIZE);
+    /// let new = KDBush::create(vec![(5.0, 5.5), (0.0, 0.0), (20.0, 0.0)], DEFAULT_NODE_SIZE);
+    /// let diff = old.diff(&new, 1.0);
+    /// assert_eq!(diff.unchanged, vec![(0, 1)]);
+    /// assert_eq!(diff.moved, vec![(1, 0, 0.5)]);
+    /// assert_eq!(diff.removed, vec![2]);
+    /// assert_eq!(diff.added, vec![2]);
+    /// ```
+    pub fn diff(&self, new: &KDBush, tolerance: TNumber) -> PointDiff {
+        let mut diff = PointDiff::default();
+        let mut matched = vec![false; self.ids.len()];
+        let mut new_matched = vec![false; new.ids.len()];
+        for (i, j, d2) in self.match_idx(new, tolerance, MatchStrategy::Greedy) {
+            matched[i] = true;
+            new_matched[j] = true;
+            if d2 == 0.0 {
+                diff.unchanged.push((self.ids[i], new.ids[j]));
+            } else {
+                diff.moved.push((self.ids[i], new.ids[j], math::sqrt(d2)));
+            }
+        }
+        diff.removed = self.unmatched_ids(&matched);
+        diff.added = new.unmatched_ids(&new_matched);
+        diff.unchanged.sort();
+        diff.moved.sort_by_key(|m| (m.0, m.1));
+        diff
+    }
+
+    fn unmatched_ids(&self, matched: &[bool]) -> Vec<TIndex> {
+        let mut ids: Vec<TIndex> = self
+            .ids
+            .iter()
+            .zip(matched)
+            .filter(|(_, &m)| !m)
+            .map(|(&id, _)| id)
+            .collect();
+        ids.sort();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_diff() {
+        let old: Vec<(f64, f64)> = (0..300)
+            .map(|i| ((i * 37 % 101) as f64 * 10.0, (i * 53 % 97) as f64 * 10.0))
+            .collect();
+        // Drops every 10th point, moves every 7th point and adds a few new ones
+        let mut new: Vec<(f64, f64)> = old
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 10 != 0)
+            .map(|(i, p)| {
+                if i % 7 == 0 {
+                    (p.0 + 0.3, p.1 - 0.4)
+                } else {
+                    *p
+                }
+            })
+            .collect();
+        new.push((5000.0, 5000.0));
+        new.push((-5000.0, 0.0));
+
+        let diff = KDBush::create(old.clone(), 8).diff(&KDBush::create(new.clone(), 8), 1.0);
+        assert_eq!(
+            diff.removed,
+            (0..300).filter(|i| i % 10 == 0).collect::<Vec<_>>()
+        );
+        assert_eq!(diff.added, vec![new.len() - 2, new.len() - 1]);
+        assert_eq!(
+            diff.moved.len(),
+            (0..300).filter(|i| i % 7 == 0 && i % 10 != 0).count()
+        );
+        for &(o, n, d) in &diff.moved {
+            assert_eq!(new[n], (old[o].0 + 0.3, old[o].1 - 0.4));
+            assert!((d - 0.5).abs() < 1e-9);
+        }
+        for &(o, n) in &diff.unchanged {
+            assert_eq!(old[o], new[n]);
+        }
+        assert_eq!(diff.unchanged.len() + diff.moved.len(), 270);
+    }
+
+    #[test]
+    fn test_diff_empty() {
+        let old = KDBush::create(vec![(1.0, 1.0)], 8);
+        let new = KDBush::create(vec![], 8);
+        let diff = old.diff(&new, 1.0);
+        assert_eq!(diff.removed, vec![0]);
+        assert!(diff.added.is_empty());
+        assert_eq!(new.diff(&old, 1.0).added, vec![0]);
+    }
+
+    #[test]
+    fn test_diff_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let index = KDBush::create(points, node_size);
+            assert_eq!(index.diff(&index, 0.0).unchanged.len(), n);
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use crate::kdbush::{Bbox, KDBush, Point, TIndex, TNumber};
+use crate::math;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+/// Node of a kd-tree, covering a range of positions in the sorted arrays
+///
+/// Item ids and coordinates at a position are returned by [`KDBush::item`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TreeNode {
+    /// First position
+    pub left: TIndex,
+    /// Last position (inclusive)
+    pub right: TIndex,
+    /// Bounding box of all items in the node
+    pub bbox: Bbox,
+}
+
+impl TreeNode {
+    /// Number of items
+    pub fn size(&self) -> usize {
+        self.right - self.left + 1
+    }
+
+    /// Positions of the items
+    pub fn positions(&self) -> RangeInclusive<TIndex> {
+        self.left..=self.right
+    }
+
+    /// Position of the node's median, which is unique among all nodes with more than one item
+    ///
+    /// Can be used to store traversal state per node.
+    pub fn median(&self) -> TIndex {
+        (self.left + self.right) >> 1
+    }
+}
+
+/// Callbacks of a simultaneous traversal of two kd-trees, see [`KDBush::dual_traverse`]
+///
+/// Inner nodes are split into three children: both subtrees and a node with the median
+/// item only.
+pub trait DualTraversal {
+    /// Returns whether all item pairs of a query and a reference node can be skipped
+    fn prune(&mut self, query: &TreeNode, reference: &TreeNode) -> bool;
+
+    /// Processes all item pairs of a query and a reference leaf
+    fn process(&mut self, query: &TreeNode, reference: &TreeNode);
+
+    /// Called after the children of a query node have been traversed with a reference node
+    fn query_done(&mut self, _query: &TreeNode, _children: &[TreeNode]) {}
+}
+
+/// Tree with the bounding boxes of its nodes
+struct BoxedTree<'a> {
+    tree: &'a KDBush,
+    bboxes: alloc::borrow::Cow<'a, [Bbox]>,
+}
+
+impl<'a> BoxedTree<'a> {
+    fn new(tree: &'a KDBush) -> Self {
+        BoxedTree {
+            tree,
+            bboxes: tree.node_bboxes(),
+        }
+    }
+
+    fn root(&self) -> TreeNode {
+        self.node(0, self.tree.ids.len() - 1)
+    }
+
+    fn node(&self, left: TIndex, right: TIndex) -> TreeNode {
+        let bbox = if left == right {
+            let p = self.tree.points[left];
+            [p[0], p[1], p[0], p[1]]
+        } else {
+            self.bboxes[(left + right) >> 1]
+        };
+        TreeNode { left, right, bbox }
+    }
+
+    /// Children of an inner node: both subtrees and the median as single item node
+    fn children(&self, node: &TreeNode) -> Option<[TreeNode; 3]> {
+        if node.right - node.left <= self.tree.node_size as usize {
+            return None;
+        }
+        let m = node.median();
+        Some([
+            self.node(node.left, m - 1),
+            self.node(m, m),
+            self.node(m + 1, node.right),
+        ])
+    }
+}
+
+/// Squared minimal distance between two boxes
+pub(crate) fn sq_box_dist(a: &Bbox, b: &Bbox) -> TNumber {
+    let dx = (b[0] - a[2]).max(a[0] - b[2]).max(0.0);
+    let dy = (b[1] - a[3]).max(a[1] - b[3]).max(0.0);
+    dx * dx + dy * dy
+}
+
+impl KDBush {
+    /// Traverses this index and a reference index simultaneously
+    ///
+    /// Starting with both roots, each node pair which is not pruned by the traversal is
+    /// either processed, if both nodes are leaves, or the larger node is split and its
+    /// children are paired with the other node. Reference children are visited nearest
+    /// first. This is the building block for joins, matchings and statistics over two point
+    /// sets (or one point set with itself).
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - Reference index, may be the same as this index
+    /// * `traversal` - Pruning and processing callbacks
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{DualTraversal, KDBush, TreeNode, DEFAULT_NODE_SIZE};
+    ///
+    /// /// Counts the pairs of items closer than `r`
+    /// struct CountPairs<'a> {
+    ///     query: &'a KDBush,
+    ///     reference: &'a KDBush,
+    ///     r: f64,
+    ///     count: usize,
+    /// }
+    ///
+    /// impl<'a> DualTraversal for CountPairs<'a> {
+    ///     fn prune(&mut self, query: &TreeNode, reference: &TreeNode) -> bool {
+    ///         let (a, b) = (query.bbox, reference.bbox);
+    ///         let dx = (b[0] - a[2]).max(a[0] - b[2]).max(0.0);
+    ///         let dy = (b[1] - a[3]).max(a[1] - b[3]).max(0.0);
+    ///         dx * dx + dy * dy > self.r * self.r
+    ///     }
+    ///
+    ///     fn process(&mut self, query: &TreeNode, reference: &TreeNode) {
+    ///         for i in query.positions() {
+    ///             let (_, p) = self.query.item(i);
+    ///             for j in reference.positions() {
+    ///                 let (_, q) = self.reference.item(j);
+    ///                 if (p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) <= self.r * self.r {
+    ///                     self.count += 1;
+    ///                 }
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let a = KDBush::create(vec![(0.0, 0.0), (5.0, 5.0)], DEFAULT_NODE_SIZE);
+    /// let b = KDBush::create(vec![(0.5, 0.0), (9.0, 9.0), (5.0, 5.5)], DEFAULT_NODE_SIZE);
+    /// let mut count = CountPairs { query: &a, reference: &b, r: 1.0, count: 0 };
+    /// a.dual_traverse(&b, &mut count);
+    /// assert_eq!(count.count, 2);
+    /// ```
+    pub fn dual_traverse<T: DualTraversal>(&self, reference: &KDBush, traversal: &mut T) {
+        dual_traverse(self, reference, traversal);
+    }
+
+    /// Returns the id and coordinates of the item at a position in the sorted arrays
+    pub fn item(&self, pos: TIndex) -> (TIndex, Point) {
+        (self.ids[pos], self.points[pos])
+    }
+}
+
+/// Traverses all node pairs of two trees which are not pruned
+///
+/// The larger node of a pair is split first, reference children are visited nearest first.
+pub(crate) fn dual_traverse<T: DualTraversal>(
+    query: &KDBush,
+    reference: &KDBush,
+    traversal: &mut T,
+) {
+    if query.ids.is_empty() || reference.ids.is_empty() {
+        return;
+    }
+    let query = BoxedTree::new(query);
+    let reference = BoxedTree::new(reference);
+    dual_idx(
+        &query,
+        &reference,
+        query.root(),
+        reference.root(),
+        traversal,
+    );
+}
+
+fn dual_idx<T: DualTraversal>(
+    qtree: &BoxedTree,
+    rtree: &BoxedTree,
+    q: TreeNode,
+    r: TreeNode,
+    traversal: &mut T,
+) {
+    if traversal.prune(&q, &r) {
+        return;
+    }
+    let query_children = qtree.children(&q);
+    let reference_children = rtree.children(&r);
+    match (query_children, reference_children) {
+        (None, None) => traversal.process(&q, &r),
+        (Some(children), None) => split_query(qtree, rtree, q, r, children, traversal),
+        (Some(children), Some(_)) if q.size() >= r.size() => {
+            split_query(qtree, rtree, q, r, children, traversal)
+        }
+        (_, Some(mut children)) => {
+            children.sort_by(|a, b| {
+                sq_box_dist(&q.bbox, &a.bbox)
+                    .partial_cmp(&sq_box_dist(&q.bbox, &b.bbox))
+                    .unwrap_or(core::cmp::Ordering::Equal)
+            });
+            for c in &children {
+                dual_idx(qtree, rtree, q, *c, traversal);
+            }
+        }
+    }
+}
+
+fn split_query<T: DualTraversal>(
+    qtree: &BoxedTree,
+    rtree: &BoxedTree,
+    q: TreeNode,
+    r: TreeNode,
+    children: [TreeNode; 3],
+    traversal: &mut T,
+) {
+    for c in &children {
+        dual_idx(qtree, rtree, *c, r, traversal);
+    }
+    traversal.query_done(&q, &children);
+}
+
+/// Nearest other item of each item
+struct AllNearest<'a> {
+    tree: &'a KDBush,
+    /// Squared distance and position of the nearest item found so far, by position
+    best: Vec<(TNumber, TIndex)>,
+    /// Maximal squared distance of `best` within each node, by node median
+    bounds: Vec<TNumber>,
+}
+
+impl<'a> AllNearest<'a> {
+    fn bound(&self, node: &TreeNode) -> TNumber {
+        if node.size() == 1 {
+            self.best[node.left].0
+        } else {
+            self.bounds[node.median()]
+        }
+    }
+}
+
+impl<'a> DualTraversal for AllNearest<'a> {
+    fn prune(&mut self, query: &TreeNode, reference: &TreeNode) -> bool {
+        sq_box_dist(&query.bbox, &reference.bbox) > self.bound(query)
+    }
+
+    fn process(&mut self, query: &TreeNode, reference: &TreeNode) {
+        let points = &self.tree.points;
+        let mut bound: TNumber = 0.0;
+        for i in query.left..query.right + 1 {
+            for j in reference.left..reference.right + 1 {
+                let d2 = KDBush::sq_dist(points[i][0], points[i][1], points[j][0], points[j][1]);
+                if i != j && d2 < self.best[i].0 {
+                    self.best[i] = (d2, j);
+                }
+            }
+            bound = bound.max(self.best[i].0);
+        }
+        if query.size() > 1 {
+            self.bounds[query.median()] = bound;
+        }
+    }
+
+    fn query_done(&mut self, query: &TreeNode, children: &[TreeNode]) {
+        let bound = children
+            .iter()
+            .map(|c| self.bound(c))
+            .fold(0.0, TNumber::max);
+        self.bounds[query.median()] = bound;
+    }
+}
+
+impl KDBush {
+    /// Finds the nearest other item of every item
+    ///
+    /// Uses a simultaneous traversal of the tree with itself, which is much faster than
+    /// querying the nearest neighbor of each item separately. Returns `(id, nearest id,
+    /// distance)`, ordered by id. Items are only returned if the index has at least two items.
+    pub fn all_nearest(&self) -> Vec<(TIndex, TIndex, TNumber)> {
+        if self.ids.len() < 2 {
+            return Vec::new();
+        }
+        let mut traversal = AllNearest {
+            tree: self,
+            best: vec![(f64::INFINITY, 0); self.ids.len()],
+            bounds: vec![f64::INFINITY; self.ids.len()],
+        };
+        dual_traverse(self, self, &mut traversal);
+        let mut nearest: Vec<(TIndex, TIndex, TNumber)> = traversal
+            .best
+            .iter()
+            .enumerate()
+            .map(|(i, &(d2, j))| (self.ids[i], self.ids[j], math::sqrt(d2)))
+            .collect();
+        nearest.sort_by_key(|n| n.0);
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_all_nearest() {
+        let points: Vec<(f64, f64)> = (0..700)
+            .map(|i| {
+                (
+                    (i * 37 % 101) as f64 + (i as f64) * 0.001,
+                    (i * 53 % 97) as f64,
+                )
+            })
+            .collect();
+        for index in &[
+            KDBush::create(points.clone(), 8),
+            KDBush::create_with_bboxes(points.clone(), 3),
+        ] {
+            let nearest = index.all_nearest();
+            assert_eq!(nearest.len(), points.len());
+            for (id, &(nid, other, d)) in nearest.iter().enumerate() {
+                assert_eq!(id, nid);
+                assert_ne!(id, other);
+                let expected = (0..points.len())
+                    .filter(|&j| j != id)
+                    .map(|j| KDBush::sq_dist(points[id].0, points[id].1, points[j].0, points[j].1))
+                    .fold(f64::INFINITY, f64::min);
+                assert_eq!(d, expected.sqrt());
+            }
+        }
+    }
+
+    /// Collects all pairs of items closer than `r`
+    struct Pairs<'a> {
+        query: &'a KDBush,
+        reference: &'a KDBush,
+        r: f64,
+        pairs: Vec<(usize, usize)>,
+    }
+
+    impl<'a> DualTraversal for Pairs<'a> {
+        fn prune(&mut self, query: &TreeNode, reference: &TreeNode) -> bool {
+            sq_box_dist(&query.bbox, &reference.bbox) > self.r * self.r
+        }
+
+        fn process(&mut self, query: &TreeNode, reference: &TreeNode) {
+            for i in query.positions() {
+                let (id, p) = self.query.item(i);
+                for j in reference.positions() {
+                    let (other, q) = self.reference.item(j);
+                    if KDBush::sq_dist(p[0], p[1], q[0], q[1]) <= self.r * self.r {
+                        self.pairs.push((id, other));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dual_traverse() {
+        let a: Vec<(f64, f64)> = (0..300)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let b: Vec<(f64, f64)> = (0..200)
+            .map(|i| ((i * 13 % 89) as f64 + 0.5, (i * 29 % 83) as f64))
+            .collect();
+        let query = KDBush::create(a.clone(), 4);
+        let reference = KDBush::create(b.clone(), 8);
+        let mut traversal = Pairs {
+            query: &query,
+            reference: &reference,
+            r: 2.0,
+            pairs: Vec::new(),
+        };
+        query.dual_traverse(&reference, &mut traversal);
+        traversal.pairs.sort();
+
+        let mut expected = Vec::new();
+        for (i, p) in a.iter().enumerate() {
+            for (j, q) in b.iter().enumerate() {
+                if KDBush::sq_dist(p.0, p.1, q.0, q.1) <= 4.0 {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(expected, traversal.pairs);
+    }
+
+    #[test]
+    fn test_all_nearest_duplicates_and_tiny() {
+        let index = KDBush::create(vec![(1.0, 1.0), (1.0, 1.0), (5.0, 5.0)], 1);
+        let nearest = index.all_nearest();
+        assert_eq!(nearest[..2], [(0, 1, 0.0), (1, 0, 0.0)]);
+        assert_eq!((nearest[2].0, nearest[2].2), (2, 32f64.sqrt()));
+        assert!(KDBush::create(vec![(1.0, 1.0)], 1).all_nearest().is_empty());
+        assert!(KDBush::create(vec![], 1).all_nearest().is_empty());
+    }
+
+    #[test]
+    fn test_all_nearest_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let index = KDBush::create(points, node_size);
+            assert_eq!(index.all_nearest().len(), if n > 1 { n } else { 0 });
+        }
+    }
+}
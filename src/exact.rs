@@ -0,0 +1,252 @@
+use crate::kdbush::{KDBush, TIndex, TNumber};
+use crate::math;
+use alloc::vec;
+use core::cmp::Ordering;
+
+/// Bound beyond which floats are compared without conversion
+const TWO_POW_100: f64 = 1267650600228229401496703205376.0;
+
+/// Largest integer up to which all integers are exactly representable as `f64`
+const MAX_EXACT_INT: f64 = 9007199254740992.0;
+
+/// Exact comparison of an integer with a float, `None` for NaN
+fn cmp_exact(a: i128, b: TNumber) -> Option<Ordering> {
+    if b.is_nan() {
+        None
+    } else if b >= TWO_POW_100 {
+        Some(Ordering::Less)
+    } else if b <= -TWO_POW_100 {
+        Some(Ordering::Greater)
+    } else {
+        // floor(b) is exactly representable as i128 in this range
+        let floor = math::floor(b);
+        match a.cmp(&(floor as i128)) {
+            Ordering::Equal if b > floor => Some(Ordering::Less),
+            ord => Some(ord),
+        }
+    }
+}
+
+/// Integer value of an integral coordinate within the exactly representable range
+fn to_int(v: TNumber) -> Option<i128> {
+    if math::floor(v) == v && math::abs(v) <= MAX_EXACT_INT {
+        Some(v as i128)
+    } else {
+        None
+    }
+}
+
+impl KDBush {
+    /// Finds all items within the given integer bounding box, using exact comparisons
+    ///
+    /// Unlike [`KDBush::range`], the bounds are never rounded to floating point, so boundary
+    /// decisions are bit-identical on all platforms.
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader
+    pub fn range_exact<F>(&self, minx: i64, miny: i64, maxx: i64, maxy: i64, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.ids.is_empty() || minx > maxx || miny > maxy {
+            return;
+        }
+        let bbox = [minx as i128, miny as i128, maxx as i128, maxy as i128];
+        let mut stack = vec![(0, self.ids.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                for i in left..right + 1 {
+                    if KDBush::contains_exact(&bbox, self.points[i][0], self.points[i][1]) {
+                        visitor(self.ids[i]);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let x = self.points[m][0];
+            let y = self.points[m][1];
+
+            if KDBush::contains_exact(&bbox, x, y) {
+                visitor(self.ids[m]);
+            }
+
+            let split = if axis == 0 { x } else { y };
+            // Right child first, so the left child is visited next
+            if cmp_exact(bbox[2 + axis], split) != Some(Ordering::Less) {
+                stack.push((m + 1, right, (axis + 1) % 2));
+            }
+            if cmp_exact(bbox[axis], split) != Some(Ordering::Greater) {
+                stack.push((left, m - 1, (axis + 1) % 2));
+            }
+        }
+    }
+
+    /// Finds all items within a given radius from an integer query point, using exact integer arithmetic
+    ///
+    /// Squared distances are computed in `u128`, so boundary decisions are never subject to
+    /// floating-point rounding or integer overflow. Only items with integer coordinates up to
+    /// ±2^53 are matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader
+    pub fn within_exact<F>(&self, qx: i64, qy: i64, r: i64, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.ids.is_empty() || r < 0 {
+            return;
+        }
+        let (qx, qy, r) = (qx as i128, qy as i128, r as i128);
+        // r is at most i64::MAX, so r² fits u128, unlike the squared distance of far items
+        let r2 = (r as u128).pow(2);
+        let matches = |x: TNumber, y: TNumber| match (to_int(x), to_int(y)) {
+            (Some(x), Some(y)) => {
+                let (dx, dy) = (x.abs_diff(qx), y.abs_diff(qy));
+                dx.checked_mul(dx)
+                    .and_then(|d2| d2.checked_add(dy * dy))
+                    .is_some_and(|d2| d2 <= r2)
+            }
+            _ => false,
+        };
+
+        let mut stack = vec![(0, self.ids.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                for i in left..right + 1 {
+                    if matches(self.points[i][0], self.points[i][1]) {
+                        visitor(self.ids[i]);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let x = self.points[m][0];
+            let y = self.points[m][1];
+
+            if matches(x, y) {
+                visitor(self.ids[m]);
+            }
+
+            let (q, split) = if axis == 0 { (qx, x) } else { (qy, y) };
+            // Right child first, so the left child is visited next
+            if cmp_exact(q + r, split) != Some(Ordering::Less) {
+                stack.push((m + 1, right, (axis + 1) % 2));
+            }
+            if cmp_exact(q - r, split) != Some(Ordering::Greater) {
+                stack.push((left, m - 1, (axis + 1) % 2));
+            }
+        }
+    }
+
+    fn contains_exact(bbox: &[i128; 4], x: TNumber, y: TNumber) -> bool {
+        let le = |a: i128, b: TNumber| cmp_exact(a, b).is_some_and(|o| o != Ordering::Greater);
+        let ge = |a: i128, b: TNumber| cmp_exact(a, b).is_some_and(|o| o != Ordering::Less);
+        le(bbox[0], x) && ge(bbox[2], x) && le(bbox[1], y) && ge(bbox[3], y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_cmp_exact() {
+        assert_eq!(cmp_exact(3, 3.0), Some(Ordering::Equal));
+        assert_eq!(cmp_exact(3, 3.5), Some(Ordering::Less));
+        assert_eq!(cmp_exact(-4, -3.5), Some(Ordering::Less));
+        assert_eq!(cmp_exact(-3, -3.5), Some(Ordering::Greater));
+        // 2^53 + 1 is not representable as f64 and rounds to 2^53
+        assert_eq!(
+            cmp_exact(9007199254740993, 9007199254740992.0),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(cmp_exact(0, f64::NAN), None);
+        assert_eq!(
+            cmp_exact(i64::MAX as i128, f64::INFINITY),
+            Some(Ordering::Less)
+        );
+    }
+
+    #[test]
+    fn test_exact_queries() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points.clone(), 8);
+
+        let mut expected = Vec::new();
+        index.within(40.0, 50.0, 13.0, |id| expected.push(id));
+        expected.sort();
+        let mut result = Vec::new();
+        index.within_exact(40, 50, 13, |id| result.push(id));
+        result.sort();
+        assert_eq!(expected, result);
+
+        let mut expected = Vec::new();
+        index.range(10.0, 20.0, 30.0, 60.0, |id| expected.push(id));
+        expected.sort();
+        let mut result = Vec::new();
+        index.range_exact(10, 20, 30, 60, |id| result.push(id));
+        result.sort();
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_exact_large_coordinates() {
+        // Differences which are lost when the query is converted to f64
+        let big = 1i64 << 53;
+        let index = KDBush::create(vec![(big as f64, 0.0), (0.5, 0.0)], 1);
+        let mut result = Vec::new();
+        index.range_exact(big + 1, -1, big + 2, 1, |id| result.push(id));
+        assert!(result.is_empty());
+        index.within_exact(big + 1, 0, 1, |id| result.push(id));
+        assert_eq!(result, vec![0]);
+        result.clear();
+        index.within_exact(0, 0, 1, |id| result.push(id));
+        assert!(result.is_empty());
+        index.range_exact(0, 0, 1, 0, |id| result.push(id));
+        assert_eq!(result, vec![1]);
+    }
+
+    #[test]
+    fn test_exact_extreme_queries() {
+        let big = (1i64 << 53) as f64;
+        let index = KDBush::create(vec![(big, big), (-big, -big), (0.0, 0.0)], 1);
+        for &(q, r) in &[(i64::MIN, 1), (i64::MAX, 1), (i64::MIN, i64::MAX)] {
+            let mut result = Vec::new();
+            index.within_exact(q, q, r, |id| result.push(id));
+            assert!(result.is_empty());
+        }
+        let mut result = Vec::new();
+        index.within_exact(i64::MAX, 0, i64::MAX, |id| result.push(id));
+        result.sort();
+        assert_eq!(result, vec![0, 2]);
+        let mut result = Vec::new();
+        index.range_exact(i64::MIN, i64::MIN, i64::MAX, i64::MAX, |id| result.push(id));
+        assert_eq!(result.len(), 3);
+    }
+
+    #[test]
+    fn test_exact_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids = Vec::new();
+            index.range_exact(-1, -1, 5, 5, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+            let mut ids = Vec::new();
+            index.within_exact(0, 0, 5, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, Bbox, KDBush, TIndex, TNumber};
+use core::ops::ControlFlow;
+
+impl KDBush {
+    /// Finds all items within the given bounding box, stopping at the first visitor error
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader, the query is aborted and returns its error on failure
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    /// use std::io::Write;
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let mut out = Vec::new();
+    /// index.try_range(20.0, 30.0, 70.0, 70.0, |id| writeln!(out, "{}", id))?;
+    /// assert_eq!(out, b"2\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn try_range<E, F>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        mut visitor: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(TIndex) -> Result<(), E>,
+    {
+        self.try_range_pos([minx, miny, maxx, maxy], |i| visitor(self.ids[i]))
+    }
+
+    /// Finds all items within a given radius from the query point, stopping at the first visitor error
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader, the query is aborted and returns its error on failure
+    pub fn try_within<E, F>(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        r: TNumber,
+        mut visitor: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(TIndex) -> Result<(), E>,
+    {
+        self.try_within_pos(qx, qy, r, |i| visitor(self.ids[i]))
+    }
+
+    /// Finds items within the given bounding box until the visitor breaks
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader, returning `ControlFlow::Break` stops the traversal
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    /// use std::ops::ControlFlow;
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let found = index.range_until(50.0, 0.0, 100.0, 40.0, |id| ControlFlow::Break(id));
+    /// assert!(found.is_break());
+    /// ```
+    pub fn range_until<B, F>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        mut visitor: F,
+    ) -> ControlFlow<B>
+    where
+        F: FnMut(TIndex) -> ControlFlow<B>,
+    {
+        from_result(self.try_range(minx, miny, maxx, maxy, |id| to_result(visitor(id))))
+    }
+
+    /// Finds items within a given radius from the query point until the visitor breaks
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader, returning `ControlFlow::Break` stops the traversal
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    /// use std::ops::ControlFlow;
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let any = index.within_until(60.0, 30.0, 10.0, |_| ControlFlow::Break(())).is_break();
+    /// assert!(any);
+    /// ```
+    pub fn within_until<B, F>(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        r: TNumber,
+        mut visitor: F,
+    ) -> ControlFlow<B>
+    where
+        F: FnMut(TIndex) -> ControlFlow<B>,
+    {
+        from_result(self.try_within(qx, qy, r, |id| to_result(visitor(id))))
+    }
+}
+
+impl KDBush {
+    /// Visits the positions of the items within the bounding box until the visitor fails
+    pub(crate) fn try_range_pos<E, F>(&self, bbox: Bbox, mut visitor: F) -> Result<(), E>
+    where
+        F: FnMut(TIndex) -> Result<(), E>,
+    {
+        if self.ids.is_empty() || !is_valid_bbox(bbox[0], bbox[1], bbox[2], bbox[3]) {
+            return Ok(());
+        }
+        self.try_range_idx(bbox, &mut visitor, 0, self.ids.len() - 1, 0)
+    }
+
+    /// Visits the positions of the items within the radius until the visitor fails
+    ///
+    /// Like [`KDBush::within`], an infinite radius visits all items without searching the tree.
+    pub(crate) fn try_within_pos<E, F>(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        r: TNumber,
+        mut visitor: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(TIndex) -> Result<(), E>,
+    {
+        if self.ids.is_empty() || !is_valid_radius(r) {
+            return Ok(());
+        }
+        if r == f64::INFINITY {
+            return (0..self.ids.len()).try_for_each(visitor);
+        }
+        self.try_within_dist_idx(
+            [qx, qy],
+            r * r,
+            &mut |i, _| visitor(i),
+            0,
+            self.ids.len() - 1,
+            0,
+        )
+    }
+}
+
+fn to_result<B>(flow: ControlFlow<B>) -> Result<(), B> {
+    match flow {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(b) => Err(b),
+    }
+}
+
+fn from_result<B>(result: Result<(), B>) -> ControlFlow<B> {
+    match result {
+        Ok(()) => ControlFlow::Continue(()),
+        Err(b) => ControlFlow::Break(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_try_queries() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points, 8);
+
+        let mut expected = Vec::new();
+        index.range(10.0, 20.0, 60.0, 50.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        let ok: Result<(), ()> = index.try_range(10.0, 20.0, 60.0, 50.0, |id| {
+            result.push(id);
+            Ok(())
+        });
+        assert!(ok.is_ok());
+        assert_eq!(expected, result);
+
+        let mut expected = Vec::new();
+        index.within(40.0, 50.0, 15.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        let err = index.try_within(40.0, 50.0, 15.0, |id| {
+            if result.len() == 5 {
+                return Err(id);
+            }
+            result.push(id);
+            Ok(())
+        });
+        assert_eq!(err, Err(expected[5]));
+        assert_eq!(result, expected[..5]);
+    }
+
+    #[test]
+    fn test_until_queries() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points, 8);
+
+        let mut expected = Vec::new();
+        index.range(10.0, 20.0, 60.0, 50.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        let flow = index.range_until(10.0, 20.0, 60.0, 50.0, |id| {
+            result.push(id);
+            if result.len() == 10 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(result, expected[..10]);
+
+        let mut count = 0;
+        let flow: ControlFlow<()> = index.within_until(40.0, 50.0, 15.0, |_| {
+            count += 1;
+            ControlFlow::Continue(())
+        });
+        assert_eq!(flow, ControlFlow::Continue(()));
+        let mut expected = 0;
+        index.within(40.0, 50.0, 15.0, |_| expected += 1);
+        assert_eq!(count, expected);
+
+        let first = index.within_until(40.0, 50.0, 15.0, ControlFlow::Break);
+        assert!(first.is_break());
+        assert!(index
+            .within_until(500.0, 500.0, 1.0, ControlFlow::Break)
+            .is_continue());
+    }
+
+    #[test]
+    fn test_try_queries_degenerate() {
+        let empty = KDBush::create(vec![], 8);
+        assert_eq!(empty.try_within(0.0, 0.0, 1.0, |_| Err(())), Ok(()));
+        let index = KDBush::create(vec![(1.0, 1.0)], 8);
+        assert_eq!(index.try_within(0.0, 0.0, -1.0, |_| Err(())), Ok(()));
+        assert_eq!(index.try_range(0.0, 0.0, 2.0, 2.0, |_| Err(())), Err(()));
+    }
+
+    #[test]
+    fn test_fallible_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids = Vec::new();
+            let result = index.try_within(0.0, 0.0, 5.0, |id| {
+                ids.push(id);
+                Ok::<(), ()>(())
+            });
+            assert_eq!(result, Ok(()));
+            ids.sort();
+            assert_eq!(ids, all);
+            let flow = index.range_until(-1.0, -1.0, 5.0, 5.0, core::ops::ControlFlow::Break);
+            assert_eq!(flow.is_break(), !all.is_empty());
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Great-circle queries on longitude/latitude points, like the JavaScript geokdbush
+//!
+//! Points are `(longitude, latitude)` in degrees, distances are in kilometers on a sphere.
+
+use crate::kdbush::{is_valid_radius, Bbox, KDBush, TIndex, TNumber};
+
+/// Mean earth radius in kilometers
+pub const EARTH_RADIUS_KM: TNumber = 6371.0088;
+
+/// Haversine of an angle in radians
+fn hav(theta: TNumber) -> TNumber {
+    let s = (theta / 2.0).sin();
+    s * s
+}
+
+/// Haversine of the central angle between two points, given the haversine of their longitude difference
+fn hav_dist_partial(hav_dlon: TNumber, cos_lat1: TNumber, lat1: TNumber, lat2: TNumber) -> TNumber {
+    cos_lat1 * lat2.to_radians().cos() * hav_dlon + hav((lat1 - lat2).to_radians())
+}
+
+/// Converts the haversine of a central angle to kilometers
+fn hav_to_km(h: TNumber) -> TNumber {
+    2.0 * EARTH_RADIUS_KM * h.clamp(0.0, 1.0).sqrt().asin()
+}
+
+/// Converts kilometers to the haversine of the central angle
+fn km_to_hav(km: TNumber) -> TNumber {
+    hav((km / EARTH_RADIUS_KM).min(std::f64::consts::PI))
+}
+
+/// Great-circle distance between two points in kilometers
+///
+/// # Arguments
+///
+/// * `lon1`, `lat1` - First point in degrees
+/// * `lon2`, `lat2` - Second point in degrees
+pub fn geo_distance(lon1: TNumber, lat1: TNumber, lon2: TNumber, lat2: TNumber) -> TNumber {
+    let h = hav_dist_partial(
+        hav((lon1 - lon2).to_radians()),
+        lat1.to_radians().cos(),
+        lat1,
+        lat2,
+    );
+    hav_to_km(h)
+}
+
+/// Query point with precomputed values
+struct GeoQuery {
+    lon: TNumber,
+    lat: TNumber,
+    cos_lat: TNumber,
+}
+
+impl GeoQuery {
+    fn new(lon: TNumber, lat: TNumber) -> GeoQuery {
+        GeoQuery {
+            lon,
+            lat,
+            cos_lat: lat.to_radians().cos(),
+        }
+    }
+
+    /// Haversine of the central angle to a point
+    fn hav_dist(&self, lon: TNumber, lat: TNumber) -> TNumber {
+        hav_dist_partial(
+            hav((self.lon - lon).to_radians()),
+            self.cos_lat,
+            self.lat,
+            lat,
+        )
+    }
+
+    /// Lower bound of the haversine of the central angle to all points within a bounding box
+    fn hav_box_dist(&self, bbox: &Bbox) -> TNumber {
+        // Split bounds may be infinite
+        let min_lon = bbox[0].max(-180.0);
+        let min_lat = bbox[1].max(-90.0);
+        let max_lon = bbox[2].min(180.0);
+        let max_lat = bbox[3].min(90.0);
+        if self.lon >= min_lon && self.lon <= max_lon {
+            return if self.lat < min_lat {
+                hav((self.lat - min_lat).to_radians())
+            } else if self.lat > max_lat {
+                hav((self.lat - max_lat).to_radians())
+            } else {
+                0.0
+            };
+        }
+        // The nearest point is on the nearest meridian of the box, at the latitude where the
+        // great circle distance has its extremum or at a corner
+        let hav_dlon =
+            hav((min_lon - self.lon).to_radians()).min(hav((max_lon - self.lon).to_radians()));
+        let extremum_lat = vertex_lat(self.lat, hav_dlon);
+        if extremum_lat > min_lat && extremum_lat < max_lat {
+            return hav_dist_partial(hav_dlon, self.cos_lat, self.lat, extremum_lat);
+        }
+        hav_dist_partial(hav_dlon, self.cos_lat, self.lat, min_lat).min(hav_dist_partial(
+            hav_dlon,
+            self.cos_lat,
+            self.lat,
+            max_lat,
+        ))
+    }
+}
+
+/// Latitude of the point on a meridian with the extremal distance from a query point
+fn vertex_lat(lat: TNumber, hav_dlon: TNumber) -> TNumber {
+    let cos_dlon = 1.0 - 2.0 * hav_dlon;
+    if cos_dlon <= 0.0 {
+        if lat > 0.0 {
+            90.0
+        } else {
+            -90.0
+        }
+    } else {
+        (lat.to_radians().tan() / cos_dlon).atan().to_degrees()
+    }
+}
+
+impl KDBush {
+    /// Finds all items within a great-circle distance from the query point
+    ///
+    /// Items are `(longitude, latitude)` points in degrees. Pruning accounts for the
+    /// convergence of meridians and for distances across the antimeridian.
+    ///
+    /// # Arguments
+    ///
+    /// * `lon`, `lat` - Query point in degrees
+    /// * `radius_km` - Radius in kilometers
+    /// * `visitor` - Result reader
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// // Zurich, Bern and the other side of the antimeridian near Fiji
+    /// let points = vec![(8.54, 47.37), (7.45, 46.95), (-179.9, -17.0)];
+    /// let index = KDBush::create(points, DEFAULT_NODE_SIZE);
+    /// let mut result = Vec::new();
+    /// index.within_geo(179.9, -17.0, 50.0, |id| result.push(id));
+    /// assert_eq!(result, vec![2]);
+    /// assert_eq!(index.nearest_geo(8.0, 47.0, 1)[0].0, 1);
+    /// ```
+    pub fn within_geo<F>(&self, lon: TNumber, lat: TNumber, radius_km: TNumber, visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_radius(radius_km) {
+            return;
+        }
+        let query = GeoQuery::new(lon, lat);
+        let max_hav = km_to_hav(radius_km);
+        self.within_region(
+            |minx, miny, maxx, maxy| query.hav_box_dist(&[minx, miny, maxx, maxy]) <= max_hav,
+            |x, y| query.hav_dist(x, y) <= max_hav,
+            visitor,
+        );
+    }
+
+    /// Finds the `k` items nearest to the query point by great-circle distance
+    ///
+    /// Returns `(id, distance in kilometers)` pairs, ordered by increasing distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `lon`, `lat` - Query point in degrees
+    /// * `k` - Maximal number of results
+    pub fn nearest_geo(&self, lon: TNumber, lat: TNumber, k: usize) -> Vec<(TIndex, TNumber)> {
+        let query = GeoQuery::new(lon, lat);
+        let mut best = self.result_heap(k);
+        self.best_k_idx(
+            k,
+            f64::INFINITY,
+            |i| query.hav_dist(self.points[i][0], self.points[i][1]),
+            |b| query.hav_box_dist(b),
+            &mut best,
+        );
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|r| (self.ids[r.item], hav_to_km(r.key)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> Vec<(f64, f64)> {
+        (0..2000)
+            .map(|i| {
+                let lon = (i * 137 % 3600) as f64 / 10.0 - 180.0;
+                let lat = (i * 71 % 1780) as f64 / 10.0 - 89.0;
+                (lon, lat)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_geo_distance() {
+        // Zurich - New York
+        let d = geo_distance(8.54, 47.37, -74.0, 40.71);
+        assert!((d - 6320.0).abs() < 10.0);
+        assert!(geo_distance(179.9, 0.0, -179.9, 0.0) < 23.0);
+        assert!((geo_distance(0.0, 90.0, 123.0, 90.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_within_geo() {
+        let points = grid();
+        for &index in &[
+            &KDBush::create(points.clone(), 8),
+            &KDBush::create_with_bboxes(points.clone(), 8),
+        ] {
+            for &(lon, lat, r) in &[
+                (179.5, 10.0, 800.0),
+                (-179.5, -60.0, 1500.0),
+                (30.0, 88.0, 1000.0),
+                (0.0, 0.0, 0.0),
+                (0.0, 0.0, 30000.0),
+            ] {
+                let mut expected: Vec<usize> = (0..points.len())
+                    .filter(|&i| geo_distance(lon, lat, points[i].0, points[i].1) <= r)
+                    .collect();
+                let mut result = Vec::new();
+                index.within_geo(lon, lat, r, |id| result.push(id));
+                expected.sort();
+                result.sort();
+                assert_eq!(expected, result);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nearest_geo() {
+        let points = grid();
+        let index = KDBush::create(points.clone(), 8);
+        for &(lon, lat) in &[(179.9, 5.0), (-30.0, -89.5), (100.0, 45.0)] {
+            let mut expected: Vec<f64> = points
+                .iter()
+                .map(|p| geo_distance(lon, lat, p.0, p.1))
+                .collect();
+            expected.sort_by(|a, b| a.total_cmp(b));
+            let result = index.nearest_geo(lon, lat, 10);
+            assert_eq!(result.len(), 10);
+            for (r, e) in result.iter().zip(&expected) {
+                assert!((r.1 - e).abs() < 1e-6);
+            }
+        }
+        assert!(KDBush::create(vec![], 8)
+            .nearest_geo(0.0, 0.0, 3)
+            .is_empty());
+    }
+}
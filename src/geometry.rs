@@ -0,0 +1,408 @@
+use crate::kdbush::{is_valid_radius, Bbox, KDBush, Point, TIndex, TNumber};
+use alloc::vec::Vec;
+
+impl KDBush {
+    /// Finds all items within a given distance from a line segment
+    ///
+    /// The query region is a capsule, i.e. the area swept by a circle moving along the segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `x0`, `y0`, `x1`, `y1` - Segment start and end point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader
+    pub fn within_segment<F>(
+        &self,
+        x0: TNumber,
+        y0: TNumber,
+        x1: TNumber,
+        y1: TNumber,
+        r: TNumber,
+        visitor: F,
+    ) where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_radius(r) {
+            return;
+        }
+        let r2 = r * r;
+        self.within_region(
+            |minx, miny, maxx, maxy| {
+                segment_intersects_box(x0, y0, x1, y1, [minx - r, miny - r, maxx + r, maxy + r])
+            },
+            |x, y| sq_seg_dist(x, y, x0, y0, x1, y1) <= r2,
+            visitor,
+        );
+    }
+
+    /// Finds all items within a polygon
+    ///
+    /// Nodes outside the bounding box of the polygon are skipped, the remaining items are
+    /// tested with a point-in-polygon test. The ring may be closed or open. Items exactly
+    /// on the boundary may or may not be included. Use [`KDBush::within_multipolygon`] for
+    /// polygons with holes.
+    ///
+    /// # Arguments
+    ///
+    /// * `ring` - Polygon vertices
+    /// * `visitor` - Result reader
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let triangle = [[50.0, 0.0], [100.0, 0.0], [50.0, 50.0]];
+    /// let mut result = Vec::new();
+    /// index.within_polygon(&triangle, |id| result.push(id));
+    /// assert_eq!(result, vec![0]);
+    /// ```
+    pub fn within_polygon<F>(&self, ring: &[Point], visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        let b = ring_bbox(ring);
+        self.within_region(
+            |minx, miny, maxx, maxy| b[0] <= maxx && b[2] >= minx && b[1] <= maxy && b[3] >= miny,
+            |x, y| x >= b[0] && x <= b[2] && y >= b[1] && y <= b[3] && ring_contains(ring, x, y),
+            visitor,
+        );
+    }
+
+    /// Finds all items within an axis-aligned ellipse
+    ///
+    /// Semi-axes which are negative or NaN match no items.
+    ///
+    /// # Arguments
+    ///
+    /// * `cx`, `cy` - Center
+    /// * `rx`, `ry` - Semi-axes along x and y
+    /// * `visitor` - Result reader
+    pub fn within_ellipse<F>(&self, cx: TNumber, cy: TNumber, rx: TNumber, ry: TNumber, visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_radius(rx) || !is_valid_radius(ry) {
+            return;
+        }
+        // Normalized squared distance, scaling the ellipse to a unit circle
+        let scaled = |dx: TNumber, dy: TNumber| {
+            let sx = if dx == 0.0 { 0.0 } else { dx / rx };
+            let sy = if dy == 0.0 { 0.0 } else { dy / ry };
+            sx * sx + sy * sy
+        };
+        self.within_region(
+            |minx, miny, maxx, maxy| {
+                scaled(cx.clamp(minx, maxx) - cx, cy.clamp(miny, maxy) - cy) <= 1.0
+            },
+            |x, y| scaled(x - cx, y - cy) <= 1.0,
+            visitor,
+        );
+    }
+
+    /// Finds all items within a multipolygon
+    ///
+    /// Each polygon consists of an exterior ring followed by its interior rings (holes), with
+    /// the same nesting as GeoJSON coordinates. Items within holes are excluded. Rings may
+    /// be closed or open. Items exactly on a boundary may or may not be included.
+    ///
+    /// # Arguments
+    ///
+    /// * `polygons` - Polygons, each a list of rings
+    /// * `visitor` - Result reader
+    pub fn within_multipolygon<F>(&self, polygons: &[Vec<Vec<Point>>], visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        let components: Vec<(Bbox, &[Vec<Point>])> = polygons
+            .iter()
+            .filter(|rings| !rings.is_empty())
+            .map(|rings| (ring_bbox(&rings[0]), &rings[..]))
+            .collect();
+        self.within_region(
+            |minx, miny, maxx, maxy| {
+                components
+                    .iter()
+                    .any(|(b, _)| b[0] <= maxx && b[2] >= minx && b[1] <= maxy && b[3] >= miny)
+            },
+            |x, y| {
+                components.iter().any(|(b, rings)| {
+                    x >= b[0]
+                        && x <= b[2]
+                        && y >= b[1]
+                        && y <= b[3]
+                        && rings
+                            .iter()
+                            .filter(|ring| ring_contains(ring, x, y))
+                            .count()
+                            % 2
+                            == 1
+                })
+            },
+            visitor,
+        );
+    }
+}
+
+fn ring_bbox(ring: &[Point]) -> Bbox {
+    ring.iter().fold(
+        [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ],
+        |b, p| {
+            [
+                b[0].min(p[0]),
+                b[1].min(p[1]),
+                b[2].max(p[0]),
+                b[3].max(p[1]),
+            ]
+        },
+    )
+}
+
+/// Whether a point is inside a ring (crossing number test)
+fn ring_contains(ring: &[Point], x: TNumber, y: TNumber) -> bool {
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+    for (i, pi) in ring.iter().enumerate() {
+        let pj = ring[j];
+        if (pi[1] > y) != (pj[1] > y) && x < (pj[0] - pi[0]) * (y - pi[1]) / (pj[1] - pi[1]) + pi[0]
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Squared distance from a point to a segment
+fn sq_seg_dist(
+    x: TNumber,
+    y: TNumber,
+    x0: TNumber,
+    y0: TNumber,
+    x1: TNumber,
+    y1: TNumber,
+) -> TNumber {
+    let dx = x1 - x0;
+    let dy = y1 - y0;
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((x - x0) * dx + (y - y0) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    KDBush::sq_dist(x, y, x0 + t * dx, y0 + t * dy)
+}
+
+/// Whether a segment intersects a box (Liang-Barsky clipping)
+fn segment_intersects_box(
+    x0: TNumber,
+    y0: TNumber,
+    x1: TNumber,
+    y1: TNumber,
+    bbox: [TNumber; 4],
+) -> bool {
+    let dx = x1 - x0;
+    let dy = y1 - y0;
+    let mut t0: TNumber = 0.0;
+    let mut t1: TNumber = 1.0;
+    for &(p, q) in &[
+        (-dx, x0 - bbox[0]),
+        (dx, bbox[2] - x0),
+        (-dy, y0 - bbox[1]),
+        (dy, bbox[3] - y0),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    fn points() -> Vec<(f64, f64)> {
+        (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect()
+    }
+
+    #[test]
+    fn test_within_segment() {
+        let points = points();
+        for index in &[
+            KDBush::create(points.clone(), 8),
+            KDBush::create_with_bboxes(points.clone(), 8),
+        ] {
+            for &(x0, y0, x1, y1, r) in &[
+                (10.0, 10.0, 90.0, 70.0, 3.0),
+                (50.0, 0.0, 50.0, 100.0, 1.5),
+                (30.0, 30.0, 30.0, 30.0, 10.0),
+            ] {
+                let mut expected_ids: Vec<usize> = (0..points.len())
+                    .filter(|&i| sq_seg_dist(points[i].0, points[i].1, x0, y0, x1, y1) <= r * r)
+                    .collect();
+                expected_ids.sort();
+                let mut result = Vec::new();
+                index.within_segment(x0, y0, x1, y1, r, |id| result.push(id));
+                result.sort();
+                assert_eq!(expected_ids, result);
+                assert!(!result.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_within_multipolygon() {
+        let points = points();
+        let square = |minx: f64, miny: f64, maxx: f64, maxy: f64| {
+            vec![
+                [minx, miny],
+                [maxx, miny],
+                [maxx, maxy],
+                [minx, maxy],
+                [minx, miny],
+            ]
+        };
+        let polygons = vec![
+            vec![
+                square(10.5, 10.5, 60.5, 60.5),
+                square(20.5, 20.5, 30.5, 30.5),
+                square(40.5, 40.5, 50.5, 50.5),
+            ],
+            vec![square(70.5, 70.5, 90.5, 80.5)],
+        ];
+        let contained = |x: f64, y: f64| {
+            let within = |minx: f64, miny: f64, maxx: f64, maxy: f64| {
+                x >= minx && x <= maxx && y >= miny && y <= maxy
+            };
+            (within(10.5, 10.5, 60.5, 60.5)
+                && !within(20.5, 20.5, 30.5, 30.5)
+                && !within(40.5, 40.5, 50.5, 50.5))
+                || within(70.5, 70.5, 90.5, 80.5)
+        };
+        let mut expected_ids: Vec<usize> = (0..points.len())
+            .filter(|&i| contained(points[i].0, points[i].1))
+            .collect();
+        expected_ids.sort();
+        assert!(!expected_ids.is_empty());
+
+        for index in &[
+            KDBush::create(points.clone(), 8),
+            KDBush::create_with_bboxes(points.clone(), 8),
+        ] {
+            let mut result = Vec::new();
+            index.within_multipolygon(&polygons, |id| result.push(id));
+            result.sort();
+            assert_eq!(expected_ids, result);
+        }
+    }
+
+    #[test]
+    fn test_within_polygon() {
+        let points = points();
+        let ring = [[10.5, 10.5], [90.5, 20.5], [60.5, 80.5], [30.5, 50.5]];
+        let mut expected_ids: Vec<usize> = (0..points.len())
+            .filter(|&i| ring_contains(&ring, points[i].0, points[i].1))
+            .collect();
+        expected_ids.sort();
+        assert!(!expected_ids.is_empty());
+
+        let mut multipolygon_ids = Vec::new();
+        let polygons = vec![vec![ring.to_vec()]];
+        let index = KDBush::create(points.clone(), 8);
+        index.within_multipolygon(&polygons, |id| multipolygon_ids.push(id));
+        multipolygon_ids.sort();
+        assert_eq!(expected_ids, multipolygon_ids);
+
+        for index in &[
+            KDBush::create(points.clone(), 8),
+            KDBush::create_with_bboxes(points.clone(), 8),
+        ] {
+            let mut result = Vec::new();
+            index.within_polygon(&ring, |id| result.push(id));
+            result.sort();
+            assert_eq!(expected_ids, result);
+            index.within_polygon(&[], |_| panic!());
+        }
+    }
+
+    #[test]
+    fn test_within_ellipse() {
+        let points = points();
+        for index in &[
+            KDBush::create(points.clone(), 8),
+            KDBush::create_with_bboxes(points.clone(), 8),
+        ] {
+            for &(cx, cy, rx, ry) in &[(50.0, 40.0, 30.0, 8.0), (20.0, 70.0, 3.0, 25.0)] {
+                let mut expected_ids: Vec<usize> = (0..points.len())
+                    .filter(|&i| {
+                        let dx = (points[i].0 - cx) / rx;
+                        let dy = (points[i].1 - cy) / ry;
+                        dx * dx + dy * dy <= 1.0
+                    })
+                    .collect();
+                expected_ids.sort();
+                assert!(!expected_ids.is_empty());
+                let mut result = Vec::new();
+                index.within_ellipse(cx, cy, rx, ry, |id| result.push(id));
+                result.sort();
+                assert_eq!(expected_ids, result);
+            }
+            let mut result = Vec::new();
+            index.within_ellipse(0.0, 0.0, 0.0, 0.0, |id| result.push(id));
+            assert_eq!(result, vec![0]);
+            index.within_ellipse(0.0, 0.0, -1.0, 5.0, |_| panic!());
+        }
+    }
+
+    #[test]
+    fn test_segment_intersects_box() {
+        let bbox = [0.0, 0.0, 10.0, 10.0];
+        assert!(segment_intersects_box(-5.0, 5.0, 15.0, 5.0, bbox));
+        assert!(segment_intersects_box(2.0, 2.0, 3.0, 3.0, bbox));
+        assert!(!segment_intersects_box(-5.0, 11.0, 15.0, 11.0, bbox));
+        assert!(!segment_intersects_box(11.0, -5.0, 20.0, 0.0, bbox));
+    }
+
+    #[test]
+    fn test_geometry_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids = Vec::new();
+            index.within_segment(0.0, 0.0, 1.0, 2.0, 1.0, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+            let square = [[-5.0, -5.0], [10.0, -5.0], [10.0, 10.0], [-5.0, 10.0]];
+            let mut ids = Vec::new();
+            index.within_polygon(&square, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+            let mut ids = Vec::new();
+            index.within_ellipse(0.0, 0.0, 5.0, 5.0, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::kdbush::{KDBush, Point, PointReader, TIndex, TNumber};
+use crate::math;
+use alloc::vec::Vec;
+
+/// Spatial index which stores exactly coincident points as a single location
+///
+/// The tree only contains unique locations, each with the ids of all points at this
+/// location. Datasets with heavily stacked points get a smaller tree and avoid the many
+/// duplicate split values which degrade the construction.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{GroupedKDBush, DEFAULT_NODE_SIZE};
+///
+/// let points = vec![(54.0, 1.0), (97.0, 21.0), (54.0, 1.0)];
+/// let index = GroupedKDBush::create(points, DEFAULT_NODE_SIZE);
+/// index.range(50.0, 0.0, 60.0, 10.0, |location, ids| {
+///     assert_eq!(location, [54.0, 1.0]);
+///     assert_eq!(ids, &[0, 2]);
+/// });
+/// ```
+pub struct GroupedKDBush {
+    /// Index of the unique locations, with the location number as id
+    pub(crate) tree: KDBush,
+    /// Point ids, grouped by location
+    pub(crate) ids: Vec<TIndex>,
+    /// Start of the ids of each location in `ids`, plus the total number of ids
+    pub(crate) offsets: Vec<usize>,
+    pub(crate) locations: Vec<Point>,
+}
+
+impl GroupedKDBush {
+    /// Creates an index from the given points, grouping coincident points
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points reader
+    /// * `node_size` - Size of the KD-tree node
+    pub fn create<R: PointReader>(points: R, node_size: u8) -> GroupedKDBush {
+        let mut items: Vec<(Point, TIndex)> = Vec::with_capacity(points.size_hint());
+        // Adding zero turns -0.0 into 0.0, so both are grouped
+        points.visit_all(|id, x, y| items.push(([x + 0.0, y + 0.0], id)));
+        items.sort_by(|a, b| {
+            a.0[0]
+                .total_cmp(&b.0[0])
+                .then(a.0[1].total_cmp(&b.0[1]))
+                .then(a.1.cmp(&b.1))
+        });
+
+        let mut ids = Vec::with_capacity(items.len());
+        let mut offsets = Vec::new();
+        let mut locations: Vec<Point> = Vec::new();
+        for (p, id) in items {
+            let same = locations.last().is_some_and(|last| {
+                last[0].to_bits() == p[0].to_bits() && last[1].to_bits() == p[1].to_bits()
+            });
+            if !same {
+                offsets.push(ids.len());
+                locations.push(p);
+            }
+            ids.push(id);
+        }
+        offsets.push(ids.len());
+
+        let mut tree = KDBush::new(locations.len(), node_size);
+        for (i, p) in locations.iter().enumerate() {
+            tree.add_point(i, p[0], p[1]);
+        }
+        tree.build_index();
+        GroupedKDBush {
+            tree,
+            ids,
+            offsets,
+            locations,
+        }
+    }
+
+    /// Number of unique locations
+    pub fn locations(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Finds all locations within the given bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader, called with each location and the ids of its points
+    pub fn range<F>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        mut visitor: F,
+    ) where
+        F: FnMut(Point, &[TIndex]),
+    {
+        if self.locations.is_empty() {
+            return;
+        }
+        self.tree.range(minx, miny, maxx, maxy, |loc| {
+            visitor(self.locations[loc], self.location_ids(loc))
+        });
+    }
+
+    /// Finds all locations within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader, called with each location and the ids of its points
+    pub fn within<F>(&self, qx: TNumber, qy: TNumber, r: TNumber, mut visitor: F)
+    where
+        F: FnMut(Point, &[TIndex]),
+    {
+        if self.locations.is_empty() {
+            return;
+        }
+        self.tree.within(qx, qy, r, |loc| {
+            visitor(self.locations[loc], self.location_ids(loc))
+        });
+    }
+
+    /// Finds the `k` distinct locations nearest to the query point
+    ///
+    /// Coincident points count as a single location, so stacked points don't use up `k`.
+    /// Returns the locations with the ids of their points and their distance, ordered by
+    /// increasing distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Maximal number of locations
+    pub fn knn(&self, qx: TNumber, qy: TNumber, k: usize) -> Vec<(Point, &[TIndex], TNumber)> {
+        self.tree
+            .knn_idx(qx, qy, k)
+            .into_iter()
+            .map(|(i, d2)| {
+                let loc = self.tree.ids[i];
+                (self.locations[loc], self.location_ids(loc), math::sqrt(d2))
+            })
+            .collect()
+    }
+
+    pub(crate) fn location_ids(&self, loc: usize) -> &[TIndex] {
+        &self.ids[self.offsets[loc]..self.offsets[loc + 1]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_grouped() {
+        let points = vec![
+            (1.0, 1.0),
+            (2.0, 2.0),
+            (1.0, 1.0),
+            (-0.0, 3.0),
+            (0.0, 3.0),
+            (1.0, 1.0),
+        ];
+        let index = GroupedKDBush::create(points, 2);
+        assert_eq!(index.locations(), 3);
+
+        let mut result = Vec::new();
+        index.range(0.0, 0.0, 1.5, 5.0, |p, ids| result.push((p, ids.to_vec())));
+        result.sort_by(|a, b| a.0[1].partial_cmp(&b.0[1]).unwrap());
+        assert_eq!(
+            result,
+            vec![([1.0, 1.0], vec![0, 2, 5]), ([0.0, 3.0], vec![3, 4])]
+        );
+
+        let mut result = Vec::new();
+        index.within(2.0, 2.0, 0.5, |p, ids| result.push((p, ids.to_vec())));
+        assert_eq!(result, vec![([2.0, 2.0], vec![1])]);
+    }
+
+    #[test]
+    fn test_grouped_knn() {
+        let points = vec![
+            (1.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 1.0),
+            (4.0, 5.0),
+            (1.0, 1.0),
+            (10.0, 10.0),
+        ];
+        let index = GroupedKDBush::create(points, 2);
+        let result = index.knn(0.0, 1.0, 2);
+        assert_eq!(
+            result,
+            vec![
+                ([1.0, 1.0], &[0, 1, 2, 4][..], 1.0),
+                ([4.0, 5.0], &[3][..], 4.0 * 2f64.sqrt())
+            ]
+        );
+        assert_eq!(index.knn(0.0, 0.0, 10).len(), 3);
+        assert!(GroupedKDBush::create(vec![], 2).knn(0.0, 0.0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_grouped_empty() {
+        let index = GroupedKDBush::create(vec![], 2);
+        assert_eq!(index.locations(), 0);
+        index.range(0.0, 0.0, 1.0, 1.0, |_, _| panic!());
+        index.within(0.0, 0.0, 1.0, |_, _| panic!());
+    }
+
+    #[test]
+    fn test_grouped_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let grouped = GroupedKDBush::create(points, node_size);
+            let groups = grouped.knn(0.0, 0.0, 5);
+            assert_eq!(groups.len(), n);
+            assert!(groups.iter().all(|(_, ids, _)| ids.len() == 1));
+        }
+    }
+}
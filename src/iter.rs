@@ -0,0 +1,176 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, KDBush, TIndex, TNumber};
+use crate::stepped::Shape;
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+use core::ops::Range;
+
+/// Lazy iterator over the results of a query
+///
+/// Created by [`KDBush::range_iter`] and [`KDBush::within_iter`]. Tree nodes are only
+/// visited when more results are requested, so stopping early skips the remaining work.
+pub struct QueryIter<'a> {
+    index: &'a KDBush,
+    shape: Shape,
+    /// Nodes still to visit, as position ranges with their split axis
+    stack: Vec<(TIndex, TIndex, usize)>,
+    /// Positions of the leaf currently scanned
+    scan: Range<TIndex>,
+}
+
+impl<'a> QueryIter<'a> {
+    fn new(index: &'a KDBush, shape: Shape, valid: bool) -> QueryIter<'a> {
+        let mut stack = Vec::new();
+        if valid && !index.ids.is_empty() {
+            stack.push((0, index.ids.len() - 1, 0));
+        }
+        QueryIter {
+            index,
+            shape,
+            stack,
+            scan: 0..0,
+        }
+    }
+}
+
+impl<'a> Iterator for QueryIter<'a> {
+    type Item = TIndex;
+
+    fn next(&mut self) -> Option<TIndex> {
+        let index = self.index;
+        loop {
+            for i in self.scan.by_ref() {
+                if self.shape.contains(index.points[i][0], index.points[i][1]) {
+                    return Some(index.ids[i]);
+                }
+            }
+
+            let (left, right, axis) = self.stack.pop()?;
+            if right - left <= index.node_size as usize {
+                self.scan = left..right + 1;
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let p = index.points[m];
+            let (below, above) = self.shape.reaches(p[axis], axis);
+            // Right child first, so the left child is visited next
+            if above {
+                self.stack.push((m + 1, right, (axis + 1) % 2));
+            }
+            if below {
+                self.stack.push((left, m - 1, (axis + 1) % 2));
+            }
+            if self.shape.contains(p[0], p[1]) {
+                return Some(index.ids[m]);
+            }
+        }
+    }
+}
+
+impl<'a> FusedIterator for QueryIter<'a> {}
+
+impl KDBush {
+    /// Iterates over all items within the given bounding box
+    ///
+    /// Yields the same items as [`KDBush::range`], lazily.
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let first = index.range_iter(50.0, 0.0, 100.0, 40.0).find(|&id| id != 0);
+    /// assert!(first.is_some());
+    /// ```
+    pub fn range_iter(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+    ) -> QueryIter<'_> {
+        let valid = is_valid_bbox(minx, miny, maxx, maxy);
+        QueryIter::new(self, Shape::Range([minx, miny, maxx, maxy]), valid)
+    }
+
+    /// Iterates over all items within a given radius from the query point
+    ///
+    /// Yields the same items as [`KDBush::within`], lazily.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let ids: Vec<usize> = index.within_iter(60.0, 30.0, 10.0).collect();
+    /// assert_eq!(ids, vec![2]);
+    /// ```
+    pub fn within_iter(&self, qx: TNumber, qy: TNumber, r: TNumber) -> QueryIter<'_> {
+        let valid = is_valid_radius(r);
+        QueryIter::new(self, Shape::Within { qx, qy, r2: r * r }, valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_query_iter() {
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points, 4);
+
+        let mut expected = Vec::new();
+        index.range(10.0, 20.0, 60.0, 50.0, |id| expected.push(id));
+        let mut result: Vec<usize> = index.range_iter(10.0, 20.0, 60.0, 50.0).collect();
+        expected.sort();
+        result.sort();
+        assert_eq!(expected, result);
+
+        let mut expected = Vec::new();
+        index.within(40.0, 50.0, 15.0, |id| expected.push(id));
+        let mut result: Vec<usize> = index.within_iter(40.0, 50.0, 15.0).collect();
+        expected.sort();
+        result.sort();
+        assert_eq!(expected, result);
+
+        assert_eq!(index.within_iter(40.0, 50.0, 15.0).take(3).count(), 3);
+    }
+
+    #[test]
+    fn test_query_iter_degenerate() {
+        let index = KDBush::create(vec![(1.0, 1.0)], 4);
+        assert_eq!(index.within_iter(0.0, 0.0, -1.0).next(), None);
+        assert_eq!(index.range_iter(2.0, 0.0, 0.0, 2.0).next(), None);
+        let empty = KDBush::create(vec![], 4);
+        assert_eq!(empty.within_iter(0.0, 0.0, 1.0).next(), None);
+    }
+
+    #[test]
+    fn test_iter_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids: Vec<TIndex> = index.range_iter(-1.0, -1.0, 5.0, 5.0).collect();
+            ids.sort();
+            assert_eq!(ids, all);
+            let mut ids: Vec<TIndex> = index.within_iter(0.0, 0.0, 5.0).collect();
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::dual::{dual_traverse, sq_box_dist, DualTraversal, TreeNode};
+use crate::kdbush::{is_valid_radius, KDBush, TIndex, TNumber};
+
+/// Pairs of items within a distance
+struct PairsWithin<'a, F> {
+    query: &'a KDBush,
+    reference: &'a KDBush,
+    r2: TNumber,
+    /// Whether both trees are the same, reporting each pair only once
+    self_join: bool,
+    visitor: F,
+}
+
+impl<'a, F> DualTraversal for PairsWithin<'a, F>
+where
+    F: FnMut(TIndex, TIndex),
+{
+    fn prune(&mut self, query: &TreeNode, reference: &TreeNode) -> bool {
+        // A self-join only reports pairs with the query position before the reference position
+        (self.self_join && query.left >= reference.right)
+            || sq_box_dist(&query.bbox, &reference.bbox) > self.r2
+    }
+
+    fn process(&mut self, query: &TreeNode, reference: &TreeNode) {
+        for i in query.positions() {
+            let p = self.query.points[i];
+            for j in reference.positions() {
+                if self.self_join && i >= j {
+                    continue;
+                }
+                let q = self.reference.points[j];
+                if KDBush::sq_dist(p[0], p[1], q[0], q[1]) <= self.r2 {
+                    (self.visitor)(self.query.ids[i], self.reference.ids[j]);
+                }
+            }
+        }
+    }
+}
+
+impl KDBush {
+    /// Finds all pairs of items within a given distance of each other
+    ///
+    /// Uses a simultaneous traversal of the tree with itself, which is much faster than a
+    /// radius query per item. Each pair is reported once, in no particular order.
+    ///
+    /// # Arguments
+    ///
+    /// * `r` - Maximal distance
+    /// * `visitor` - Result reader, called with the ids of both items
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(0.0, 0.0), (5.0, 5.0), (0.5, 0.0)], DEFAULT_NODE_SIZE);
+    /// let mut pairs = Vec::new();
+    /// index.all_pairs_within(1.0, |a, b| pairs.push((a.min(b), a.max(b))));
+    /// assert_eq!(pairs, vec![(0, 2)]);
+    /// ```
+    pub fn all_pairs_within<F>(&self, r: TNumber, visitor: F)
+    where
+        F: FnMut(TIndex, TIndex),
+    {
+        if !is_valid_radius(r) {
+            return;
+        }
+        let mut traversal = PairsWithin {
+            query: self,
+            reference: self,
+            r2: r * r,
+            self_join: true,
+            visitor,
+        };
+        dual_traverse(self, self, &mut traversal);
+    }
+
+    /// Finds all pairs of an item of this index and an item of another index within a given distance
+    ///
+    /// Uses a simultaneous traversal of both trees. Pairs are reported in no particular order.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Joined index
+    /// * `r` - Maximal distance
+    /// * `visitor` - Result reader, called with the id of this index and the id of the other index
+    pub fn join<F>(&self, other: &KDBush, r: TNumber, visitor: F)
+    where
+        F: FnMut(TIndex, TIndex),
+    {
+        if !is_valid_radius(r) {
+            return;
+        }
+        let mut traversal = PairsWithin {
+            query: self,
+            reference: other,
+            r2: r * r,
+            self_join: false,
+            visitor,
+        };
+        dual_traverse(self, other, &mut traversal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    fn points(n: usize, offset: f64) -> Vec<(f64, f64)> {
+        (0..n)
+            .map(|i| ((i * 37 % 101) as f64 + offset, (i * 53 % 97) as f64))
+            .collect()
+    }
+
+    fn brute_force(a: &[(f64, f64)], b: &[(f64, f64)], r: f64) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for (i, p) in a.iter().enumerate() {
+            for (j, q) in b.iter().enumerate() {
+                if KDBush::sq_dist(p.0, p.1, q.0, q.1) <= r * r {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn test_all_pairs_within() {
+        let points = points(600, 0.0);
+        let mut expected: Vec<(usize, usize)> = brute_force(&points, &points, 2.5)
+            .into_iter()
+            .filter(|&(i, j)| i < j)
+            .collect();
+        expected.sort();
+        assert!(!expected.is_empty());
+        for index in &[
+            KDBush::create(points.clone(), 8),
+            KDBush::create_with_bboxes(points.clone(), 2),
+        ] {
+            let mut pairs = Vec::new();
+            index.all_pairs_within(2.5, |a, b| pairs.push((a.min(b), a.max(b))));
+            pairs.sort();
+            assert_eq!(expected, pairs);
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        let a = points(400, 0.0);
+        let b = points(300, 0.7);
+        let mut expected = brute_force(&a, &b, 1.5);
+        expected.sort();
+        assert!(!expected.is_empty());
+        let index_a = KDBush::create(a, 8);
+        let index_b = KDBush::create(b, 4);
+        let mut pairs = Vec::new();
+        index_a.join(&index_b, 1.5, |i, j| pairs.push((i, j)));
+        pairs.sort();
+        assert_eq!(expected, pairs);
+
+        let empty = KDBush::create(Vec::new(), 8);
+        index_a.join(&empty, 1.5, |_, _| panic!());
+        index_a.join(&index_b, -1.0, |_, _| panic!());
+        empty.all_pairs_within(1.0, |_, _| panic!());
+    }
+
+    #[test]
+    fn test_join_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let index = KDBush::create(points, node_size);
+            let mut pairs = 0;
+            index.all_pairs_within(5.0, |_, _| pairs += 1);
+            assert_eq!(pairs, n * n.saturating_sub(1) / 2);
+            let mut pairs = 0;
+            index.join(&index, 5.0, |_, _| pairs += 1);
+            assert_eq!(pairs, n * n);
+        }
+    }
+}
@@ -1,13 +1,150 @@
-use std::cmp;
-use std::f64;
+use crate::lookup::CoordLookup;
+use crate::scan::{scan_range, scan_within};
+use crate::sort::{Items, SortOptions};
+use alloc::borrow::Cow;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::{Infallible, TryFrom};
+use core::fmt::Debug;
+use core::ops::Add;
 
-type TIndex = usize;
-type TNumber = f64;
-type Point = [TNumber; 2];
+pub(crate) type TIndex = usize;
+pub(crate) type TNumber = f64;
+pub(crate) type Point = [TNumber; 2];
+pub(crate) type Bbox = [TNumber; 4];
 
 pub const DEFAULT_NODE_SIZE: u8 = 64;
 
-/// Input points reader trait
+/// Coordinate type of an index
+///
+/// Implemented for `f64`, `f32`, `i32` and `u32`. Squared distances of integer coordinates
+/// are computed exactly in `u128`, `f32` distances are computed in `f64`.
+pub trait KdNum: Copy + PartialOrd + Debug + Send + Sync {
+    /// Type of squared distances
+    type Dist: Copy + PartialOrd + Add<Output = Self::Dist>;
+
+    const ZERO: Self;
+
+    /// Squared difference of two coordinates
+    fn sq_diff(a: Self, b: Self) -> Self::Dist;
+
+    /// Total order, also for NaN values
+    fn total_cmp(&self, other: &Self) -> Ordering;
+
+    /// Whether the value is infinite
+    fn is_infinite(self) -> bool {
+        false
+    }
+}
+
+impl KdNum for f64 {
+    type Dist = f64;
+    const ZERO: f64 = 0.0;
+    fn sq_diff(a: f64, b: f64) -> f64 {
+        (a - b) * (a - b)
+    }
+    fn total_cmp(&self, other: &f64) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+    fn is_infinite(self) -> bool {
+        f64::is_infinite(self)
+    }
+}
+
+impl KdNum for f32 {
+    type Dist = f64;
+    const ZERO: f32 = 0.0;
+    fn sq_diff(a: f32, b: f32) -> f64 {
+        let d = f64::from(a) - f64::from(b);
+        d * d
+    }
+    fn total_cmp(&self, other: &f32) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+    fn is_infinite(self) -> bool {
+        f32::is_infinite(self)
+    }
+}
+
+impl KdNum for i32 {
+    type Dist = u128;
+    const ZERO: i32 = 0;
+    fn sq_diff(a: i32, b: i32) -> u128 {
+        let d = u128::from(a.abs_diff(b));
+        d * d
+    }
+    fn total_cmp(&self, other: &i32) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl KdNum for u32 {
+    type Dist = u128;
+    const ZERO: u32 = 0;
+    fn sq_diff(a: u32, b: u32) -> u128 {
+        let d = u128::from(a.abs_diff(b));
+        d * d
+    }
+    fn total_cmp(&self, other: &u32) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+/// Whether a bounding box may contain items, i.e. is not inverted and has no NaN bounds
+pub(crate) fn is_valid_bbox<T: PartialOrd>(minx: T, miny: T, maxx: T, maxy: T) -> bool {
+    minx <= maxx && miny <= maxy
+}
+
+/// Whether a radius may contain items, i.e. is neither negative nor NaN
+pub(crate) fn is_valid_radius<T: KdNum>(r: T) -> bool {
+    r >= T::ZERO
+}
+
+/// Storage type of the item ids of an index
+///
+/// Implemented for `usize`, `u32` and `u16`. Narrower ids need less memory, like the
+/// `Uint16Array` and `Uint32Array` ids of the JavaScript kdbush. Ids are always passed
+/// to and reported from the index as `usize`.
+pub trait KdId: Copy + Debug + Send + Sync {
+    /// Converts an id for storage
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id doesn't fit into the storage type.
+    fn from_usize(id: usize) -> Self;
+
+    fn to_usize(self) -> usize;
+}
+
+impl KdId for usize {
+    fn from_usize(id: usize) -> usize {
+        id
+    }
+    fn to_usize(self) -> usize {
+        self
+    }
+}
+
+impl KdId for u32 {
+    fn from_usize(id: usize) -> u32 {
+        u32::try_from(id).expect("id exceeds u32")
+    }
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl KdId for u16 {
+    fn from_usize(id: usize) -> u16 {
+        u16::try_from(id).expect("id exceeds u16")
+    }
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+/// Input points reader trait, generic over the coordinate type
 ///
 /// # Example
 ///
@@ -26,20 +163,20 @@ pub const DEFAULT_NODE_SIZE: u8 = 64;
 ///    }
 /// }
 /// ```
-pub trait PointReader {
+pub trait PointReader<T = TNumber> {
     fn size_hint(&self) -> usize;
     fn visit_all<F>(&self, visitor: F)
     where
-        F: FnMut(usize, f64, f64);
+        F: FnMut(usize, T, T);
 }
 
-impl PointReader for Vec<(f64, f64)> {
+impl<T: KdNum> PointReader<T> for Vec<(T, T)> {
     fn size_hint(&self) -> usize {
         self.len()
     }
     fn visit_all<F>(&self, mut visitor: F)
     where
-        F: FnMut(usize, f64, f64),
+        F: FnMut(usize, T, T),
     {
         for (i, point) in self.iter().enumerate() {
             visitor(i, point.0, point.1);
@@ -48,32 +185,69 @@ impl PointReader for Vec<(f64, f64)> {
 }
 
 /// A very fast static spatial index for 2D points based on a flat KD-tree
-pub struct KDBush {
-    ids: Vec<TIndex>,
-    points: Vec<Point>,
-    node_size: u8,
+///
+/// Coordinates are `f64` by default, indices with `f32`, `i32` or `u32` coordinates (see
+/// [`KdNum`]) support the basic construction and queries:
+///
+/// ```
+/// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+///
+/// let pixels: Vec<(u32, u32)> = vec![(12, 40), (200, 17), (13, 41)];
+/// let index = KDBush::create(pixels, DEFAULT_NODE_SIZE);
+/// let mut found = Vec::new();
+/// index.within(12, 40, 2, |id| found.push(id));
+/// assert_eq!(found.len(), 2);
+/// ```
+///
+/// Ids are stored as `usize` by default. Indices created with
+/// [`KDBush::create_with_id_type`] store them as `u32` or `u16` (see [`KdId`]):
+///
+/// ```
+/// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+///
+/// let points = vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)];
+/// let index = KDBush::<f64, u32>::create_with_id_type(points, DEFAULT_NODE_SIZE);
+/// let mut found = Vec::new();
+/// index.within(60.0, 30.0, 10.0, |id| found.push(id));
+/// assert_eq!(found, vec![2]);
+/// ```
+///
+/// Queries don't modify the index, so a visitor may issue further queries on the same
+/// index, e.g. to expand a neighborhood:
+///
+/// ```
+/// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+///
+/// let points = vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (10.0, 0.0)];
+/// let index = KDBush::create(points.clone(), DEFAULT_NODE_SIZE);
+/// let mut reached = vec![false; points.len()];
+/// index.within(0.0, 0.0, 1.0, |id| {
+///     let (x, y) = points[id];
+///     index.within(x, y, 1.0, |neighbor| reached[neighbor] = true);
+/// });
+/// assert_eq!(reached, vec![true, true, true, false]);
+/// ```
+pub struct KDBush<T = TNumber, I = TIndex> {
+    pub(crate) ids: Vec<I>,
+    pub(crate) points: Vec<[T; 2]>,
+    pub(crate) node_size: u8,
+    /// Bounding box of each node, stored at the position of the node's median
+    pub(crate) bboxes: Option<Vec<Bbox>>,
+    /// Ids by exact coordinates
+    pub(crate) lookup: Option<CoordLookup>,
+    /// EPSG code of the coordinate reference system
+    pub(crate) crs: Option<u32>,
 }
 
-impl KDBush {
+impl<T: KdNum> KDBush<T> {
     /// Creates an index from the given points
     ///
     /// # Arguments
     ///
     /// * `points` - Input points reader
     /// * `node_size` - Size of the KD-tree node, 64 by default. Higher means faster indexing but slower search, and vise versa
-    pub fn create<R: PointReader>(points: R, node_size: u8) -> KDBush {
-        let mut kdbush = KDBush {
-            ids: Vec::with_capacity(points.size_hint()),
-            points: Vec::with_capacity(points.size_hint()),
-            node_size: node_size,
-        };
-        points.visit_all(|id, x, y| {
-            kdbush.points.push([x, y]);
-            kdbush.ids.push(id);
-        });
-        let size = kdbush.points.len();
-        kdbush.sort_kd(0, size.saturating_sub(1), 0);
-        kdbush
+    pub fn create<R: PointReader<T>>(points: R, node_size: u8) -> KDBush<T> {
+        KDBush::create_with_id_type(points, node_size)
     }
 
     /// Creates an empty index
@@ -81,75 +255,255 @@ impl KDBush {
     /// # Arguments
     ///
     /// * `size_hint` - Number of points to add (maybe 0, if unkown).
-    /// * `node_size` - Size of the KD-tree node.
-    pub fn new(size_hint: usize, node_size: u8) -> KDBush {
+    /// * `node_size` - Size of the KD-tree node, a node size of 0 is treated as 1.
+    pub fn new(size_hint: usize, node_size: u8) -> KDBush<T> {
+        KDBush::new_with_id_type(size_hint, node_size)
+    }
+}
+
+impl<T: KdNum, I: KdId> KDBush<T, I> {
+    /// Creates an index from the given points, storing the ids as `I`
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points reader
+    /// * `node_size` - Size of the KD-tree node
+    ///
+    /// # Panics
+    ///
+    /// Panics if an id doesn't fit into `I`.
+    pub fn create_with_id_type<R: PointReader<T>>(points: R, node_size: u8) -> KDBush<T, I> {
+        let mut kdbush = KDBush::new_with_id_type(points.size_hint(), node_size);
+        points.visit_all(|id, x, y| kdbush.add_point(id, x, y));
+        kdbush.sort_kd(cfg!(feature = "parallel"), false);
+        kdbush
+    }
+
+    /// Creates an empty index, storing the ids as `I`
+    ///
+    /// # Arguments
+    ///
+    /// * `size_hint` - Number of points to add (maybe 0, if unkown).
+    /// * `node_size` - Size of the KD-tree node, a node size of 0 is treated as 1.
+    pub fn new_with_id_type(size_hint: usize, node_size: u8) -> KDBush<T, I> {
         KDBush {
             ids: Vec::with_capacity(size_hint),
             points: Vec::with_capacity(size_hint),
-            node_size: node_size,
+            node_size: node_size.max(1),
+            bboxes: None,
+            lookup: None,
+            crs: None,
         }
     }
 
     /// Add point to index
-    pub fn add_point(&mut self, id: usize, x: f64, y: f64) {
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id doesn't fit into the id type of the index.
+    pub fn add_point(&mut self, id: usize, x: T, y: T) {
         self.points.push([x, y]);
-        self.ids.push(id);
+        self.ids.push(I::from_usize(id));
     }
 
     /// Build index
     pub fn build_index(&mut self) {
-        let size = self.points.len();
-        self.sort_kd(0, size.saturating_sub(1), 0);
+        self.build_index_with(cfg!(feature = "parallel"), false);
+    }
+
+    /// Build index, sorting large subtrees on multiple threads if `parallel` is set and
+    /// ordering equal coordinates by id if `stable` is set
+    pub(crate) fn build_index_with(&mut self, parallel: bool, stable: bool) {
+        self.sort_kd(parallel, stable);
+        self.bboxes = None;
+        self.lookup = None;
+    }
+
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether the index contains no items
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Size of the KD-tree node
+    pub fn node_size(&self) -> u8 {
+        self.node_size
+    }
+
+    /// Ids in the order of the tree
+    pub fn ids(&self) -> &[I] {
+        &self.ids
+    }
+
+    /// Points `[x, y]` in the order of the tree, matching [`KDBush::ids`]
+    pub fn points(&self) -> &[[T; 2]] {
+        &self.points
+    }
+
+    /// Interleaved coordinates `[x0, y0, x1, y1, ...]` in the order of the tree
+    pub fn coords(&self) -> &[T] {
+        self.points.as_flattened()
     }
 
     /// Finds all items within the given bounding box
     ///
+    /// An inverted bounding box (min > max) or NaN bounds match no items.
+    ///
     /// # Arguments
     ///
     /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
     /// * `visitor` - Result reader
-    pub fn range<F>(
-        &self,
-        minx: TNumber,
-        miny: TNumber,
-        maxx: TNumber,
-        maxy: TNumber,
-        mut visitor: F,
-    ) where
+    pub fn range<F>(&self, minx: T, miny: T, maxx: T, maxy: T, mut visitor: F)
+    where
         F: FnMut(TIndex),
     {
+        if self.ids.is_empty() || !is_valid_bbox(minx, miny, maxx, maxy) {
+            return;
+        }
         self.range_idx(
             minx,
             miny,
             maxx,
             maxy,
-            &mut visitor,
+            &mut |i| visitor(self.ids[i].to_usize()),
             0,
             self.ids.len() - 1,
             0,
         );
     }
 
+    /// Finds all items within the given bounding box, ordered by their coordinate along an axis
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `axis` - Sort axis, 0 for x and 1 for y
+    /// * `visitor` - Result reader
+    ///
+    /// # Panics
+    ///
+    /// Panics if `axis` is neither 0 nor 1.
+    pub fn range_sorted<F>(&self, minx: T, miny: T, maxx: T, maxy: T, axis: usize, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        assert!(axis < 2, "sort axis must be 0 or 1, got {}", axis);
+        if self.ids.is_empty() || !is_valid_bbox(minx, miny, maxx, maxy) {
+            return;
+        }
+        let found = self.range_sorted_idx([minx, miny, maxx, maxy], axis);
+        for i in found {
+            visitor(self.ids[i].to_usize());
+        }
+    }
+
     /// Finds all items within a given radius from the query point
     ///
+    /// A radius of 0 matches items exactly at the query point, an infinite radius matches
+    /// all items without searching the tree. Negative or NaN radii match no items.
+    ///
     /// # Arguments
     ///
     /// * `qx`, `qy` - Query point
     /// * `r` - Radius
     /// * `visitor` - Result reader
-    pub fn within<F>(&self, qx: TNumber, qy: TNumber, r: TNumber, mut visitor: F)
+    pub fn within<F>(&self, qx: T, qy: T, r: T, mut visitor: F)
     where
         F: FnMut(TIndex),
     {
-        self.within_idx(qx, qy, r, &mut visitor, 0, self.ids.len() - 1, 0);
+        if self.ids.is_empty() || !is_valid_radius(r) {
+            return;
+        }
+        if r.is_infinite() {
+            self.ids.iter().for_each(|&id| visitor(id.to_usize()));
+            return;
+        }
+        self.within_idx(
+            qx,
+            qy,
+            T::sq_diff(r, T::ZERO),
+            &mut |i| visitor(self.ids[i].to_usize()),
+            0,
+            self.ids.len() - 1,
+            0,
+        );
+    }
+
+    /// Finds all items within the given bounding box, reporting their coordinates
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader, called with the id and the coordinates of each item
+    pub fn range_with_points<F>(&self, minx: T, miny: T, maxx: T, maxy: T, mut visitor: F)
+    where
+        F: FnMut(TIndex, [T; 2]),
+    {
+        if self.ids.is_empty() || !is_valid_bbox(minx, miny, maxx, maxy) {
+            return;
+        }
+        self.range_idx(
+            minx,
+            miny,
+            maxx,
+            maxy,
+            &mut |i| visitor(self.ids[i].to_usize(), self.points[i]),
+            0,
+            self.ids.len() - 1,
+            0,
+        );
     }
 
-    fn range_idx<F>(
+    /// Finds all items within a given radius from the query point, reporting their squared distances
+    ///
+    /// Like with [`KDBush::within`], negative or NaN radii match no items.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader, called with the id and the squared distance of each item
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// index.within_with_dist(62.0, 31.0, 10.0, |id, d2| {
+    ///     assert_eq!((id, d2), (2, 25.0));
+    /// });
+    /// ```
+    pub fn within_with_dist<F>(&self, qx: T, qy: T, r: T, mut visitor: F)
+    where
+        F: FnMut(TIndex, T::Dist),
+    {
+        if self.ids.is_empty() || !is_valid_radius(r) {
+            return;
+        }
+        self.within_dist_idx(
+            qx,
+            qy,
+            T::sq_diff(r, T::ZERO),
+            &mut |i, d2| visitor(self.ids[i].to_usize(), d2),
+            0,
+            self.ids.len() - 1,
+            0,
+        );
+    }
+
+    /// Finds all items within the given bounding box, visiting their positions in the sorted arrays
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn range_idx<F>(
         &self,
-        minx: TNumber,
-        miny: TNumber,
-        maxx: TNumber,
-        maxy: TNumber,
+        minx: T,
+        miny: T,
+        maxx: T,
+        maxy: T,
         visitor: &mut F,
         left: TIndex,
         right: TIndex,
@@ -157,50 +511,63 @@ impl KDBush {
     ) where
         F: FnMut(TIndex),
     {
-        if right - left <= self.node_size as usize {
-            for i in left..right + 1 {
-                let x = self.points[i][0];
-                let y = self.points[i][1];
-                if x >= minx && x <= maxx && y >= miny && y <= maxy {
-                    visitor(self.ids[i]);
-                }
-            }
-            return;
-        }
+        let bbox = [minx, miny, maxx, maxy];
+        let mut visitor = |i| {
+            visitor(i);
+            Ok::<(), Infallible>(())
+        };
+        self.try_range_idx(bbox, &mut visitor, left, right, axis)
+            .unwrap_or_else(|never| match never {});
+    }
 
-        let m = (left + right) >> 1;
-        let x = self.points[m][0];
-        let y = self.points[m][1];
+    /// Finds all items within the given bounding box, visiting their positions until the visitor fails
+    pub(crate) fn try_range_idx<E, F>(
+        &self,
+        bbox: [T; 4],
+        visitor: &mut F,
+        left: TIndex,
+        right: TIndex,
+        axis: usize,
+    ) -> Result<(), E>
+    where
+        F: FnMut(TIndex) -> Result<(), E>,
+    {
+        let [minx, miny, maxx, maxy] = bbox;
+        let mut stack = vec![(left, right, axis)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                scan_range(&self.points[left..right + 1], left, bbox, visitor)?;
+                continue;
+            }
 
-        if x >= minx && x <= maxx && y >= miny && y <= maxy {
-            visitor(self.ids[m]);
-        }
+            let m = (left + right) >> 1;
+            let x = self.points[m][0];
+            let y = self.points[m][1];
 
-        let lte = if axis == 0 { minx <= x } else { miny <= y };
-        if lte {
-            self.range_idx(minx, miny, maxx, maxy, visitor, left, m - 1, (axis + 1) % 2);
-        }
+            if x >= minx && x <= maxx && y >= miny && y <= maxy {
+                visitor(m)?;
+            }
 
-        let gte = if axis == 0 { maxx >= x } else { maxy >= y };
-        if gte {
-            self.range_idx(
-                minx,
-                miny,
-                maxx,
-                maxy,
-                visitor,
-                m + 1,
-                right,
-                (axis + 1) % 2,
-            );
+            // Right child first, so the left child is visited next
+            let gte = if axis == 0 { maxx >= x } else { maxy >= y };
+            if gte {
+                stack.push((m + 1, right, (axis + 1) % 2));
+            }
+            let lte = if axis == 0 { minx <= x } else { miny <= y };
+            if lte {
+                stack.push((left, m - 1, (axis + 1) % 2));
+            }
         }
+        Ok(())
     }
 
-    fn within_idx<F>(
+    /// Finds all items within a given squared radius, visiting their positions in the sorted arrays
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn within_idx<F>(
         &self,
-        qx: TNumber,
-        qy: TNumber,
-        r: TNumber,
+        qx: T,
+        qy: T,
+        r2: T::Dist,
         visitor: &mut F,
         left: TIndex,
         right: TIndex,
@@ -208,123 +575,358 @@ impl KDBush {
     ) where
         F: FnMut(TIndex),
     {
-        let r2 = r * r;
-
-        if right - left <= self.node_size as usize {
-            for i in left..right + 1 {
-                let x = self.points[i][0];
-                let y = self.points[i][1];
-                if KDBush::sq_dist(x, y, qx, qy) <= r2 {
-                    visitor(self.ids[i]);
-                }
+        self.within_dist_idx(qx, qy, r2, &mut |i, _| visitor(i), left, right, axis);
+    }
+
+    /// Finds all items within a given squared radius, visiting their positions and squared distances
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn within_dist_idx<F>(
+        &self,
+        qx: T,
+        qy: T,
+        r2: T::Dist,
+        visitor: &mut F,
+        left: TIndex,
+        right: TIndex,
+        axis: usize,
+    ) where
+        F: FnMut(TIndex, T::Dist),
+    {
+        let mut visitor = |i, d2| {
+            visitor(i, d2);
+            Ok::<(), Infallible>(())
+        };
+        self.try_within_dist_idx([qx, qy], r2, &mut visitor, left, right, axis)
+            .unwrap_or_else(|never| match never {});
+    }
+
+    /// Finds all items within a given squared radius, visiting their positions and squared
+    /// distances until the visitor fails
+    pub(crate) fn try_within_dist_idx<E, F>(
+        &self,
+        q: [T; 2],
+        r2: T::Dist,
+        visitor: &mut F,
+        left: TIndex,
+        right: TIndex,
+        axis: usize,
+    ) -> Result<(), E>
+    where
+        F: FnMut(TIndex, T::Dist) -> Result<(), E>,
+    {
+        let [qx, qy] = q;
+        let mut stack = vec![(left, right, axis)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                scan_within(&self.points[left..right + 1], left, q, r2, visitor)?;
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let x = self.points[m][0];
+            let y = self.points[m][1];
+
+            let d2 = T::sq_diff(x, qx) + T::sq_diff(y, qy);
+            if d2 <= r2 {
+                visitor(m, d2)?;
+            }
+
+            // Compares squared axis distances, so pruning is consistent with the distance test
+            let (q, split) = if axis == 0 { (qx, x) } else { (qy, y) };
+            let near = T::sq_diff(q, split) <= r2;
+            // Right child first, so the left child is visited next
+            if q >= split || near {
+                stack.push((m + 1, right, (axis + 1) % 2));
+            }
+            if q <= split || near {
+                stack.push((left, m - 1, (axis + 1) % 2));
             }
-            return;
         }
+        Ok(())
+    }
 
-        let m = (left + right) >> 1;
-        let x = self.points[m][0];
-        let y = self.points[m][1];
+    /// Positions of the items within the bounding box, sorted along `sort_axis`
+    ///
+    /// Traverses the tree in order, so items left of a median precede it along the split
+    /// axis and only the runs of subtrees split along the other axis need to be merged.
+    fn range_sorted_idx(&self, bbox: [T; 4], sort_axis: usize) -> Vec<TIndex> {
+        enum Step {
+            Node(TIndex, TIndex, usize),
+            Item(TIndex),
+            /// Merges the sorted runs found from the given position on
+            Merge(usize),
+        }
+        let [minx, miny, maxx, maxy] = bbox;
+        let inside = |p: [T; 2]| p[0] >= minx && p[0] <= maxx && p[1] >= miny && p[1] <= maxy;
+        let mut found = Vec::new();
+        let mut stack = vec![Step::Node(0, self.ids.len() - 1, 0)];
+        while let Some(step) = stack.pop() {
+            let (left, right, axis) = match step {
+                Step::Node(left, right, axis) => (left, right, axis),
+                Step::Item(m) => {
+                    found.push(m);
+                    continue;
+                }
+                Step::Merge(start) => {
+                    self.sort_found(&mut found[start..], sort_axis);
+                    continue;
+                }
+            };
+            let start = found.len();
 
-        if KDBush::sq_dist(x, y, qx, qy) <= r2 {
-            visitor(self.ids[m]);
+            if right - left <= self.node_size as usize {
+                found.extend((left..right + 1).filter(|&i| inside(self.points[i])));
+                self.sort_found(&mut found[start..], sort_axis);
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let p = self.points[m];
+            // Steps in reverse order: left subtree, median, right subtree, merge
+            if axis != sort_axis {
+                stack.push(Step::Merge(start));
+            }
+            if bbox[2 + axis] >= p[axis] {
+                stack.push(Step::Node(m + 1, right, (axis + 1) % 2));
+            }
+            if inside(p) {
+                stack.push(Step::Item(m));
+            }
+            if bbox[axis] <= p[axis] {
+                stack.push(Step::Node(left, m - 1, (axis + 1) % 2));
+            }
         }
+        found
+    }
+
+    fn sort_found(&self, found: &mut [TIndex], axis: usize) {
+        found.sort_by(|&a, &b| {
+            self.points[a][axis]
+                .partial_cmp(&self.points[b][axis])
+                .unwrap_or(Ordering::Equal)
+        });
+    }
 
-        let lte = if axis == 0 { qx - r <= x } else { qy - r <= y };
-        if lte {
-            self.within_idx(qx, qy, r, visitor, left, m - 1, (axis + 1) % 2);
+    /// Sorted arrays of ids and coordinates
+    pub(crate) fn items(&mut self) -> Items<'_, T, I> {
+        Items {
+            ids: &mut self.ids,
+            points: &mut self.points,
         }
+    }
+
+    /// Sorts the items into a KD-tree
+    fn sort_kd(&mut self, parallel: bool, stable: bool) {
+        let options = SortOptions {
+            node_size: self.node_size as usize,
+            parallel,
+            stable,
+        };
+        self.items().sort_kd(options, 0);
+    }
+}
 
-        let gte = if axis == 0 { qx + r >= x } else { qy + r >= y };
-        if gte {
-            self.within_idx(qx, qy, r, visitor, m + 1, right, (axis + 1) % 2);
+impl KDBush {
+    /// Creates an index from the given points, recording the bounding box of each node
+    ///
+    /// Node bounding boxes need additional memory, but allow [`KDBush::within_region`]
+    /// to prune far more subtrees than the split values alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points reader
+    /// * `node_size` - Size of the KD-tree node
+    pub fn create_with_bboxes<R: PointReader>(points: R, node_size: u8) -> KDBush {
+        let mut kdbush = KDBush::create(points, node_size);
+        kdbush.build_bboxes();
+        kdbush
+    }
+
+    /// Build index, recording the bounding box of each node
+    pub fn build_index_with_bboxes(&mut self) {
+        self.build_index();
+        self.build_bboxes();
+    }
+
+    /// Finds all items within a given squared radius from the query point
+    ///
+    /// Items are matched by comparing their squared distance with `r2`, without any
+    /// square root. Like with [`KDBush::within`], negative or NaN values match no items.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r2` - Squared radius
+    /// * `visitor` - Result reader
+    pub fn within_sq<F>(&self, qx: TNumber, qy: TNumber, r2: TNumber, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.ids.is_empty() || !is_valid_radius(r2) {
+            return;
+        }
+        if r2 == f64::INFINITY {
+            self.ids.iter().for_each(|&id| visitor(id));
+            return;
         }
+        self.within_idx(
+            qx,
+            qy,
+            r2,
+            &mut |i| visitor(self.ids[i]),
+            0,
+            self.ids.len() - 1,
+            0,
+        );
     }
 
-    fn sort_kd(&mut self, left: TIndex, right: TIndex, axis: u8) {
-        if right - left <= self.node_size as usize {
+    /// Finds all items within an arbitrary region
+    ///
+    /// Nodes are pruned by their extent, which is the recorded bounding box if the index was
+    /// built with bboxes, or the area bounded by the split values otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `intersects` - Returns whether the region intersects the box `minx`, `miny`, `maxx`, `maxy`
+    /// * `contains` - Returns whether the region contains the point `x`, `y`
+    /// * `visitor` - Result reader
+    pub fn within_region<I, C, F>(&self, intersects: I, contains: C, mut visitor: F)
+    where
+        I: Fn(TNumber, TNumber, TNumber, TNumber) -> bool,
+        C: Fn(TNumber, TNumber) -> bool,
+        F: FnMut(TIndex),
+    {
+        if self.ids.is_empty() {
             return;
         }
-        let m: TIndex = (left + right) >> 1;
-        if axis == 0 {
-            self.select(m, left, right, 0);
-        } else {
-            self.select(m, left, right, 1);
-        }
-        self.sort_kd(left, m.saturating_sub(1), (axis + 1) % 2);
-        self.sort_kd(m + 1, right, (axis + 1) % 2);
-    }
-
-    fn select(&mut self, k: TIndex, mut left: TIndex, mut right: TIndex, axis: usize) {
-        while right > left {
-            if right - left > 600 {
-                let n = (right - left + 1) as f64;
-                let m = (k - left + 1) as f64;
-                let z = f64::ln(n);
-                let s = 0.5 * f64::exp(2.0 * z / 3.0);
-                let r = k as f64 - m * s / n
-                    + 0.5
-                        * f64::sqrt(z * s * (1.0 - s / n))
-                        * (if 2.0 * m < n { -1.0 } else { 1.0 });
-                self.select(
-                    k,
-                    cmp::max(left, r as usize),
-                    cmp::min(right, (r + s) as usize),
-                    axis,
-                );
+        let bounds = [
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::INFINITY,
+        ];
+        let mut stack = vec![(0, self.ids.len() - 1, 0, bounds)];
+        while let Some((left, right, axis, bounds)) = stack.pop() {
+            let m = (left + right) >> 1;
+            let bounds = match self.bboxes {
+                Some(ref bboxes) => bboxes[m],
+                None => bounds,
+            };
+            if !intersects(bounds[0], bounds[1], bounds[2], bounds[3]) {
+                continue;
             }
 
-            let t = self.points[k][axis];
-            let mut i = left;
-            let mut j = right;
-
-            self.swap_item(left, k);
-            if self.points[right][axis] > t {
-                self.swap_item(left, right);
+            if right - left <= self.node_size as usize {
+                for i in left..right + 1 {
+                    if contains(self.points[i][0], self.points[i][1]) {
+                        visitor(self.ids[i]);
+                    }
+                }
+                continue;
             }
 
-            while i < j {
-                self.swap_item(i, j);
-                i += 1;
-                j -= 1;
-                while self.points[i][axis] < t {
-                    i += 1;
-                }
-                while self.points[j][axis] > t {
-                    j -= 1;
-                }
+            let x = self.points[m][0];
+            let y = self.points[m][1];
+
+            if contains(x, y) {
+                visitor(self.ids[m]);
             }
 
-            if self.points[left][axis] == t {
-                self.swap_item(left, j);
+            let mut lower = bounds;
+            let mut upper = bounds;
+            if axis == 0 {
+                lower[2] = x;
+                upper[0] = x;
             } else {
-                j += 1;
-                self.swap_item(j, right);
+                lower[3] = y;
+                upper[1] = y;
             }
+            // Right child first, so the left child is visited next
+            stack.push((m + 1, right, (axis + 1) % 2, upper));
+            stack.push((left, m - 1, (axis + 1) % 2, lower));
+        }
+    }
+
+    pub(crate) fn build_bboxes(&mut self) {
+        self.bboxes = Some(self.compute_bboxes());
+    }
+
+    /// Recorded node bounding boxes, or newly computed ones if the index has none
+    pub(crate) fn node_bboxes(&self) -> Cow<'_, [Bbox]> {
+        match self.bboxes {
+            Some(ref bboxes) => Cow::Borrowed(bboxes),
+            None => Cow::Owned(self.compute_bboxes()),
+        }
+    }
 
-            if j <= k {
-                left = j + 1;
+    fn compute_bboxes(&self) -> Vec<Bbox> {
+        let mut bboxes = vec![[0.0; 4]; self.points.len()];
+        if self.points.is_empty() {
+            return bboxes;
+        }
+        // Nodes in depth-first order, so in reverse each node follows its children
+        let mut nodes = Vec::new();
+        let mut stack = vec![(0, self.points.len() - 1)];
+        while let Some((left, right)) = stack.pop() {
+            nodes.push((left, right));
+            if right - left > self.node_size as usize {
+                let m = (left + right) >> 1;
+                stack.push((m + 1, right));
+                stack.push((left, m - 1));
             }
-            if k <= j {
-                right = j - 1;
+        }
+
+        let extend = |bbox: &mut Bbox, b: &Bbox| {
+            bbox[0] = bbox[0].min(b[0]);
+            bbox[1] = bbox[1].min(b[1]);
+            bbox[2] = bbox[2].max(b[2]);
+            bbox[3] = bbox[3].max(b[3]);
+        };
+        for &(left, right) in nodes.iter().rev() {
+            let m = (left + right) >> 1;
+            let mut bbox = [
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ];
+            if right - left <= self.node_size as usize {
+                for p in &self.points[left..right + 1] {
+                    extend(&mut bbox, &[p[0], p[1], p[0], p[1]]);
+                }
+            } else {
+                // Node boxes are stored at the median position of the node
+                let p = self.points[m];
+                extend(&mut bbox, &[p[0], p[1], p[0], p[1]]);
+                extend(&mut bbox, &bboxes[(left + m - 1) >> 1]);
+                extend(&mut bbox, &bboxes[(m + 1 + right) >> 1]);
             }
+            bboxes[m] = bbox;
         }
+        bboxes
     }
 
-    fn swap_item(&mut self, i: TIndex, j: TIndex) {
-        self.ids.swap(i, j);
-        self.points.swap(i, j);
+    pub(crate) fn sq_dist(ax: TNumber, ay: TNumber, bx: TNumber, by: TNumber) -> TNumber {
+        (ax - bx) * (ax - bx) + (ay - by) * (ay - by)
     }
+}
 
-    fn sq_dist(ax: TNumber, ay: TNumber, bx: TNumber, by: TNumber) -> TNumber {
-        (ax - bx).powi(2) + (ay - by).powi(2)
-    }
+/// Empty and tiny point sets, each with node sizes below, at and above their size
+#[cfg(test)]
+pub(crate) fn tiny_inputs() -> impl Iterator<Item = (Vec<(f64, f64)>, u8)> {
+    (0..3).flat_map(|n| {
+        [0, 1, 64].iter().map(move |&node_size| {
+            let points = (0..n).map(|i| (i as f64, 2.0 * i as f64)).collect();
+            (points, node_size)
+        })
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[cfg_attr(rustfmt, rustfmt_skip)]
+    #[rustfmt::skip]
     const POINTS: [Point; 100] = [
         [54.0,  1.0], [97.0, 21.0], [65.0, 35.0], [33.0, 54.0], [95.0, 39.0], [54.0,  3.0], [53.0, 54.0], [84.0, 72.0],
         [33.0, 34.0], [43.0, 15.0], [52.0, 83.0], [81.0, 23.0], [ 1.0, 61.0], [38.0, 74.0], [11.0, 91.0], [24.0, 56.0],
@@ -366,6 +968,31 @@ mod tests {
         assert_eq!(expected_ids, result);
     }
 
+    #[test]
+    fn test_range_sorted() {
+        let index = KDBush::create(POINTS, 10);
+        for &axis in &[0, 1] {
+            let mut expected_ids = Vec::new();
+            index.range(20.0, 30.0, 50.0, 70.0, |idx| expected_ids.push(idx));
+            expected_ids.sort_by(|&a, &b| POINTS[a][axis].partial_cmp(&POINTS[b][axis]).unwrap());
+            let mut result = Vec::new();
+            index.range_sorted(20.0, 30.0, 50.0, 70.0, axis, |idx| result.push(idx));
+            let coords =
+                |ids: &Vec<usize>| ids.iter().map(|&i| POINTS[i][axis]).collect::<Vec<_>>();
+            assert_eq!(coords(&expected_ids), coords(&result));
+            result.sort();
+            expected_ids.sort();
+            assert_eq!(expected_ids, result);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "sort axis must be 0 or 1")]
+    fn test_range_sorted_invalid_axis() {
+        // Also for queries without results, not only when a leaf is sorted
+        KDBush::create(POINTS, 10).range_sorted(0.0, 0.0, -1.0, -1.0, 2, |_| {});
+    }
+
     #[test]
     fn test_radius() {
         let index = KDBush::create(POINTS, 10);
@@ -375,6 +1002,55 @@ mod tests {
         assert_eq!(expected_ids, result);
     }
 
+    #[test]
+    fn test_queries_with_dist_and_points() {
+        let index = KDBush::create(POINTS, 10);
+        let mut result = Vec::new();
+        index.within_with_dist(50.0, 50.0, 20.0, |idx, d2| {
+            assert_eq!(
+                d2,
+                KDBush::sq_dist(POINTS[idx][0], POINTS[idx][1], 50.0, 50.0)
+            );
+            result.push(idx);
+        });
+        assert_eq!(result, vec![3, 96, 71, 44, 18, 45, 60, 6, 25, 92, 42, 20]);
+        let mut result = Vec::new();
+        index.range_with_points(20.0, 30.0, 50.0, 70.0, |idx, p| {
+            assert_eq!(p, POINTS[idx]);
+            result.push(idx);
+        });
+        assert_eq!(result.len(), 18);
+    }
+
+    #[test]
+    fn test_id_types() {
+        fn check<I: KdId>() {
+            let index = KDBush::<f64, I>::create_with_id_type(
+                POINTS.iter().map(|p| (p[0], p[1])).collect::<Vec<_>>(),
+                10,
+            );
+            let mut result = Vec::new();
+            index.range(20.0, 30.0, 50.0, 70.0, |id| result.push(id));
+            assert_eq!(
+                result,
+                vec![3, 90, 77, 72, 62, 96, 47, 8, 17, 15, 69, 71, 44, 19, 18, 45, 60, 20]
+            );
+            let mut result = Vec::new();
+            index.within(50.0, 50.0, 20.0, |id| result.push(id));
+            assert_eq!(result, vec![3, 96, 71, 44, 18, 45, 60, 6, 25, 92, 42, 20]);
+        }
+        check::<usize>();
+        check::<u32>();
+        check::<u16>();
+    }
+
+    #[test]
+    #[should_panic(expected = "id exceeds u16")]
+    fn test_id_type_overflow() {
+        let mut index = KDBush::<f64, u16>::new_with_id_type(1, 10);
+        index.add_point(70000, 1.0, 1.0);
+    }
+
     #[test]
     fn test_push_api() {
         let mut index = KDBush::new(POINTS.len(), 10);
@@ -390,6 +1066,108 @@ mod tests {
         assert_eq!(expected_ids, result);
     }
 
+    #[test]
+    fn test_region() {
+        // Triangle (20, 20), (80, 20), (50, 80)
+        let inside = |x: f64, y: f64| {
+            let edge =
+                |ax: f64, ay: f64, bx: f64, by: f64| (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+            edge(20.0, 20.0, 80.0, 20.0) >= 0.0
+                && edge(80.0, 20.0, 50.0, 80.0) >= 0.0
+                && edge(50.0, 80.0, 20.0, 20.0) >= 0.0
+        };
+        let intersects = |minx: f64, miny: f64, maxx: f64, maxy: f64| {
+            maxx >= 20.0 && minx <= 80.0 && maxy >= 20.0 && miny <= 80.0
+        };
+        let mut expected_ids: Vec<usize> = (0..POINTS.len())
+            .filter(|&i| inside(POINTS[i][0], POINTS[i][1]))
+            .collect();
+        expected_ids.sort();
+
+        let index = KDBush::create(POINTS, 10);
+        let mut result = Vec::new();
+        index.within_region(intersects, inside, |idx| result.push(idx));
+        result.sort();
+        assert_eq!(expected_ids, result);
+
+        let index = KDBush::create_with_bboxes(POINTS, 10);
+        let mut result = Vec::new();
+        index.within_region(intersects, inside, |idx| result.push(idx));
+        result.sort();
+        assert_eq!(expected_ids, result);
+    }
+
+    #[test]
+    fn test_nested_queries() {
+        let index = KDBush::create(POINTS, 10);
+        let mut expected_ids = Vec::new();
+        index.within(50.0, 50.0, 10.0, |id| {
+            index.within(POINTS[id][0], POINTS[id][1], 10.0, |n| expected_ids.push(n))
+        });
+        let mut result = Vec::new();
+        index.within(50.0, 50.0, 10.0, |id| {
+            index.range(0.0, 0.0, 100.0, 100.0, |_| {});
+            let mut neighbors = Vec::new();
+            index.within(POINTS[id][0], POINTS[id][1], 10.0, |n| {
+                index.within(POINTS[n][0], POINTS[n][1], 0.0, |same| assert_eq!(same, n));
+                neighbors.push(n)
+            });
+            result.extend(neighbors);
+        });
+        assert_eq!(expected_ids, result);
+        assert!(!result.is_empty());
+
+        fn assert_sync<T: Sync>(_: &T) {}
+        assert_sync(&index);
+    }
+
+    #[test]
+    fn test_degenerate_parameters() {
+        let index = KDBush::create(POINTS, 10);
+        fn query<Q: Fn(&mut dyn FnMut(usize))>(f: Q) -> Vec<usize> {
+            let mut result = Vec::new();
+            f(&mut |id| result.push(id));
+            result.sort();
+            result
+        }
+        let all: Vec<usize> = (0..POINTS.len()).collect();
+        assert_eq!(query(|v| index.within(50.0, 50.0, f64::INFINITY, v)), all);
+        assert_eq!(query(|v| index.within(33.0, 54.0, 0.0, v)), vec![3]);
+        assert!(query(|v| index.within(33.0, 54.0, -1.0, v)).is_empty());
+        assert!(query(|v| index.within(33.0, 54.0, f64::NAN, v)).is_empty());
+        assert_eq!(query(|v| index.range(33.0, 54.0, 33.0, 54.0, v)), vec![3]);
+        assert!(query(|v| index.range(50.0, 30.0, 20.0, 70.0, v)).is_empty());
+        assert!(query(|v| index.range(20.0, 70.0, 50.0, 30.0, v)).is_empty());
+        assert!(query(|v| index.range(f64::NAN, 0.0, 100.0, 100.0, v)).is_empty());
+        assert!(query(|v| index.range_sorted(50.0, 30.0, 20.0, 70.0, 0, v)).is_empty());
+        let unbounded = query(|v| {
+            index.range(
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                f64::INFINITY,
+                v,
+            )
+        });
+        assert_eq!(unbounded, all);
+    }
+
+    #[test]
+    fn test_radius_sq() {
+        let index = KDBush::create(POINTS, 10);
+        let expected_ids = vec![3, 96, 71, 44, 18, 45, 60, 6, 25, 92, 42, 20];
+        let mut result = Vec::new();
+        index.within_sq(50.0, 50.0, 400.0, |idx| result.push(idx));
+        assert_eq!(expected_ids, result);
+
+        // Boundary points at squared distance 2 are matched exactly
+        let index = KDBush::create(vec![(1.0, 1.0), (-1.0, -1.0), (1.0, 1.5)], 1);
+        let mut result = Vec::new();
+        index.within_sq(0.0, 0.0, 2.0, |idx| result.push(idx));
+        result.sort();
+        assert_eq!(result, vec![0, 1]);
+    }
+
     #[test]
     fn test_readme() {
         let points = vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)];
@@ -398,8 +1176,161 @@ mod tests {
         index.within(50.0, 50.0, 20.0, |id| print!("{} ", id));
     }
 
+    /// Checks that every node median splits its range along the node's axis
+    fn assert_kd_order(index: &KDBush, left: usize, right: usize, axis: usize) {
+        if right - left <= index.node_size as usize {
+            return;
+        }
+        let m = (left + right) >> 1;
+        let split = index.points[m][axis];
+        assert!(index.points[left..m].iter().all(|p| p[axis] <= split));
+        assert!(index.points[m + 1..right + 1]
+            .iter()
+            .all(|p| p[axis] >= split));
+        assert_kd_order(index, left, m - 1, 1 - axis);
+        assert_kd_order(index, m + 1, right, 1 - axis);
+    }
+
+    #[test]
+    fn test_select_fallback() {
+        let mut index = KDBush::new(1000, 10);
+        for i in 0..1000 {
+            index.add_point(i, ((i * 389) % 1000) as f64, (i % 3) as f64);
+        }
+        index.items().select_fallback(500, 100, 899, 0);
+        let split = index.points[500][0];
+        assert!(index.points[100..500].iter().all(|p| p[0] <= split));
+        assert!(index.points[501..900].iter().all(|p| p[0] >= split));
+        let mut ids = index.ids.clone();
+        ids.sort();
+        assert_eq!(ids, (0..1000).collect::<Vec<_>>());
+        for (&id, p) in index.ids.iter().zip(&index.points) {
+            assert_eq!(p[0], ((id * 389) % 1000) as f64);
+        }
+    }
+
+    #[test]
+    fn test_build_ordered_and_duplicated() {
+        let n = 20000;
+        let inputs: Vec<Vec<(f64, f64)>> = vec![
+            (0..n).map(|i| (i as f64, i as f64)).collect(),
+            (0..n).map(|i| ((n - i) as f64, i as f64)).collect(),
+            (0..n).map(|i| ((i % 2) as f64, 1.0)).collect(),
+            (0..n)
+                .map(|i| (((i * i) % 7) as f64, (i % 13) as f64))
+                .collect(),
+        ];
+        for points in inputs {
+            let index = KDBush::create(points, 16);
+            assert_kd_order(&index, 0, n - 1, 0);
+        }
+    }
+
     #[test]
     fn test_create_empty() {
-        KDBush::create(vec![], DEFAULT_NODE_SIZE);
+        KDBush::<f64>::create(vec![], DEFAULT_NODE_SIZE);
+    }
+
+    #[test]
+    fn test_coordinate_types() {
+        fn check<T: KdNum>(convert: fn(f64) -> T) {
+            let points: Vec<(T, T)> = POINTS
+                .iter()
+                .map(|p| (convert(p[0]), convert(p[1])))
+                .collect();
+            let index = KDBush::create(points, 10);
+            let mut result = Vec::new();
+            index.range(
+                convert(20.0),
+                convert(30.0),
+                convert(50.0),
+                convert(70.0),
+                |id| result.push(id),
+            );
+            assert_eq!(
+                result,
+                vec![3, 90, 77, 72, 62, 96, 47, 8, 17, 15, 69, 71, 44, 19, 18, 45, 60, 20]
+            );
+            let mut result = Vec::new();
+            index.within(convert(50.0), convert(50.0), convert(20.0), |id| {
+                result.push(id)
+            });
+            assert_eq!(result, vec![3, 96, 71, 44, 18, 45, 60, 6, 25, 92, 42, 20]);
+        }
+        check(|v| v as f32);
+        check(|v| v as i32);
+        check(|v| v as u32);
+
+        // Squared distances of extreme integer coordinates don't overflow
+        let index = KDBush::create(vec![(i32::MIN, i32::MIN), (i32::MAX, i32::MAX)], 1);
+        let mut result = Vec::new();
+        index.within(i32::MIN, i32::MIN, i32::MAX, |id| result.push(id));
+        assert_eq!(result, vec![0]);
+        let index = KDBush::create(vec![(0u32, 0u32), (u32::MAX, u32::MAX)], 1);
+        let mut result = Vec::new();
+        index.within(0, u32::MAX, u32::MAX, |id| result.push(id));
+        result.sort();
+        assert_eq!(result, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], 4);
+        assert_eq!(index.len(), 3);
+        assert!(!index.is_empty());
+        assert_eq!(index.node_size(), 4);
+        assert_eq!(index.coords().len(), 6);
+        for (i, &id) in index.ids().iter().enumerate() {
+            assert_eq!(
+                index.points()[i],
+                [index.coords()[2 * i], index.coords()[2 * i + 1]]
+            );
+            assert!(id < 3);
+        }
+    }
+
+    #[test]
+    fn test_empty_and_tiny() {
+        fn sorted(mut ids: Vec<TIndex>) -> Vec<TIndex> {
+            ids.sort();
+            ids
+        }
+        type Visitor<'a> = &'a mut dyn FnMut(TIndex);
+        fn collect(query: &dyn Fn(Visitor)) -> Vec<TIndex> {
+            let mut found = Vec::new();
+            query(&mut |id| found.push(id));
+            sorted(found)
+        }
+
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let all: Vec<TIndex> = (0..n).collect();
+            let bboxes = KDBush::create_with_bboxes(points.clone(), node_size);
+            let index = KDBush::create(points, node_size);
+            assert_eq!(index.len(), n);
+            assert_eq!(index.is_empty(), n == 0);
+
+            assert_eq!(collect(&|v| index.range(-1.0, -1.0, 5.0, 5.0, v)), all);
+            assert_eq!(
+                collect(&|v| index.range_sorted(-1.0, -1.0, 5.0, 5.0, 1, v)),
+                all
+            );
+            assert_eq!(collect(&|v| index.within(0.0, 0.0, 5.0, v)), all);
+            assert_eq!(collect(&|v| index.within_sq(0.0, 0.0, 25.0, v)), all);
+            assert_eq!(
+                collect(&|v| index.range_with_points(-1.0, -1.0, 5.0, 5.0, |id, _| v(id))),
+                all
+            );
+            assert_eq!(
+                collect(&|v| index.within_with_dist(0.0, 0.0, 5.0, |id, _| v(id))),
+                all
+            );
+            assert_eq!(
+                collect(&|v| index.within_region(|_, _, _, _| true, |_, _| true, v)),
+                all
+            );
+
+            assert_eq!(collect(&|v| bboxes.within_sq(0.0, 0.0, 25.0, v)), all);
+        }
     }
 }
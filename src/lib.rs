@@ -1,3 +1,84 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod aggregate;
+mod batch;
+#[cfg(feature = "buffer")]
+mod buffer;
+mod builder;
+mod classify;
+#[cfg(feature = "cluster")]
+mod cluster;
+mod composite;
+mod coverage;
+mod crs;
+mod diff;
+mod dual;
+mod exact;
+mod fallible;
+#[cfg(feature = "geo")]
+mod geo;
+mod geometry;
+mod grouped;
+mod iter;
+mod join;
 mod kdbush;
+mod lookup;
+mod map;
+#[cfg(feature = "buffer")]
+mod mapped;
+mod matching;
+mod math;
+mod nd;
+mod nodes;
+mod order;
+mod paged;
+mod partition;
+mod ranked;
+mod scan;
+#[cfg(feature = "shapefile")]
+mod shapefile;
+mod slice;
+mod sort;
+mod stepped;
+#[cfg(feature = "stream")]
+mod stream;
+mod transform;
+mod updatable;
+mod validate;
+mod warm;
 
+#[cfg(feature = "buffer")]
+pub use crate::buffer::*;
+pub use crate::builder::*;
+pub use crate::classify::*;
+#[cfg(feature = "cluster")]
+pub use crate::cluster::*;
+pub use crate::composite::*;
+pub use crate::crs::*;
+pub use crate::diff::*;
+pub use crate::dual::*;
+#[cfg(feature = "geo")]
+pub use crate::geo::*;
+pub use crate::grouped::*;
+pub use crate::iter::*;
 pub use crate::kdbush::*;
+pub use crate::map::*;
+#[cfg(feature = "buffer")]
+pub use crate::mapped::*;
+pub use crate::matching::*;
+pub use crate::nd::*;
+pub use crate::nodes::*;
+pub use crate::order::*;
+pub use crate::paged::*;
+pub use crate::partition::*;
+#[cfg(feature = "shapefile")]
+pub use crate::shapefile::*;
+pub use crate::slice::*;
+pub use crate::stepped::*;
+#[cfg(feature = "stream")]
+pub use crate::stream::*;
+pub use crate::updatable::*;
+pub use crate::validate::*;
+pub use crate::warm::*;
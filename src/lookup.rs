@@ -0,0 +1,103 @@
+use crate::kdbush::{KDBush, PointReader, TIndex, TNumber};
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// Item ids by the bit patterns of their coordinates
+pub(crate) type CoordLookup = HashMap<[u64; 2], Vec<TIndex>>;
+
+/// Lookup key of a coordinate pair, treating -0.0 and 0.0 as equal
+fn coord_key(x: TNumber, y: TNumber) -> [u64; 2] {
+    [(x + 0.0).to_bits(), (y + 0.0).to_bits()]
+}
+
+impl KDBush {
+    /// Creates an index from the given points, with a hash index for exact coordinate lookups
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points reader
+    /// * `node_size` - Size of the KD-tree node
+    pub fn create_with_lookup<R: PointReader>(points: R, node_size: u8) -> KDBush {
+        let mut kdbush = KDBush::create(points, node_size);
+        kdbush.build_lookup();
+        kdbush
+    }
+
+    /// Build index, including a hash index for exact coordinate lookups
+    pub fn build_index_with_lookup(&mut self) {
+        self.build_index();
+        self.build_lookup();
+    }
+
+    /// Returns the ids of all items located exactly at the given coordinates
+    ///
+    /// Uses the hash index if the index was built with lookup, a tree search otherwise.
+    /// NaN coordinates never match.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y` - Coordinates
+    pub fn ids_at(&self, x: TNumber, y: TNumber) -> Vec<TIndex> {
+        match self.lookup {
+            Some(ref lookup) => lookup.get(&coord_key(x, y)).cloned().unwrap_or_default(),
+            None => {
+                let mut ids = Vec::new();
+                if !self.ids.is_empty() {
+                    self.range(x, y, x, y, |id| ids.push(id));
+                }
+                ids
+            }
+        }
+    }
+
+    pub(crate) fn build_lookup(&mut self) {
+        #[cfg(feature = "std")]
+        let mut lookup = CoordLookup::with_capacity(self.points.len());
+        #[cfg(not(feature = "std"))]
+        let mut lookup = CoordLookup::new();
+        for (p, &id) in self.points.iter().zip(&self.ids) {
+            if !p[0].is_nan() && !p[1].is_nan() {
+                lookup.entry(coord_key(p[0], p[1])).or_default().push(id);
+            }
+        }
+        self.lookup = Some(lookup);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_ids_at() {
+        let points = vec![(54.0, 1.0), (97.0, 21.0), (54.0, 1.0), (0.0, -0.0)];
+        for index in &[
+            KDBush::create(points.clone(), 10),
+            KDBush::create_with_lookup(points.clone(), 10),
+        ] {
+            let mut ids = index.ids_at(54.0, 1.0);
+            ids.sort();
+            assert_eq!(ids, vec![0, 2]);
+            assert_eq!(index.ids_at(97.0, 21.0), vec![1]);
+            assert_eq!(index.ids_at(-0.0, 0.0), vec![3]);
+            assert!(index.ids_at(54.0, 1.000001).is_empty());
+            assert!(index.ids_at(f64::NAN, 1.0).is_empty());
+        }
+        assert!(KDBush::create(vec![], 10).ids_at(0.0, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_lookup_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let lookup = KDBush::create_with_lookup(points, node_size);
+            assert_eq!(lookup.ids_at(0.0, 0.0), (0..n.min(1)).collect::<Vec<_>>());
+            assert_eq!(lookup.ids_at(1.0, 2.0), (1..n).collect::<Vec<_>>());
+            assert!(lookup.ids_at(2.0, 1.0).is_empty());
+        }
+    }
+}
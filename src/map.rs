@@ -0,0 +1,202 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, KDBush, TIndex, TNumber};
+use crate::math;
+use alloc::vec::Vec;
+
+/// Spatial index which stores a value with each point
+///
+/// Values are kept in the order of the tree, so queries read them next to the coordinates
+/// instead of looking them up in a separate array. Results are reported as the value and
+/// the index of the point in the input.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{KDBushMap, DEFAULT_NODE_SIZE};
+///
+/// let cities = vec![(54.0, 1.0, "a"), (97.0, 21.0, "b"), (65.0, 35.0, "c")];
+/// let index = KDBushMap::create(cities, DEFAULT_NODE_SIZE);
+/// index.within(60.0, 30.0, 10.0, |name, id| {
+///     assert_eq!(*name, "c");
+///     assert_eq!(id, 2);
+/// });
+/// ```
+pub struct KDBushMap<V> {
+    /// Index of the points, with the input position as id
+    pub(crate) tree: KDBush,
+    /// Values in the order of the sorted tree
+    pub(crate) values: Vec<V>,
+}
+
+impl<V> KDBushMap<V> {
+    /// Creates an index from the given points and their values
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - Points as `(x, y, value)`
+    /// * `node_size` - Size of the KD-tree node
+    pub fn create<I>(items: I, node_size: u8) -> KDBushMap<V>
+    where
+        I: IntoIterator<Item = (TNumber, TNumber, V)>,
+    {
+        let items = items.into_iter();
+        let mut tree = KDBush::new(items.size_hint().0, node_size);
+        let mut slots = Vec::with_capacity(items.size_hint().0);
+        for (i, (x, y, value)) in items.enumerate() {
+            tree.add_point(i, x, y);
+            slots.push(Some(value));
+        }
+        tree.build_index();
+        let values = tree
+            .ids
+            .iter()
+            .map(|&i| slots[i].take().expect("ids are unique"))
+            .collect();
+        KDBushMap { tree, values }
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the index has no points
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Index of the points, with the input position as id
+    pub fn index(&self) -> &KDBush {
+        &self.tree
+    }
+
+    /// Finds all points within the given bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader, called with the value and the input index of each point
+    pub fn range<F>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        mut visitor: F,
+    ) where
+        F: FnMut(&V, TIndex),
+    {
+        if self.is_empty() || !is_valid_bbox(minx, miny, maxx, maxy) {
+            return;
+        }
+        self.tree.range_idx(
+            minx,
+            miny,
+            maxx,
+            maxy,
+            &mut |i| visitor(&self.values[i], self.tree.ids[i]),
+            0,
+            self.len() - 1,
+            0,
+        );
+    }
+
+    /// Finds all points within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader, called with the value and the input index of each point
+    pub fn within<F>(&self, qx: TNumber, qy: TNumber, r: TNumber, mut visitor: F)
+    where
+        F: FnMut(&V, TIndex),
+    {
+        if self.is_empty() || !is_valid_radius(r) {
+            return;
+        }
+        self.tree.within_idx(
+            qx,
+            qy,
+            r * r,
+            &mut |i| visitor(&self.values[i], self.tree.ids[i]),
+            0,
+            self.len() - 1,
+            0,
+        );
+    }
+
+    /// Finds the `k` points nearest to the query point
+    ///
+    /// Returns the value, input index and distance of each point, ordered by increasing distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Maximal number of results
+    pub fn knn(&self, qx: TNumber, qy: TNumber, k: usize) -> Vec<(&V, TIndex, TNumber)> {
+        self.tree
+            .knn_idx(qx, qy, k)
+            .into_iter()
+            .map(|(i, d2)| (&self.values[i], self.tree.ids[i], math::sqrt(d2)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_queries() {
+        let items: Vec<(f64, f64, String)> = (0..500)
+            .map(|i| {
+                (
+                    (i * 37 % 101) as f64,
+                    (i * 53 % 97) as f64,
+                    format!("p{}", i),
+                )
+            })
+            .collect();
+        let index = KDBushMap::create(items.clone(), 8);
+        assert_eq!(index.len(), 500);
+
+        let mut found = 0;
+        index.range(10.0, 20.0, 60.0, 50.0, |value, id| {
+            assert_eq!(*value, items[id].2);
+            found += 1;
+        });
+        let mut expected = 0;
+        index
+            .index()
+            .range(10.0, 20.0, 60.0, 50.0, |_| expected += 1);
+        assert_eq!(found, expected);
+
+        let mut found = Vec::new();
+        index.within(40.0, 50.0, 15.0, |value, id| {
+            assert_eq!(*value, items[id].2);
+            found.push(id);
+        });
+        let mut expected = Vec::new();
+        index
+            .index()
+            .within(40.0, 50.0, 15.0, |id| expected.push(id));
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+
+        let nearest = index.knn(40.0, 50.0, 3);
+        assert_eq!(nearest.len(), 3);
+        for (value, id, _) in nearest {
+            assert_eq!(*value, items[id].2);
+        }
+    }
+
+    #[test]
+    fn test_map_empty() {
+        let index: KDBushMap<u8> = KDBushMap::create(Vec::new(), 8);
+        assert!(index.is_empty());
+        index.range(0.0, 0.0, 1.0, 1.0, |_, _| panic!());
+        index.within(0.0, 0.0, 1.0, |_, _| panic!());
+        assert!(index.knn(0.0, 0.0, 1).is_empty());
+    }
+}
@@ -0,0 +1,408 @@
+use crate::buffer::{check_ids, BufferInfo, VerifyError};
+use crate::kdbush::{is_valid_bbox, is_valid_radius, KDBush, TIndex, TNumber};
+use crate::stepped::Shape;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Read-only index over a flat buffer, queried in place without copying
+///
+/// The buffer is in the format of the JavaScript kdbush v4, as written by
+/// [`KDBush::write_to`] or [`KDBush::to_bytes`]. Coordinates and ids are decoded on access,
+/// so the buffer needs no particular alignment. This makes it suitable for memory-mapped
+/// files: several processes can share one index in the OS page cache instead of each
+/// loading a private copy.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{KDBush, KDBushRef, DEFAULT_NODE_SIZE};
+///
+/// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+/// let bytes = index.to_bytes().unwrap();
+/// // e.g. `let bytes = unsafe { memmap2::Mmap::map(&file)? };`
+/// let view = KDBushRef::from_slice(&bytes).unwrap();
+/// let mut result = Vec::new();
+/// view.range(20.0, 30.0, 70.0, 70.0, |id| result.push(id));
+/// assert_eq!(result, vec![2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KDBushRef<'a> {
+    data: &'a [u8],
+    info: BufferInfo,
+}
+
+impl<'a> KDBushRef<'a> {
+    /// Opens a flat buffer for querying
+    ///
+    /// Only the header and the buffer length are checked, so opening takes constant time and
+    /// reads no more than the first page. The ids are not checked, queries on a corrupted
+    /// buffer may report wrong ids but never read out of bounds. Use [`KDBushRef::verify`]
+    /// to check the ids of untrusted buffers. The buffer must contain a finished index.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Buffer contents
+    pub fn from_slice(data: &'a [u8]) -> Result<KDBushRef<'a>, VerifyError> {
+        let info = KDBush::verify_header(data)?;
+        Ok(KDBushRef { data, info })
+    }
+
+    /// Checks that the ids are a permutation of `0..len`, like [`KDBush::verify_buffer`]
+    ///
+    /// Reads all ids and allocates one flag per item.
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        check_ids(self.info.ids(self.data), self.len())
+    }
+
+    /// Underlying buffer, e.g. for sending it unchanged to a JavaScript client
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Layout of the underlying buffer
+    pub fn info(&self) -> &BufferInfo {
+        &self.info
+    }
+
+    /// Number of indexed items
+    pub fn len(&self) -> usize {
+        self.info.num_items
+    }
+
+    /// Whether the index contains no items
+    pub fn is_empty(&self) -> bool {
+        self.info.num_items == 0
+    }
+
+    /// EPSG code of the coordinate reference system, if the buffer has a CRS trailer
+    pub fn crs(&self) -> Option<u32> {
+        self.info.crs
+    }
+
+    /// Finds all items within the given bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader
+    pub fn range<F>(&self, minx: TNumber, miny: TNumber, maxx: TNumber, maxy: TNumber, visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if is_valid_bbox(minx, miny, maxx, maxy) {
+            self.query(&Shape::Range([minx, miny, maxx, maxy]), visitor);
+        }
+    }
+
+    /// Finds all items within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader
+    pub fn within<F>(&self, qx: TNumber, qy: TNumber, r: TNumber, visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if is_valid_radius(r) {
+            self.query(&Shape::Within { qx, qy, r2: r * r }, visitor);
+        }
+    }
+
+    /// Reads the ids and coordinates of the top tree levels, loading their pages into memory
+    ///
+    /// Every query starts at the root and descends through the medians of the top levels,
+    /// so faulting their pages in after opening a memory-mapped file avoids page faults in
+    /// the first queries. Leaves within these levels are read completely.
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - Number of tree levels to read, `usize::MAX` reads the whole buffer
+    pub fn warm_up(&self, levels: usize) {
+        let mut sum = 0u64;
+        self.top_positions(levels, |i| {
+            let p = self.point(i);
+            sum = sum
+                .wrapping_add(self.id(i) as u64)
+                .wrapping_add(p[0].to_bits() ^ p[1].to_bits());
+        });
+        core::hint::black_box(sum);
+    }
+
+    /// Visits the positions of the medians and leaf items of the top tree levels
+    fn top_positions<F>(&self, levels: usize, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.is_empty() {
+            return;
+        }
+        let node_size = usize::from(self.info.node_size);
+        let mut nodes = vec![(0, self.len() - 1)];
+        let mut next = Vec::new();
+        for _ in 0..levels {
+            if nodes.is_empty() {
+                break;
+            }
+            for &(left, right) in &nodes {
+                if right - left <= node_size {
+                    (left..right + 1).for_each(&mut visitor);
+                    continue;
+                }
+                let m = (left + right) >> 1;
+                visitor(m);
+                next.push((left, m - 1));
+                next.push((m + 1, right));
+            }
+            core::mem::swap(&mut nodes, &mut next);
+            next.clear();
+        }
+    }
+
+    fn query<F>(&self, shape: &Shape, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.is_empty() {
+            return;
+        }
+        let node_size = usize::from(self.info.node_size);
+        let mut stack = vec![(0, self.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= node_size {
+                for i in left..right + 1 {
+                    let p = self.point(i);
+                    if shape.contains(p[0], p[1]) {
+                        visitor(self.id(i));
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let p = self.point(m);
+            if shape.contains(p[0], p[1]) {
+                visitor(self.id(m));
+            }
+            let (below, above) = shape.reaches(p[axis], axis);
+            // Right child first, so the left child is visited next
+            if above {
+                stack.push((m + 1, right, (axis + 1) % 2));
+            }
+            if below {
+                stack.push((left, m - 1, (axis + 1) % 2));
+            }
+        }
+    }
+
+    /// Id of the item at position `i` in the sorted arrays
+    fn id(&self, i: TIndex) -> TIndex {
+        let b = &self.data[self.info.ids_offset + i * self.info.id_size..];
+        if self.info.id_size == 2 {
+            u16::from_le_bytes([b[0], b[1]]) as usize
+        } else {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize
+        }
+    }
+
+    /// Coordinates of the item at position `i` in the sorted arrays
+    fn point(&self, i: TIndex) -> [TNumber; 2] {
+        let coord_type = self.info.coord_type;
+        let size = coord_type.size();
+        let b = &self.data[self.info.coords_offset + 2 * i * size..];
+        [coord_type.read(b), coord_type.read(&b[size..])]
+    }
+}
+
+/// Owned flat buffer, queried in place through [`KDBushBuf::view`]
+///
+/// Holds the bytes of a flat buffer without decoding them, so [`KDBushBuf::as_bytes`]
+/// returns the storage itself, e.g. for sending the index to a JavaScript client, and
+/// taking over a received buffer copies nothing.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{KDBush, KDBushBuf, DEFAULT_NODE_SIZE};
+///
+/// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+/// let buf = index.to_buf().unwrap();
+/// let received = KDBushBuf::from_vec(buf.as_bytes().to_vec()).unwrap();
+/// let mut result = Vec::new();
+/// received.view().within(60.0, 30.0, 10.0, |id| result.push(id));
+/// assert_eq!(result, vec![2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KDBushBuf {
+    data: Vec<u8>,
+    info: BufferInfo,
+}
+
+impl KDBushBuf {
+    /// Takes over a flat buffer, checking it like [`KDBushRef::from_slice`]
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Buffer contents
+    pub fn from_vec(data: Vec<u8>) -> Result<KDBushBuf, VerifyError> {
+        let info = KDBush::verify_header(&data)?;
+        Ok(KDBushBuf { data, info })
+    }
+
+    /// View of the buffer for querying
+    pub fn view(&self) -> KDBushRef<'_> {
+        KDBushRef {
+            data: &self.data,
+            info: self.info.clone(),
+        }
+    }
+
+    /// Underlying buffer
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the underlying buffer
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl KDBush {
+    /// Returns the index as owned flat buffer, in the format of [`KDBush::to_bytes`]
+    pub fn to_buf(&self) -> Result<KDBushBuf, VerifyError> {
+        KDBushBuf::from_vec(self.to_bytes()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::{CoordType, MAGIC, VERSION};
+
+    #[test]
+    fn test_ref_queries() {
+        let points: Vec<(f64, f64)> = (0..70000)
+            .map(|i| ((i * 37 % 1001) as f64, (i * 53 % 997) as f64 + 0.5))
+            .collect();
+        for &n in &[0, 3, 1000, 70000] {
+            let index = KDBush::create(points[..n].to_vec(), 16);
+            let bytes = index.to_bytes().unwrap();
+            // Unaligned start
+            let mut shifted = vec![0u8];
+            shifted.extend_from_slice(&bytes);
+            let view = KDBushRef::from_slice(&shifted[1..]).unwrap();
+            assert_eq!(view.len(), n);
+            assert_eq!(view.verify(), Ok(()));
+            assert_eq!(view.as_bytes(), &bytes[..]);
+            assert_eq!(view.crs(), None);
+
+            let mut expected = Vec::new();
+            index.range(100.0, 200.0, 400.0, 300.0, |id| expected.push(id));
+            let mut result = Vec::new();
+            view.range(100.0, 200.0, 400.0, 300.0, |id| result.push(id));
+            assert_eq!(expected, result);
+
+            let mut expected = Vec::new();
+            index.within(500.0, 500.0, 60.0, |id| expected.push(id));
+            let mut result = Vec::new();
+            view.within(500.0, 500.0, 60.0, |id| result.push(id));
+            assert_eq!(expected, result);
+        }
+    }
+
+    #[test]
+    fn test_ref_js_bytes() {
+        // Int16 coordinates, as created by `new KDBush(2, 8, Int16Array)`
+        let mut bytes = vec![MAGIC, (VERSION << 4) + 3, 8, 0, 2, 0, 0, 0];
+        bytes.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        for v in &[-5i16, 7, 300, -2] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        let view = KDBushRef::from_slice(&bytes).unwrap();
+        assert_eq!(view.verify(), Ok(()));
+        assert_eq!(view.info().coord_type, CoordType::Int16);
+        let mut result = Vec::new();
+        view.within(0.0, 0.0, 10.0, |id| result.push(id));
+        assert_eq!(result, vec![1]);
+
+        assert_eq!(
+            KDBushRef::from_slice(&bytes[..12]).err(),
+            Some(VerifyError::LengthMismatch {
+                expected: 24,
+                actual: 12
+            })
+        );
+
+        // Duplicate ids are only found by verify, queries still stay in bounds
+        bytes[8] = 0;
+        let view = KDBushRef::from_slice(&bytes).unwrap();
+        assert_eq!(view.verify(), Err(VerifyError::DuplicateId(0)));
+        let mut result = Vec::new();
+        view.range(-1000.0, -1000.0, 1000.0, 1000.0, |id| result.push(id));
+        assert_eq!(result, vec![0, 0]);
+    }
+
+    #[test]
+    fn test_buf() {
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|i| ((i * 37 % 1001) as f64, (i * 53 % 997) as f64 + 0.5))
+            .collect();
+        let mut index = KDBush::create(points, 16);
+        index.set_crs(Some(4326));
+        let buf = index.to_buf().unwrap();
+        let bytes = index.to_bytes().unwrap();
+        assert_eq!(buf.as_bytes(), &bytes[..]);
+        assert_eq!(buf.view().as_bytes().as_ptr(), buf.as_bytes().as_ptr());
+        assert_eq!(buf.view().crs(), Some(4326));
+
+        let mut expected = Vec::new();
+        index.within(500.0, 500.0, 60.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        buf.view().within(500.0, 500.0, 60.0, |id| result.push(id));
+        assert_eq!(expected, result);
+
+        let ptr = bytes.as_ptr();
+        let received = KDBushBuf::from_vec(bytes).unwrap();
+        assert_eq!(received.as_bytes().as_ptr(), ptr);
+        let vec = received.into_vec();
+        assert_eq!(vec.as_ptr(), ptr);
+        assert_eq!(
+            KDBushBuf::from_vec(vec![0; 4]).unwrap_err(),
+            VerifyError::TooShort
+        );
+    }
+
+    #[test]
+    fn test_warm_up() {
+        for &(n, node_size) in &[(0, 8), (1, 8), (5, 8), (1000, 1), (1000, 16)] {
+            let points: Vec<(f64, f64)> = (0..n).map(|i| (i as f64, (i * 7 % 13) as f64)).collect();
+            let bytes = KDBush::create(points, node_size).to_bytes().unwrap();
+            let view = KDBushRef::from_slice(&bytes).unwrap();
+            for &levels in &[0, 1, 3, 64, usize::MAX] {
+                view.warm_up(levels);
+                let mut seen = vec![false; n];
+                view.top_positions(levels, |i| {
+                    assert!(i < n);
+                    assert!(!seen[i]);
+                    seen[i] = true;
+                });
+                let count = seen.iter().filter(|&&s| s).count();
+                if levels == 0 {
+                    assert_eq!(count, 0);
+                }
+                if levels >= 64 {
+                    assert_eq!(count, n);
+                }
+            }
+        }
+        let bytes = KDBush::create((0..1000).map(|i| (i as f64, 0.0)).collect::<Vec<_>>(), 16)
+            .to_bytes()
+            .unwrap();
+        let view = KDBushRef::from_slice(&bytes).unwrap();
+        let mut touched = Vec::new();
+        view.top_positions(2, |i| touched.push(i));
+        assert_eq!(touched, vec![499, 249, 749]);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::kdbush::{is_valid_radius, KDBush, TIndex, TNumber};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Pairing strategy for [`KDBush::match_points`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchStrategy {
+    /// Pairs candidates by increasing distance, skipping points which are already matched
+    Greedy,
+    /// Only pairs points which are each other's nearest neighbor
+    MutualNearest,
+}
+
+impl KDBush {
+    /// Matches items of this index one-to-one with items of another index
+    ///
+    /// Every item is part of at most one pair. Pairs are returned as `(id, other_id)`,
+    /// ordered by increasing distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Index to match against
+    /// * `max_dist` - Maximal distance between matched items
+    /// * `strategy` - Pairing strategy
+    pub fn match_points(
+        &self,
+        other: &KDBush,
+        max_dist: TNumber,
+        strategy: MatchStrategy,
+    ) -> Vec<(TIndex, TIndex)> {
+        self.match_idx(other, max_dist, strategy)
+            .into_iter()
+            .map(|(i, j, _)| (self.ids[i], other.ids[j]))
+            .collect()
+    }
+
+    /// Matches positions of this index with positions of another index
+    ///
+    /// Returns `(position, other position, squared distance)`, ordered by increasing distance.
+    pub(crate) fn match_idx(
+        &self,
+        other: &KDBush,
+        max_dist: TNumber,
+        strategy: MatchStrategy,
+    ) -> Vec<(TIndex, TIndex, TNumber)> {
+        if self.ids.is_empty() || other.ids.is_empty() || !is_valid_radius(max_dist) {
+            return Vec::new();
+        }
+
+        // Candidate pairs as (squared distance, position in self, position in other)
+        let mut candidates = Vec::new();
+        for (i, p) in self.points.iter().enumerate() {
+            other.within_idx(
+                p[0],
+                p[1],
+                max_dist * max_dist,
+                &mut |j| {
+                    let q = other.points[j];
+                    candidates.push((KDBush::sq_dist(p[0], p[1], q[0], q[1]), i, j));
+                },
+                0,
+                other.ids.len() - 1,
+                0,
+            );
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut pairs = Vec::new();
+        match strategy {
+            MatchStrategy::Greedy => {
+                let mut matched = vec![false; self.ids.len()];
+                let mut other_matched = vec![false; other.ids.len()];
+                for &(d2, i, j) in &candidates {
+                    if !matched[i] && !other_matched[j] {
+                        matched[i] = true;
+                        other_matched[j] = true;
+                        pairs.push((i, j, d2));
+                    }
+                }
+            }
+            MatchStrategy::MutualNearest => {
+                // Candidates are sorted, so the first one seen is the nearest
+                let mut nearest = vec![None; self.ids.len()];
+                let mut other_nearest = vec![None; other.ids.len()];
+                for &(_, i, j) in &candidates {
+                    nearest[i].get_or_insert(j);
+                    other_nearest[j].get_or_insert(i);
+                }
+                for &(d2, i, j) in &candidates {
+                    if nearest[i] == Some(j) && other_nearest[j] == Some(i) {
+                        pairs.push((i, j, d2));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+    use crate::DEFAULT_NODE_SIZE;
+
+    #[test]
+    fn test_match_greedy() {
+        let a = KDBush::create(
+            vec![(0.0, 0.0), (1.0, 0.0), (10.0, 10.0)],
+            DEFAULT_NODE_SIZE,
+        );
+        let b = KDBush::create(
+            vec![(0.9, 0.0), (0.0, 0.5), (30.0, 30.0)],
+            DEFAULT_NODE_SIZE,
+        );
+        let pairs = a.match_points(&b, 2.0, MatchStrategy::Greedy);
+        assert_eq!(pairs, vec![(1, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn test_match_mutual_nearest() {
+        let a = KDBush::create(vec![(0.0, 0.0), (1.0, 0.0)], DEFAULT_NODE_SIZE);
+        let b = KDBush::create(vec![(0.9, 0.0)], DEFAULT_NODE_SIZE);
+        let pairs = a.match_points(&b, 2.0, MatchStrategy::MutualNearest);
+        assert_eq!(pairs, vec![(1, 0)]);
+        let pairs = b.match_points(&a, 0.5, MatchStrategy::MutualNearest);
+        assert_eq!(pairs, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_match_empty() {
+        let a = KDBush::create(vec![(0.0, 0.0)], DEFAULT_NODE_SIZE);
+        let b = KDBush::create(vec![], DEFAULT_NODE_SIZE);
+        assert!(a.match_points(&b, 1.0, MatchStrategy::Greedy).is_empty());
+    }
+
+    #[test]
+    fn test_match_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let index = KDBush::create(points, node_size);
+            let matches = index.match_points(&index, 1.0, MatchStrategy::Greedy);
+            assert_eq!(matches.len(), n);
+        }
+    }
+}
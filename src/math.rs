@@ -0,0 +1,197 @@
+//! Floating point functions which are not part of `core`
+//!
+//! With the `std` feature these forward to the standard library. Without it, portable
+//! implementations are used, which are accurate enough for the index and its queries.
+
+#[cfg(feature = "std")]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    x.sqrt()
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn ln(x: f64) -> f64 {
+    x.ln()
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn exp(x: f64) -> f64 {
+    x.exp()
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn floor(x: f64) -> f64 {
+    x.floor()
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn abs(x: f64) -> f64 {
+    x.abs()
+}
+
+/// Square root, correctly rounded
+#[cfg(not(feature = "std"))]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 || x.is_infinite() {
+        return x;
+    }
+    // x = m * 2^e with a 53-bit mantissa m, normalizing subnormals
+    let bits = x.to_bits();
+    let field = (bits >> 52) as i64;
+    let mut m = bits & ((1 << 52) - 1);
+    let mut e = if field == 0 {
+        let shift = m.leading_zeros() - 11;
+        m <<= shift;
+        1 - 1075 - shift as i64
+    } else {
+        m |= 1 << 52;
+        field - 1075
+    };
+    if e & 1 != 0 {
+        m <<= 1;
+        e -= 1;
+    }
+    // Integer square root with one extra bit for rounding to nearest, ties to even
+    let (q, rem) = isqrt((m as u128) << 54);
+    let mut mant = (q >> 1) as u64;
+    if q & 1 == 1 && (rem != 0 || mant & 1 == 1) {
+        mant += 1;
+    }
+    let k = (e - 54) / 2 + 1;
+    mant as f64 * pow2(k)
+}
+
+/// Integer square root and remainder
+#[cfg(not(feature = "std"))]
+fn isqrt(n: u128) -> (u128, u128) {
+    let mut rem = n;
+    let mut root = 0;
+    let mut bit = 1u128 << ((127 - n.leading_zeros()) & !1);
+    while bit != 0 {
+        if rem >= root + bit {
+            rem -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    (root, rem)
+}
+
+/// Natural logarithm
+#[cfg(not(feature = "std"))]
+pub(crate) fn ln(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x.is_infinite() {
+        return x;
+    }
+    // x = m * 2^e with m in [1, 2), normalizing subnormals first
+    let (x, offset) = if x < f64::MIN_POSITIVE {
+        (x * (1u64 << 54) as f64, 54)
+    } else {
+        (x, 0)
+    };
+    let bits = x.to_bits();
+    let e = ((bits >> 52) & 0x7ff) as i64 - 1023 - offset;
+    let m = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    // ln(m) = 2 atanh((m - 1) / (m + 1))
+    let t = (m - 1.0) / (m + 1.0);
+    let t2 = t * t;
+    let mut term = t;
+    let mut sum: f64 = 0.0;
+    let mut k = 1.0;
+    while abs(term) > 1e-17 * abs(sum) && k < 100.0 {
+        sum += term / k;
+        term *= t2;
+        k += 2.0;
+    }
+    2.0 * sum + e as f64 * core::f64::consts::LN_2
+}
+
+/// Exponential function
+#[cfg(not(feature = "std"))]
+pub(crate) fn exp(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    if x > 709.8 {
+        return f64::INFINITY;
+    }
+    if x < -745.2 {
+        return 0.0;
+    }
+    // e^x = 2^k * e^r with |r| <= ln(2) / 2
+    // ln(2) split in two parts, so k * LN2_HI is exact
+    const LN2_HI: f64 = 6.931_471_803_691_238e-1;
+    const LN2_LO: f64 = 1.908_214_929_270_587_7e-10;
+    let k = floor(x / core::f64::consts::LN_2 + 0.5);
+    let r = (x - k * LN2_HI) - k * LN2_LO;
+    let mut term: f64 = 1.0;
+    let mut sum = 1.0;
+    let mut i = 1.0;
+    while abs(term) > 1e-17 {
+        term *= r / i;
+        sum += term;
+        i += 1.0;
+    }
+    // Scale in two steps, so neither factor overflows or underflows
+    let k = k as i64;
+    let half = k / 2;
+    sum * pow2(half) * pow2(k - half)
+}
+
+/// 2^k for a normal result
+#[cfg(not(feature = "std"))]
+fn pow2(k: i64) -> f64 {
+    f64::from_bits(((k + 1023) as u64) << 52)
+}
+
+/// Absolute value, clearing the sign bit
+#[cfg(not(feature = "std"))]
+pub(crate) fn abs(x: f64) -> f64 {
+    f64::from_bits(x.to_bits() & !(1 << 63))
+}
+
+/// Largest integer less than or equal to `x`
+#[cfg(not(feature = "std"))]
+pub(crate) fn floor(x: f64) -> f64 {
+    // Values from 2^52 on are integers, so only smaller ones need rounding
+    if x.is_nan() || abs(x) >= 4503599627370496.0 {
+        return x;
+    }
+    let t = x as i64 as f64;
+    if t > x {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+#[cfg(all(test, not(feature = "std")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portable_math() {
+        for &x in &[1e-310, 1e-5, 0.3, 1.0, 2.0, 10.0, 601.0, 123456.789, 1e300] {
+            assert_eq!(sqrt(x), x.sqrt());
+            assert!((ln(x) - x.ln()).abs() <= 1e-14 * x.ln().abs().max(1.0));
+        }
+        for &x in &[-700.0, -3.5, -0.1, 0.0, 0.7, 4.6, 50.0, 700.0] {
+            assert!((exp(x) - x.exp()).abs() <= 1e-14 * x.exp());
+        }
+        for &x in &[-2.5, -2.0, -0.5, 0.0, 0.5, 3.0, 1e20] {
+            assert_eq!(floor(x), x.floor());
+        }
+        assert!(sqrt(-1.0).is_nan());
+        assert_eq!(ln(0.0), f64::NEG_INFINITY);
+    }
+}
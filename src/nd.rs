@@ -0,0 +1,314 @@
+use crate::kdbush::{is_valid_radius, KdNum, TIndex, TNumber};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Static spatial index for points with `D` dimensions, e.g. `[x, y, z]`
+///
+/// The tree cycles through the axes, splitting along axis `depth % D`. [`KDBush`](crate::KDBush)
+/// remains the index for the common two-dimensional case.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{KDBushN, DEFAULT_NODE_SIZE};
+///
+/// let points = vec![[54.0, 1.0, 3.0], [97.0, 21.0, 5.0], [65.0, 35.0, 9.0]];
+/// let index: KDBushN<3> = KDBushN::create(points, DEFAULT_NODE_SIZE);
+/// let mut result = Vec::new();
+/// index.range([20.0, 30.0, 0.0], [70.0, 70.0, 10.0], |id| result.push(id));
+/// assert_eq!(result, vec![2]);
+/// index.within([60.0, 30.0, 8.0], 10.0, |id| assert_eq!(id, 2));
+/// ```
+pub struct KDBushN<const D: usize, T = TNumber> {
+    pub(crate) ids: Vec<TIndex>,
+    pub(crate) points: Vec<[T; D]>,
+    pub(crate) node_size: u8,
+}
+
+impl<const D: usize, T: KdNum> KDBushN<D, T> {
+    /// Creates an index from points, using their positions as ids
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points
+    /// * `node_size` - Size of the KD-tree node, 64 by default. Higher means faster indexing but slower search, and vise versa
+    pub fn create<P>(points: P, node_size: u8) -> KDBushN<D, T>
+    where
+        P: IntoIterator<Item = [T; D]>,
+    {
+        let points = points.into_iter();
+        let mut index = KDBushN::new(points.size_hint().0, node_size);
+        for (i, p) in points.enumerate() {
+            index.add_point(i, p);
+        }
+        index.build_index();
+        index
+    }
+
+    /// Creates an empty index for points added with [`KDBushN::add_point`]
+    ///
+    /// # Arguments
+    ///
+    /// * `size_hint` - Number of points to add (maybe 0, if unkown).
+    /// * `node_size` - Size of the KD-tree node, a node size of 0 is treated as 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `D` is zero.
+    pub fn new(size_hint: usize, node_size: u8) -> KDBushN<D, T> {
+        assert!(D > 0, "points need at least one dimension");
+        KDBushN {
+            ids: Vec::with_capacity(size_hint),
+            points: Vec::with_capacity(size_hint),
+            node_size: node_size.max(1),
+        }
+    }
+
+    /// Adds a point to the index, which has to be built with [`KDBushN::build_index`] afterwards
+    pub fn add_point(&mut self, id: TIndex, point: [T; D]) {
+        self.ids.push(id);
+        self.points.push(point);
+    }
+
+    /// Builds the index from the added points
+    pub fn build_index(&mut self) {
+        let mut items: Vec<(TIndex, [T; D])> = self
+            .ids
+            .iter()
+            .cloned()
+            .zip(self.points.iter().cloned())
+            .collect();
+        sort_kd(&mut items, self.node_size as usize, 0);
+        for (i, (id, p)) in items.into_iter().enumerate() {
+            self.ids[i] = id;
+            self.points[i] = p;
+        }
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether the index has no points
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Size of the KD-tree node
+    pub fn node_size(&self) -> u8 {
+        self.node_size
+    }
+
+    /// Ids in the order of the tree
+    pub fn ids(&self) -> &[TIndex] {
+        &self.ids
+    }
+
+    /// Points in the order of the tree, matching [`KDBushN::ids`]
+    pub fn points(&self) -> &[[T; D]] {
+        &self.points
+    }
+
+    /// Finds all points within the given axis-aligned box
+    ///
+    /// # Arguments
+    ///
+    /// * `min`, `max` - Minimal and maximal coordinate along each axis
+    /// * `visitor` - Result reader
+    pub fn range<F>(&self, min: [T; D], max: [T; D], mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.is_empty() || !(0..D).all(|a| min[a] <= max[a]) {
+            return;
+        }
+        let inside = |p: &[T; D]| (0..D).all(|a| p[a] >= min[a] && p[a] <= max[a]);
+        let mut stack = vec![(0, self.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                for i in left..right + 1 {
+                    if inside(&self.points[i]) {
+                        visitor(self.ids[i]);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            if inside(&self.points[m]) {
+                visitor(self.ids[m]);
+            }
+            let split = self.points[m][axis];
+            // Right child first, so the left child is visited next
+            if max[axis] >= split {
+                stack.push((m + 1, right, (axis + 1) % D));
+            }
+            if min[axis] <= split {
+                stack.push((left, m - 1, (axis + 1) % D));
+            }
+        }
+    }
+
+    /// Finds all points within a given Euclidean distance from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `q` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader
+    pub fn within<F>(&self, q: [T; D], r: T, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.is_empty() || !is_valid_radius(r) {
+            return;
+        }
+        let r2 = T::sq_diff(r, T::ZERO);
+        let inside = |p: &[T; D]| {
+            let mut d = T::sq_diff(p[0], q[0]);
+            for a in 1..D {
+                d = d + T::sq_diff(p[a], q[a]);
+            }
+            d <= r2
+        };
+        let mut stack = vec![(0, self.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                for i in left..right + 1 {
+                    if inside(&self.points[i]) {
+                        visitor(self.ids[i]);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            if inside(&self.points[m]) {
+                visitor(self.ids[m]);
+            }
+            let split = self.points[m][axis];
+            let near = T::sq_diff(q[axis], split) <= r2;
+            // Right child first, so the left child is visited next
+            if q[axis] >= split || near {
+                stack.push((m + 1, right, (axis + 1) % D));
+            }
+            if q[axis] <= split || near {
+                stack.push((left, m - 1, (axis + 1) % D));
+            }
+        }
+    }
+}
+
+/// Sorts items into a KD-tree, starting with a split along `axis`
+fn sort_kd<T: KdNum, const D: usize>(
+    items: &mut [(TIndex, [T; D])],
+    node_size: usize,
+    axis: usize,
+) {
+    let mut stack = vec![(items, axis)];
+    while let Some((items, axis)) = stack.pop() {
+        let n = items.len();
+        if n == 0 || n - 1 <= node_size {
+            continue;
+        }
+        let m = (n - 1) >> 1;
+        items.select_nth_unstable_by(m, |a, b| a.1[axis].total_cmp(&b.1[axis]));
+        let (left, right) = items.split_at_mut(m);
+        stack.push((&mut right[1..], (axis + 1) % D));
+        stack.push((left, (axis + 1) % D));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+    use crate::kdbush::KDBush;
+
+    fn points() -> Vec<[f64; 3]> {
+        (0..2000)
+            .map(|i| {
+                [
+                    (i * 37 % 101) as f64,
+                    (i * 53 % 97) as f64,
+                    (i * 71 % 89) as f64,
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nd_range() {
+        let points = points();
+        let index: KDBushN<3> = KDBushN::create(points.clone(), 8);
+        assert_eq!(index.len(), points.len());
+
+        let (min, max) = ([10.0, 20.0, 30.0], [60.0, 50.0, 40.0]);
+        let mut result = Vec::new();
+        index.range(min, max, |id| result.push(id));
+        result.sort();
+        let expected: Vec<TIndex> = (0..points.len())
+            .filter(|&i| (0..3).all(|a| points[i][a] >= min[a] && points[i][a] <= max[a]))
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(result, expected);
+
+        let mut result = Vec::new();
+        index.range(max, min, |id| result.push(id));
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_nd_within() {
+        let points = points();
+        let index: KDBushN<3, f64> = KDBushN::create(points.clone(), 8);
+        let q = [50.0, 40.0, 45.0];
+        let mut result = Vec::new();
+        index.within(q, 12.0, |id| result.push(id));
+        result.sort();
+        let expected: Vec<TIndex> = (0..points.len())
+            .filter(|&i| (0..3).map(|a| (points[i][a] - q[a]).powi(2)).sum::<f64>() <= 144.0)
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(result, expected);
+
+        let empty: KDBushN<3> = KDBushN::create(vec![], 8);
+        empty.within(q, 12.0, |_| panic!());
+        index.within(q, -1.0, |_| panic!());
+    }
+
+    #[test]
+    fn test_nd_matches_2d() {
+        let points: Vec<[i32; 2]> = points()
+            .iter()
+            .map(|p| [p[0] as i32, p[1] as i32])
+            .collect();
+        let index: KDBushN<2, i32> = KDBushN::create(points.clone(), 16);
+        let flat = KDBush::create(points.iter().map(|p| (p[0], p[1])).collect::<Vec<_>>(), 16);
+        let mut result = Vec::new();
+        index.within([40, 50], 15, |id| result.push(id));
+        let mut expected = Vec::new();
+        flat.within(40, 50, 15, |id| expected.push(id));
+        result.sort();
+        expected.sort();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_nd_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let coords: Vec<[f64; 2]> = points.iter().map(|&(x, y)| [x, y]).collect();
+            let nd: KDBushN<2> = KDBushN::create(coords, node_size);
+            let mut ids = Vec::new();
+            nd.within([0.0, 0.0], 5.0, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+            let mut ids = Vec::new();
+            nd.range([-1.0, -1.0], [5.0, 5.0], |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::kdbush::{KDBush, KdId, KdNum, TIndex};
+use alloc::collections::VecDeque;
+use core::iter::FusedIterator;
+use core::ops::Range;
+
+/// Node of the KD-tree, as reported by [`KDBush::nodes`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct KdNode<T> {
+    /// Distance from the root, which has depth 0
+    pub depth: usize,
+    /// Positions of the node's items in [`KDBush::ids`] and [`KDBush::points`]
+    pub items: Range<TIndex>,
+    /// Bounding box `[minx, miny, maxx, maxy]` of the node's items
+    pub bbox: [T; 4],
+    /// Split axis and coordinate of the median, `None` for leaves
+    pub split: Option<(usize, T)>,
+}
+
+/// Breadth-first iterator over the nodes of a KD-tree
+pub struct KdNodes<'a, T, I> {
+    index: &'a KDBush<T, I>,
+    /// Nodes still to report, as position ranges with their depth
+    queue: VecDeque<(TIndex, TIndex, usize)>,
+}
+
+impl<T: KdNum, I: KdId> KDBush<T, I> {
+    /// Walks the tree breadth-first, reporting all nodes of one depth before the next
+    ///
+    /// Meant for debugging, rendering the partitioning and checking invariants, e.g. after
+    /// deserializing an index. Each node's bounding box is computed from its items, so a full
+    /// walk takes `O(n log n)` time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::KDBush;
+    ///
+    /// let points: Vec<(f64, f64)> = (0..10).map(|i| (i as f64, (i * 7 % 10) as f64)).collect();
+    /// let index = KDBush::create(points, 2);
+    /// for node in index.nodes() {
+    ///     println!("{} {:?} {:?}", node.depth, node.bbox, node.split);
+    /// }
+    /// assert_eq!(index.nodes().next().unwrap().items, 0..10);
+    /// ```
+    pub fn nodes(&self) -> KdNodes<'_, T, I> {
+        let mut queue = VecDeque::new();
+        if !self.ids.is_empty() {
+            queue.push_back((0, self.ids.len() - 1, 0));
+        }
+        KdNodes { index: self, queue }
+    }
+}
+
+impl<'a, T: KdNum, I: KdId> Iterator for KdNodes<'a, T, I> {
+    type Item = KdNode<T>;
+
+    fn next(&mut self) -> Option<KdNode<T>> {
+        let (left, right, depth) = self.queue.pop_front()?;
+        let points = &self.index.points[left..right + 1];
+        let mut bbox = [points[0][0], points[0][1], points[0][0], points[0][1]];
+        for p in &points[1..] {
+            for axis in 0..2 {
+                if p[axis] < bbox[axis] {
+                    bbox[axis] = p[axis];
+                }
+                if p[axis] > bbox[2 + axis] {
+                    bbox[2 + axis] = p[axis];
+                }
+            }
+        }
+
+        let split = if right - left <= self.index.node_size as usize {
+            None
+        } else {
+            let m = (left + right) >> 1;
+            let axis = depth % 2;
+            self.queue.push_back((left, m - 1, depth + 1));
+            self.queue.push_back((m + 1, right, depth + 1));
+            Some((axis, self.index.points[m][axis]))
+        };
+        Some(KdNode {
+            depth,
+            items: left..right + 1,
+            bbox,
+            split,
+        })
+    }
+}
+
+impl<'a, T: KdNum, I: KdId> FusedIterator for KdNodes<'a, T, I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_nodes() {
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points, 8);
+        let nodes: Vec<KdNode<f64>> = index.nodes().collect();
+
+        assert_eq!(nodes[0].depth, 0);
+        assert_eq!(nodes[0].items, 0..1000);
+        assert!(nodes.windows(2).all(|w| w[0].depth <= w[1].depth));
+
+        let mut covered = 0;
+        for node in &nodes {
+            let points = &index.points()[node.items.clone()];
+            for p in points {
+                assert!(p[0] >= node.bbox[0] && p[0] <= node.bbox[2]);
+                assert!(p[1] >= node.bbox[1] && p[1] <= node.bbox[3]);
+            }
+            match node.split {
+                None => {
+                    assert!(node.items.len() <= 9);
+                    covered += node.items.len();
+                }
+                Some((axis, split)) => {
+                    // Median plus two children
+                    covered += 1;
+                    assert_eq!(axis, node.depth % 2);
+                    let m = (node.items.start + node.items.end - 1) >> 1;
+                    assert!(points[..m - node.items.start]
+                        .iter()
+                        .all(|p| p[axis] <= split));
+                    assert!(points[m - node.items.start + 1..]
+                        .iter()
+                        .all(|p| p[axis] >= split));
+                }
+            }
+        }
+        assert_eq!(covered, index.len());
+        assert_eq!(KDBush::<f64>::create(vec![], 8).nodes().count(), 0);
+    }
+
+    #[test]
+    fn test_nodes_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let index = KDBush::create(points, node_size);
+            assert_eq!(index.nodes().count(), usize::from(n > 0));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::kdbush::{KDBush, PointReader, TIndex, TNumber};
+use alloc::vec::Vec;
+
+/// Ordering of points along a space-filling traversal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpatialOrder {
+    /// Order of the sorted KD-tree
+    Kd,
+    /// Order along a Hilbert curve over the extent of the points
+    Hilbert,
+}
+
+/// Returns the ids of the points in spatial order
+///
+/// Nearby points end up close to each other in the result, so parallel arrays reordered by
+/// this permutation have better cache locality in spatial processing. No index is retained.
+///
+/// # Arguments
+///
+/// * `points` - Input points reader
+/// * `order` - Kind of spatial order
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{spatial_order, SpatialOrder};
+///
+/// let points = vec![(0.0, 0.0), (9.0, 9.0), (0.0, 1.0), (9.0, 8.0)];
+/// let order = spatial_order(points, SpatialOrder::Hilbert);
+/// assert_eq!(order, vec![0, 2, 1, 3]);
+/// ```
+pub fn spatial_order<R: PointReader>(points: R, order: SpatialOrder) -> Vec<TIndex> {
+    match order {
+        SpatialOrder::Kd => KDBush::create(points, 1).ids,
+        SpatialOrder::Hilbert => {
+            let mut items: Vec<(TIndex, TNumber, TNumber)> = Vec::with_capacity(points.size_hint());
+            points.visit_all(|id, x, y| items.push((id, x, y)));
+            let (minx, miny, maxx, maxy) = items.iter().fold(
+                (
+                    f64::INFINITY,
+                    f64::INFINITY,
+                    f64::NEG_INFINITY,
+                    f64::NEG_INFINITY,
+                ),
+                |b, &(_, x, y)| (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y)),
+            );
+            // Grid cells of a 16 bit Hilbert curve
+            let max = f64::from(u16::MAX);
+            let cell = |v: TNumber, min: TNumber, max_v: TNumber| {
+                let scaled = if max_v > min {
+                    (v - min) / (max_v - min) * max
+                } else {
+                    0.0
+                };
+                // NaN becomes zero
+                scaled.clamp(0.0, max) as u32
+            };
+            let mut keyed: Vec<(u64, TIndex)> = items
+                .iter()
+                .map(|&(id, x, y)| (hilbert(cell(x, minx, maxx), cell(y, miny, maxy)), id))
+                .collect();
+            keyed.sort_unstable();
+            keyed.into_iter().map(|(_, id)| id).collect()
+        }
+    }
+}
+
+/// Distance of a grid cell along a 16 bit Hilbert curve
+fn hilbert(mut x: u32, mut y: u32) -> u64 {
+    let mut d = 0u64;
+    let mut s = 1u32 << 15;
+    while s > 0 {
+        let rx = u32::from(x & s > 0);
+        let ry = u32::from(y & s > 0);
+        d += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+        // Rotate the quadrant
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            core::mem::swap(&mut x, &mut y);
+        }
+        s >>= 1;
+    }
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hilbert() {
+        // Order of the cells of a 2x2 curve
+        let s = 1 << 15;
+        assert!(hilbert(0, 0) < hilbert(0, s));
+        assert!(hilbert(0, s) < hilbert(s, s));
+        assert!(hilbert(s, s) < hilbert(s, 0));
+        // Consecutive cells along the curve are neighbors
+        let mut cells: Vec<(u64, u32, u32)> = (0..64)
+            .flat_map(|x| (0..64).map(move |y| (x, y)))
+            .map(|(x, y)| (hilbert(x << 10, y << 10), x, y))
+            .collect();
+        cells.sort();
+        for w in cells.windows(2) {
+            let dist =
+                (w[0].1 as i32 - w[1].1 as i32).abs() + (w[0].2 as i32 - w[1].2 as i32).abs();
+            assert_eq!(dist, 1);
+        }
+    }
+
+    #[test]
+    fn test_spatial_order() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        for &order in &[SpatialOrder::Kd, SpatialOrder::Hilbert] {
+            let result = spatial_order(points.clone(), order);
+            let mut sorted = result.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..500).collect::<Vec<_>>());
+        }
+        assert_eq!(
+            spatial_order(points.clone(), SpatialOrder::Kd),
+            KDBush::create(points, 1).ids
+        );
+        assert!(spatial_order(vec![], SpatialOrder::Hilbert).is_empty());
+        assert_eq!(
+            spatial_order(vec![(1.0, 1.0), (1.0, 1.0)], SpatialOrder::Hilbert),
+            vec![0, 1]
+        );
+    }
+}
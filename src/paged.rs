@@ -0,0 +1,373 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, KDBush, TIndex, TNumber};
+use crate::stepped::Shape;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Pending work of a paged query
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Task {
+    /// Tree node given by its position range and split axis
+    Node(TIndex, TIndex, usize),
+    /// Remaining positions of a partially scanned leaf
+    Scan(TIndex, TIndex),
+}
+
+/// Version of the encoded resume tokens
+const TOKEN_VERSION: u8 = 1;
+/// Encoded size of a task: kind and axis, then two positions
+const TASK_SIZE: usize = 17;
+
+/// Continuation point of a paged query
+///
+/// Only valid for the query which returned it and as long as the index is unchanged.
+/// Tokens can be passed between processes, e.g. as cursor of a paged HTTP API, with
+/// [`ResumeToken::to_bytes`] and [`ResumeToken::from_bytes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResumeToken {
+    tasks: Vec<Task>,
+}
+
+impl ResumeToken {
+    /// Encodes the token as bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, ResumeToken};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], 1);
+    /// let token = index.range_page(0.0, 0.0, 100.0, 100.0, 1, None, |_| {}).unwrap();
+    /// let bytes = token.to_bytes();
+    /// assert_eq!(ResumeToken::from_bytes(&bytes, &index), Some(token));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.tasks.len() * TASK_SIZE);
+        bytes.push(TOKEN_VERSION);
+        for task in &self.tasks {
+            let (kind, left, right) = match *task {
+                Task::Node(left, right, axis) => (axis as u8, left, right),
+                Task::Scan(left, right) => (2, left, right),
+            };
+            bytes.push(kind);
+            bytes.extend_from_slice(&(left as u64).to_le_bytes());
+            bytes.extend_from_slice(&(right as u64).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a token encoded by [`ResumeToken::to_bytes`] for resuming a query on `index`
+    ///
+    /// Returns `None` if the bytes are no valid token or refer to positions outside of the
+    /// index. A token of another index with the same number of items is not detected.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Encoded token
+    /// * `index` - Index the token is used with
+    pub fn from_bytes<T, I>(data: &[u8], index: &KDBush<T, I>) -> Option<ResumeToken> {
+        let (&version, data) = data.split_first()?;
+        if version != TOKEN_VERSION || data.is_empty() || data.len() % TASK_SIZE != 0 {
+            return None;
+        }
+        let position = |bytes: &[u8]| {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(bytes);
+            usize::try_from(u64::from_le_bytes(buf))
+                .ok()
+                .filter(|&i| i < index.ids.len())
+        };
+        let tasks = data
+            .chunks_exact(TASK_SIZE)
+            .map(|b| {
+                let left = position(&b[1..9])?;
+                let right = position(&b[9..17])?;
+                if left > right {
+                    return None;
+                }
+                match b[0] {
+                    axis @ (0 | 1) => Some(Task::Node(left, right, usize::from(axis))),
+                    2 => Some(Task::Scan(left, right)),
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<Task>>>()?;
+        Some(ResumeToken { tasks })
+    }
+}
+
+impl KDBush {
+    /// Finds the next page of items within the given bounding box
+    ///
+    /// Reports at most `limit` items and returns a token for continuing the query after the
+    /// last reported item, or `None` if the query is complete. Pages contain each item
+    /// exactly once, in the same order as a single unlimited query.
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `limit` - Maximal number of items reported
+    /// * `resume` - Token returned by the previous page, `None` for the first page
+    /// * `visitor` - Result reader
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// let mut page = Vec::new();
+    /// let token = index.range_page(0.0, 0.0, 100.0, 100.0, 2, None, |id| page.push(id));
+    /// assert_eq!(page.len(), 2);
+    /// let token = index.range_page(0.0, 0.0, 100.0, 100.0, 2, token.as_ref(), |id| page.push(id));
+    /// assert_eq!(page.len(), 3);
+    /// assert!(token.is_none());
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn range_page<F>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        limit: usize,
+        resume: Option<&ResumeToken>,
+        visitor: F,
+    ) -> Option<ResumeToken>
+    where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_bbox(minx, miny, maxx, maxy) {
+            return None;
+        }
+        let shape = Shape::Range([minx, miny, maxx, maxy]);
+        self.page_idx(&shape, limit, resume, visitor)
+    }
+
+    /// Finds the next page of items within a given radius from the query point
+    ///
+    /// See [`KDBush::range_page`] for the paging.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `limit` - Maximal number of items reported
+    /// * `resume` - Token returned by the previous page, `None` for the first page
+    /// * `visitor` - Result reader
+    pub fn within_page<F>(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        r: TNumber,
+        limit: usize,
+        resume: Option<&ResumeToken>,
+        visitor: F,
+    ) -> Option<ResumeToken>
+    where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_radius(r) {
+            return None;
+        }
+        let shape = Shape::Within { qx, qy, r2: r * r };
+        self.page_idx(&shape, limit, resume, visitor)
+    }
+
+    fn page_idx<F>(
+        &self,
+        shape: &Shape,
+        limit: usize,
+        resume: Option<&ResumeToken>,
+        mut visitor: F,
+    ) -> Option<ResumeToken>
+    where
+        F: FnMut(TIndex),
+    {
+        let mut tasks = match resume {
+            Some(token) => token.tasks.clone(),
+            None if self.ids.is_empty() => return None,
+            None => vec![Task::Node(0, self.ids.len() - 1, 0)],
+        };
+        let mut count = 0;
+        while count < limit {
+            let task = tasks.pop()?;
+            let (left, right, axis) = match task {
+                Task::Scan(left, right) => (left, right, None),
+                Task::Node(left, right, _) if right - left <= self.node_size as usize => {
+                    (left, right, None)
+                }
+                Task::Node(left, right, axis) => (left, right, Some(axis)),
+            };
+            match axis {
+                None => {
+                    for i in left..right + 1 {
+                        if shape.contains(self.points[i][0], self.points[i][1]) {
+                            visitor(self.ids[i]);
+                            count += 1;
+                            if count == limit && i < right {
+                                tasks.push(Task::Scan(i + 1, right));
+                                break;
+                            }
+                        }
+                    }
+                }
+                Some(axis) => {
+                    let m = (left + right) >> 1;
+                    let p = self.points[m];
+                    if shape.contains(p[0], p[1]) {
+                        visitor(self.ids[m]);
+                        count += 1;
+                    }
+                    let (below, above) = shape.reaches(p[axis], axis);
+                    if above {
+                        tasks.push(Task::Node(m + 1, right, (axis + 1) % 2));
+                    }
+                    if below {
+                        tasks.push(Task::Node(left, m - 1, (axis + 1) % 2));
+                    }
+                }
+            }
+        }
+        if tasks.is_empty() {
+            None
+        } else {
+            Some(ResumeToken { tasks })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_paged_queries() {
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points, 8);
+
+        let mut expected = Vec::new();
+        index.range(10.0, 20.0, 60.0, 50.0, |id| expected.push(id));
+        for &limit in &[1, 7, 64, 10000] {
+            let mut result = Vec::new();
+            let mut token = None;
+            loop {
+                let mut page = Vec::new();
+                token = index.range_page(10.0, 20.0, 60.0, 50.0, limit, token.as_ref(), |id| {
+                    page.push(id)
+                });
+                assert!(page.len() <= limit);
+                result.extend(page);
+                if token.is_none() {
+                    break;
+                }
+            }
+            let mut expected = expected.clone();
+            expected.sort();
+            result.sort();
+            assert_eq!(expected, result);
+        }
+
+        let mut expected = Vec::new();
+        index.within(40.0, 50.0, 15.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        let token = index.within_page(40.0, 50.0, 15.0, 10, None, |id| result.push(id));
+        assert_eq!(result.len(), 10);
+        assert!(index
+            .within_page(40.0, 50.0, 15.0, 0, token.as_ref(), |_| panic!())
+            .is_some());
+        let token = index.within_page(40.0, 50.0, 15.0, usize::MAX, token.as_ref(), |id| {
+            result.push(id)
+        });
+        assert!(token.is_none());
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_paged_queries_degenerate() {
+        let empty = KDBush::create(vec![], 8);
+        assert!(empty.within_page(0.0, 0.0, 1.0, 5, None, |_| {}).is_none());
+        let index = KDBush::create(vec![(1.0, 1.0)], 8);
+        assert!(index.within_page(0.0, 0.0, -1.0, 5, None, |_| {}).is_none());
+        assert!(index
+            .range_page(2.0, 0.0, 0.0, 2.0, 5, None, |_| {})
+            .is_none());
+    }
+
+    #[test]
+    fn test_token_bytes() {
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points, 8);
+
+        let mut expected = Vec::new();
+        index.within(40.0, 50.0, 30.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        let mut cursor = Vec::new();
+        loop {
+            let resume = if cursor.is_empty() {
+                None
+            } else {
+                Some(ResumeToken::from_bytes(&cursor, &index).unwrap())
+            };
+            let token =
+                index.within_page(40.0, 50.0, 30.0, 13, resume.as_ref(), |id| result.push(id));
+            match token {
+                Some(token) => cursor = token.to_bytes(),
+                None => break,
+            }
+        }
+        assert_eq!(expected, result);
+
+        let token = index
+            .within_page(40.0, 50.0, 30.0, 13, None, |_| {})
+            .unwrap();
+        let bytes = token.to_bytes();
+        assert_eq!(ResumeToken::from_bytes(&bytes, &index), Some(token));
+        assert_eq!(ResumeToken::from_bytes(&[], &index), None);
+        assert_eq!(ResumeToken::from_bytes(&[TOKEN_VERSION], &index), None);
+        assert_eq!(
+            ResumeToken::from_bytes(&bytes[..bytes.len() - 1], &index),
+            None
+        );
+        let mut version = bytes.clone();
+        version[0] = 0;
+        assert_eq!(ResumeToken::from_bytes(&version, &index), None);
+        let mut kind = bytes.clone();
+        kind[1] = 3;
+        assert_eq!(ResumeToken::from_bytes(&kind, &index), None);
+
+        // Positions beyond a smaller index
+        let small = KDBush::create(vec![(1.0, 1.0); 10], 8);
+        let large = ResumeToken {
+            tasks: vec![Task::Scan(5, 900)],
+        };
+        assert_eq!(ResumeToken::from_bytes(&large.to_bytes(), &small), None);
+        let inverted = ResumeToken {
+            tasks: vec![Task::Node(7, 5, 0)],
+        };
+        assert_eq!(ResumeToken::from_bytes(&inverted.to_bytes(), &index), None);
+    }
+
+    #[test]
+    fn test_paged_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids = Vec::new();
+            let token = index.range_page(-1.0, -1.0, 5.0, 5.0, 10, None, |id| ids.push(id));
+            assert!(token.is_none());
+            ids.sort();
+            assert_eq!(ids, all);
+            let mut ids = Vec::new();
+            let token = index.within_page(0.0, 0.0, 5.0, 10, None, |id| ids.push(id));
+            assert!(token.is_none());
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::kdbush::{Bbox, KDBush, PointReader, TIndex};
+use alloc::vec::Vec;
+
+/// Spatially contiguous part of a point set
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    /// Bounding box of the members, `[minx, miny, maxx, maxy]`
+    pub bounds: Bbox,
+    /// Ids of the members
+    pub ids: Vec<TIndex>,
+}
+
+/// Splits a point set into spatially contiguous chunks of roughly equal size
+///
+/// The points are split recursively at the median of the wider extent, like the
+/// construction of the index. Chunk sizes differ by at most one. If there are fewer points
+/// than chunks requested, each point gets its own chunk.
+///
+/// # Arguments
+///
+/// * `points` - Input points reader
+/// * `n` - Number of chunks
+///
+/// # Example
+///
+/// ```
+/// use kdbush::partition;
+///
+/// let points: Vec<(f64, f64)> = (0..100).map(|i| ((i % 10) as f64, (i / 10) as f64)).collect();
+/// let chunks = partition(points, 4);
+/// assert_eq!(chunks.len(), 4);
+/// assert!(chunks.iter().all(|chunk| chunk.ids.len() == 25));
+/// ```
+pub fn partition<R: PointReader>(points: R, n: usize) -> Vec<Chunk> {
+    let mut index = KDBush::new(points.size_hint(), 1);
+    points.visit_all(|id, x, y| index.add_point(id, x, y));
+    let len = index.ids.len();
+    let n = n.min(len);
+    let mut chunks = Vec::with_capacity(n);
+    if n > 0 {
+        split(&mut index, 0, len, n, &mut chunks);
+    }
+    chunks
+}
+
+/// Splits the positions `left..right` into `n` chunks
+fn split(index: &mut KDBush, left: TIndex, right: TIndex, n: usize, chunks: &mut Vec<Chunk>) {
+    let bounds = extent(index, left, right);
+    if n == 1 {
+        chunks.push(Chunk {
+            bounds,
+            ids: index.ids[left..right].to_vec(),
+        });
+        return;
+    }
+    let axis = if bounds[2] - bounds[0] >= bounds[3] - bounds[1] {
+        0
+    } else {
+        1
+    };
+    let n_left = n / 2;
+    let k = left + (right - left) * n_left / n;
+    index.items().select(k, left, right - 1, axis);
+    split(index, left, k, n_left, chunks);
+    split(index, k, right, n - n_left, chunks);
+}
+
+fn extent(index: &KDBush, left: TIndex, right: TIndex) -> Bbox {
+    index.points[left..right].iter().fold(
+        [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ],
+        |b, p| {
+            [
+                b[0].min(p[0]),
+                b[1].min(p[1]),
+                b[2].max(p[0]),
+                b[3].max(p[1]),
+            ]
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition() {
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|i| ((i * 37 % 101) as f64 * 3.0, (i * 53 % 97) as f64))
+            .collect();
+        let chunks = partition(points.clone(), 7);
+        assert_eq!(chunks.len(), 7);
+
+        let mut ids: Vec<usize> = chunks.iter().flat_map(|c| c.ids.clone()).collect();
+        ids.sort();
+        assert_eq!(ids, (0..1000).collect::<Vec<_>>());
+        for chunk in &chunks {
+            assert!(chunk.ids.len() == 142 || chunk.ids.len() == 143);
+            for &id in &chunk.ids {
+                let (x, y) = points[id];
+                let b = chunk.bounds;
+                assert!(x >= b[0] && x <= b[2] && y >= b[1] && y <= b[3]);
+            }
+        }
+        // The first split is along the wider x extent
+        let max_x = chunks[..3].iter().map(|c| c.bounds[2]).fold(0.0, f64::max);
+        let min_x = chunks[3..].iter().map(|c| c.bounds[0]).fold(1e9, f64::min);
+        assert!(max_x <= min_x);
+    }
+
+    #[test]
+    fn test_partition_few_points() {
+        assert!(partition(vec![(1.0, 2.0)], 0).is_empty());
+        assert!(partition(vec![], 3).is_empty());
+        let chunks = partition(vec![(1.0, 2.0), (3.0, 4.0)], 5);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].bounds, [1.0, 2.0, 1.0, 2.0]);
+        assert_eq!(chunks[1].ids, vec![1]);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::kdbush::{is_valid_radius, Bbox, KDBush, TIndex, TNumber};
+use crate::math;
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+
+/// Heap entry ordered by its key only
+pub(crate) struct Ranked<T> {
+    pub key: TNumber,
+    pub item: T,
+}
+
+impl<T> PartialEq for Ranked<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Ranked<T> {}
+
+impl<T> PartialOrd for Ranked<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Ranked<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.partial_cmp(&other.key).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Tree node range with the extent of its items
+struct Node {
+    left: TIndex,
+    right: TIndex,
+    axis: usize,
+    bounds: Bbox,
+}
+
+impl KDBush {
+    /// Finds the `k` items with the lowest score
+    ///
+    /// Returns `(id, score)` pairs, ordered by increasing score.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Maximal number of results
+    /// * `score` - Score of an item, called with its id and its offset `x - qx`, `y - qy`
+    /// * `lower_bound` - Lower bound of the scores of all items with offsets within `min_dx`, `min_dy`, `max_dx`, `max_dy`. Must never exceed an actual score, otherwise results may be missed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let popularity = vec![0.0, 10.0, 2.0];
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// // Distance, reduced by up to 10 for popular places
+    /// let best = index.best_k(
+    ///     60.0,
+    ///     20.0,
+    ///     2,
+    ///     |id, dx, dy| (dx * dx + dy * dy).sqrt() - popularity[id],
+    ///     |min_dx, min_dy, max_dx, max_dy| {
+    ///         let dx = min_dx.max(0.0).max(-max_dx);
+    ///         let dy = min_dy.max(0.0).max(-max_dy);
+    ///         (dx * dx + dy * dy).sqrt() - 10.0
+    ///     },
+    /// );
+    /// assert_eq!(best.len(), 2);
+    /// ```
+    pub fn best_k<S, B>(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        k: usize,
+        score: S,
+        lower_bound: B,
+    ) -> Vec<(TIndex, TNumber)>
+    where
+        S: Fn(TIndex, TNumber, TNumber) -> TNumber,
+        B: Fn(TNumber, TNumber, TNumber, TNumber) -> TNumber,
+    {
+        let mut best = self.result_heap(k);
+        self.best_k_idx(
+            k,
+            f64::INFINITY,
+            |i| score(self.ids[i], self.points[i][0] - qx, self.points[i][1] - qy),
+            |b| lower_bound(b[0] - qx, b[1] - qy, b[2] - qx, b[3] - qy),
+            &mut best,
+        );
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|r| (self.ids[r.item], r.key))
+            .collect()
+    }
+
+    /// Finds the `k` items nearest to the query point
+    ///
+    /// Returns the ids ordered by increasing distance. Uses a best-first traversal, so no
+    /// search radius has to be guessed.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Maximal number of results
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+    /// assert_eq!(index.nearest(60.0, 30.0, 2), vec![2, 0]);
+    /// assert_eq!(index.nearest_within(60.0, 30.0, 2, 10.0), vec![2]);
+    /// ```
+    pub fn nearest(&self, qx: TNumber, qy: TNumber, k: usize) -> Vec<TIndex> {
+        self.knn_idx(qx, qy, k)
+            .into_iter()
+            .map(|(i, _)| self.ids[i])
+            .collect()
+    }
+
+    /// Finds the `k` items nearest to the query point with their distances
+    ///
+    /// Returns `(id, distance)` pairs, ordered by increasing distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Maximal number of results
+    pub fn nearest_with_dist(&self, qx: TNumber, qy: TNumber, k: usize) -> Vec<(TIndex, TNumber)> {
+        self.knn_idx(qx, qy, k)
+            .into_iter()
+            .map(|(i, d2)| (self.ids[i], math::sqrt(d2)))
+            .collect()
+    }
+
+    /// Finds the `k` items nearest to the query point, up to a maximal distance
+    ///
+    /// Returns the ids ordered by increasing distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `k` - Maximal number of results
+    /// * `max_dist` - Maximal distance of the results
+    pub fn nearest_within(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        k: usize,
+        max_dist: TNumber,
+    ) -> Vec<TIndex> {
+        if !is_valid_radius(max_dist) {
+            return Vec::new();
+        }
+        self.knn_within_idx(qx, qy, k, max_dist * max_dist)
+            .into_iter()
+            .map(|(i, _)| self.ids[i])
+            .collect()
+    }
+
+    /// Finds the `k` positions nearest to the query point
+    ///
+    /// Returns `(position, squared distance)` pairs, ordered by increasing distance.
+    pub(crate) fn knn_idx(&self, qx: TNumber, qy: TNumber, k: usize) -> Vec<(TIndex, TNumber)> {
+        self.knn_within_idx(qx, qy, k, f64::INFINITY)
+    }
+
+    /// Finds the `k` positions nearest to the query point, up to a squared distance
+    fn knn_within_idx(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        k: usize,
+        max_d2: TNumber,
+    ) -> Vec<(TIndex, TNumber)> {
+        let mut best = self.result_heap(k);
+        self.best_k_idx(
+            k,
+            max_d2,
+            |i| KDBush::sq_dist(self.points[i][0], self.points[i][1], qx, qy),
+            |b| {
+                let dx = (b[0] - qx).max(0.0).max(qx - b[2]);
+                let dy = (b[1] - qy).max(0.0).max(qy - b[3]);
+                dx * dx + dy * dy
+            },
+            &mut best,
+        );
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|r| (r.item, r.key))
+            .collect()
+    }
+
+    /// Max-heap for up to `k` results, sized by the number of items rather than `k` alone
+    pub(crate) fn result_heap<T: Ord>(&self, k: usize) -> BinaryHeap<T> {
+        BinaryHeap::with_capacity(k.min(self.ids.len()).saturating_add(1))
+    }
+
+    /// Best-first search for the `k` positions with the lowest score
+    ///
+    /// Positions scoring above `max_key` are skipped, and so are nodes whose lower bound
+    /// exceeds it. `best` is a max-heap which holds the results on return.
+    pub(crate) fn best_k_idx<S, B>(
+        &self,
+        k: usize,
+        max_key: TNumber,
+        score: S,
+        lower_bound: B,
+        best: &mut BinaryHeap<Ranked<TIndex>>,
+    ) where
+        S: Fn(TIndex) -> TNumber,
+        B: Fn(&Bbox) -> TNumber,
+    {
+        if self.ids.is_empty() || k == 0 {
+            return;
+        }
+        let add = |best: &mut BinaryHeap<Ranked<TIndex>>, i: TIndex| {
+            let key = score(i);
+            if key > max_key {
+                return;
+            }
+            if best.len() < k {
+                best.push(Ranked { key, item: i });
+            } else if best.peek().is_some_and(|worst| key < worst.key) {
+                best.pop();
+                best.push(Ranked { key, item: i });
+            }
+        };
+
+        // Min-heap of nodes by the lower bound of their scores
+        let mut queue = BinaryHeap::new();
+        let root = Node {
+            left: 0,
+            right: self.ids.len() - 1,
+            axis: 0,
+            bounds: [
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                f64::INFINITY,
+            ],
+        };
+        queue.push(Reverse(Ranked {
+            key: f64::NEG_INFINITY,
+            item: root,
+        }));
+
+        while let Some(Reverse(Ranked { key, item: node })) = queue.pop() {
+            if best.len() == k && best.peek().is_some_and(|worst| key >= worst.key) {
+                break;
+            }
+            let Node {
+                left,
+                right,
+                axis,
+                bounds,
+            } = node;
+            if right - left <= self.node_size as usize {
+                for i in left..right + 1 {
+                    add(best, i);
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            add(best, m);
+
+            let mut lower = bounds;
+            let mut upper = bounds;
+            lower[2 + axis] = self.points[m][axis];
+            upper[axis] = self.points[m][axis];
+            for &(left, right, bounds) in &[(left, m - 1, lower), (m + 1, right, upper)] {
+                let bounds = match self.bboxes {
+                    Some(ref bboxes) => bboxes[(left + right) >> 1],
+                    None => bounds,
+                };
+                let key = lower_bound(&bounds);
+                if key > max_key {
+                    continue;
+                }
+                queue.push(Reverse(Ranked {
+                    key,
+                    item: Node {
+                        left,
+                        right,
+                        axis: (axis + 1) % 2,
+                        bounds,
+                    },
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_best_k() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let weight = |id: usize| (id % 7) as f64;
+        let score = |id: usize, dx: f64, dy: f64| (dx * dx + dy * dy).sqrt() - weight(id);
+        let lower_bound = |min_dx: f64, min_dy: f64, max_dx: f64, max_dy: f64| {
+            let dx = min_dx.max(0.0).max(-max_dx);
+            let dy = min_dy.max(0.0).max(-max_dy);
+            (dx * dx + dy * dy).sqrt() - 6.0
+        };
+
+        let mut expected: Vec<f64> = points
+            .iter()
+            .enumerate()
+            .map(|(id, p)| score(id, p.0 - 40.0, p.1 - 60.0))
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.truncate(10);
+
+        for index in &[
+            KDBush::create(points.clone(), 8),
+            KDBush::create_with_bboxes(points.clone(), 8),
+        ] {
+            let best = index.best_k(40.0, 60.0, 10, score, lower_bound);
+            let scores: Vec<f64> = best.iter().map(|b| b.1).collect();
+            assert_eq!(expected, scores);
+            for (id, s) in best {
+                assert_eq!(s, score(id, points[id].0 - 40.0, points[id].1 - 60.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_nearest() {
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points.clone(), 8);
+        let dist = |id: usize| KDBush::sq_dist(points[id].0, points[id].1, 40.3, 60.7);
+        let mut expected: Vec<usize> = (0..points.len()).collect();
+        expected.sort_by(|&a, &b| dist(a).total_cmp(&dist(b)));
+
+        let nearest = index.nearest(40.3, 60.7, 20);
+        assert_eq!(
+            nearest.iter().map(|&id| dist(id)).collect::<Vec<_>>(),
+            expected[..20]
+                .iter()
+                .map(|&id| dist(id))
+                .collect::<Vec<_>>()
+        );
+        let within = index.nearest_within(40.3, 60.7, 20, 8.0);
+        let count = expected.iter().filter(|&&id| dist(id) <= 64.0).count();
+        assert!(count > 0 && count < 20);
+        assert_eq!(within, nearest[..count]);
+        for (&id, (id_with_dist, d)) in nearest.iter().zip(index.nearest_with_dist(40.3, 60.7, 20))
+        {
+            assert_eq!((id, d), (id_with_dist, dist(id).sqrt()));
+        }
+        assert!(index.nearest_within(40.3, 60.7, 20, -1.0).is_empty());
+        assert_eq!(index.nearest(0.0, 0.0, 1000).len(), 500);
+        assert!(KDBush::create(vec![], 8).nearest(0.0, 0.0, 3).is_empty());
+    }
+
+    #[test]
+    fn test_best_k_few_items() {
+        let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0)], 10);
+        let best = index.best_k(0.0, 0.0, 5, |_, dx, _| dx, |min_dx, _, _, _| min_dx);
+        assert_eq!(best, vec![(0, 54.0), (1, 97.0)]);
+        assert!(index
+            .best_k(0.0, 0.0, 0, |_, dx, _| dx, |min_dx, _, _, _| min_dx)
+            .is_empty());
+        let empty = KDBush::create(vec![], 10);
+        assert!(empty
+            .best_k(0.0, 0.0, 5, |_, dx, _| dx, |min_dx, _, _, _| min_dx)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_nearest_large_k_and_pruning() {
+        let points: Vec<(f64, f64)> = (0..5000)
+            .map(|i| ((i * 37 % 1001) as f64, (i * 53 % 997) as f64))
+            .collect();
+        let index = KDBush::create(points.clone(), 8);
+        assert_eq!(index.nearest(0.0, 0.0, usize::MAX).len(), 5000);
+        assert_eq!(index.nearest_with_dist(0.0, 0.0, 1 << 40).len(), 5000);
+
+        let within = index.nearest_within(500.0, 500.0, usize::MAX, 20.0);
+        let count = points
+            .iter()
+            .filter(|p| KDBush::sq_dist(p.0, p.1, 500.0, 500.0) <= 400.0)
+            .count();
+        assert_eq!(within.len(), count);
+
+        // The maximal distance prunes the traversal, not only the results
+        let scored = |max_key: f64| {
+            let calls = core::cell::Cell::new(0);
+            let mut best = index.result_heap(usize::MAX);
+            index.best_k_idx(
+                usize::MAX,
+                max_key,
+                |i| {
+                    calls.set(calls.get() + 1);
+                    KDBush::sq_dist(index.points[i][0], index.points[i][1], 500.0, 500.0)
+                },
+                |b| {
+                    let dx = (b[0] - 500.0).max(0.0).max(500.0 - b[2]);
+                    let dy = (b[1] - 500.0).max(0.0).max(500.0 - b[3]);
+                    dx * dx + dy * dy
+                },
+                &mut best,
+            );
+            calls.get()
+        };
+        assert_eq!(scored(f64::INFINITY), 5000);
+        assert!(scored(400.0) < 500);
+    }
+
+    #[test]
+    fn test_nearest_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids = index.nearest(0.0, 0.0, 5);
+            ids.sort();
+            assert_eq!(ids, all);
+            assert_eq!(index.nearest_with_dist(0.0, 0.0, 5).len(), all.len());
+            let mut ids = index.nearest_within(0.0, 0.0, 5, 5.0);
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Linear scans of leaf nodes
+//!
+//! With the `simd` feature, points are tested in chunks of [`LANES`] without branches and the
+//! matches are visited from a bit mask afterwards, which compilers turn into vector
+//! instructions. Both variants visit matches in the same order.
+
+use crate::kdbush::{KdNum, TIndex};
+
+/// Number of points tested together
+#[cfg(feature = "simd")]
+const LANES: usize = 4;
+
+/// Visits the positions of all points within a bounding box
+///
+/// `offset` is the position of the first point.
+#[cfg(not(feature = "simd"))]
+pub(crate) fn scan_range<T: KdNum, E, F>(
+    points: &[[T; 2]],
+    offset: TIndex,
+    bbox: [T; 4],
+    visitor: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(TIndex) -> Result<(), E>,
+{
+    for (i, p) in points.iter().enumerate() {
+        if p[0] >= bbox[0] && p[0] <= bbox[2] && p[1] >= bbox[1] && p[1] <= bbox[3] {
+            visitor(offset + i)?;
+        }
+    }
+    Ok(())
+}
+
+/// Visits the positions and squared distances of all points within a squared radius
+///
+/// `offset` is the position of the first point.
+#[cfg(not(feature = "simd"))]
+pub(crate) fn scan_within<T: KdNum, E, F>(
+    points: &[[T; 2]],
+    offset: TIndex,
+    q: [T; 2],
+    r2: T::Dist,
+    visitor: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(TIndex, T::Dist) -> Result<(), E>,
+{
+    for (i, p) in points.iter().enumerate() {
+        let d2 = T::sq_diff(p[0], q[0]) + T::sq_diff(p[1], q[1]);
+        if d2 <= r2 {
+            visitor(offset + i, d2)?;
+        }
+    }
+    Ok(())
+}
+
+/// Visits the positions of all points within a bounding box
+///
+/// `offset` is the position of the first point.
+#[cfg(feature = "simd")]
+pub(crate) fn scan_range<T: KdNum, E, F>(
+    points: &[[T; 2]],
+    offset: TIndex,
+    bbox: [T; 4],
+    visitor: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(TIndex) -> Result<(), E>,
+{
+    let inside =
+        |p: &[T; 2]| (p[0] >= bbox[0]) & (p[0] <= bbox[2]) & (p[1] >= bbox[1]) & (p[1] <= bbox[3]);
+    let mut chunks = points.chunks_exact(LANES);
+    let mut base = offset;
+    for chunk in &mut chunks {
+        let mut mask = 0u32;
+        for (k, p) in chunk.iter().enumerate() {
+            mask |= (inside(p) as u32) << k;
+        }
+        visit_mask(mask, base, &mut *visitor)?;
+        base += LANES;
+    }
+    for (k, p) in chunks.remainder().iter().enumerate() {
+        if inside(p) {
+            visitor(base + k)?;
+        }
+    }
+    Ok(())
+}
+
+/// Visits the positions and squared distances of all points within a squared radius
+///
+/// `offset` is the position of the first point.
+#[cfg(feature = "simd")]
+pub(crate) fn scan_within<T: KdNum, E, F>(
+    points: &[[T; 2]],
+    offset: TIndex,
+    q: [T; 2],
+    r2: T::Dist,
+    visitor: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(TIndex, T::Dist) -> Result<(), E>,
+{
+    let sq_dist = |p: &[T; 2]| T::sq_diff(p[0], q[0]) + T::sq_diff(p[1], q[1]);
+    let mut chunks = points.chunks_exact(LANES);
+    let mut base = offset;
+    for chunk in &mut chunks {
+        let d2: [T::Dist; LANES] = core::array::from_fn(|k| sq_dist(&chunk[k]));
+        let mut mask = 0u32;
+        for (k, &d) in d2.iter().enumerate() {
+            mask |= ((d <= r2) as u32) << k;
+        }
+        visit_mask(mask, base, |i| visitor(i, d2[i - base]))?;
+        base += LANES;
+    }
+    for (k, p) in chunks.remainder().iter().enumerate() {
+        let d2 = sq_dist(p);
+        if d2 <= r2 {
+            visitor(base + k, d2)?;
+        }
+    }
+    Ok(())
+}
+
+/// Visits the positions of the set bits of a mask in increasing order
+#[cfg(feature = "simd")]
+fn visit_mask<E, F>(mut mask: u32, base: TIndex, mut visitor: F) -> Result<(), E>
+where
+    F: FnMut(TIndex) -> Result<(), E>,
+{
+    while mask != 0 {
+        visitor(base + mask.trailing_zeros() as usize)?;
+        mask &= mask - 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let points: Vec<[f64; 2]> = (0..23).map(|i| [i as f64, (i % 5) as f64]).collect();
+        let mut result = Vec::new();
+        let ok: Result<(), ()> = scan_range(&points, 100, [3.0, 1.0, 17.0, 2.0], &mut |i| {
+            result.push(i);
+            Ok(())
+        });
+        assert!(ok.is_ok());
+        let expected: Vec<usize> = (3..18)
+            .filter(|i| (1..=2).contains(&(i % 5)))
+            .map(|i| i + 100)
+            .collect();
+        assert_eq!(result, expected);
+
+        let mut result = Vec::new();
+        let ok: Result<(), ()> = scan_within(&points, 0, [10.0, 2.0], 9.0, &mut |i, d2| {
+            result.push((i, d2));
+            Ok(())
+        });
+        assert!(ok.is_ok());
+        let expected: Vec<(usize, f64)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, (p[0] - 10.0).powi(2) + (p[1] - 2.0).powi(2)))
+            .filter(|&(_, d2)| d2 <= 9.0)
+            .collect();
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,252 @@
+//! Point loader for ESRI shapefiles
+
+use crate::kdbush::{KDBush, PointReader, TIndex};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+const FILE_CODE: i32 = 9994;
+const HEADER_SIZE: usize = 100;
+
+const NULL_SHAPE: i32 = 0;
+const POINT: i32 = 1;
+const MULTI_POINT: i32 = 8;
+const POINT_Z: i32 = 11;
+const MULTI_POINT_Z: i32 = 18;
+const POINT_M: i32 = 21;
+const MULTI_POINT_M: i32 = 28;
+
+/// Points read from the `.shp` file of a point or multipoint shapefile
+///
+/// The id of each point is the index of its record, so it can be used to look up the
+/// attributes in the `.dbf` file. All points of a multipoint record share the same id.
+/// Records with a null shape are skipped.
+///
+/// # Example
+///
+/// ```no_run
+/// use kdbush::{KDBush, ShapefilePoints, DEFAULT_NODE_SIZE};
+///
+/// let points = ShapefilePoints::open("places.shp").unwrap();
+/// let index = KDBush::create(points, DEFAULT_NODE_SIZE);
+/// ```
+pub struct ShapefilePoints {
+    points: Vec<(TIndex, f64, f64)>,
+}
+
+impl ShapefilePoints {
+    /// Reads the points of a `.shp` file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ShapefilePoints> {
+        ShapefilePoints::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads the points of `.shp` data
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<ShapefilePoints> {
+        let mut header = [0; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        if be_i32(&header[0..4]) != FILE_CODE {
+            return Err(invalid_data("not a shapefile"));
+        }
+        let shape_type = le_i32(&header[32..36]);
+        match shape_type {
+            POINT | MULTI_POINT | POINT_Z | MULTI_POINT_Z | POINT_M | MULTI_POINT_M => {}
+            _ => return Err(invalid_data("shapefile does not contain points")),
+        }
+        // File length is given in 16-bit words, including the header
+        let file_len = word_len(&header[24..28])?;
+
+        let mut points = Vec::new();
+        let mut offset = HEADER_SIZE;
+        let mut record = 0;
+        let mut content = Vec::new();
+        while offset < file_len {
+            let mut record_header = [0; 8];
+            reader.read_exact(&mut record_header)?;
+            let content_len = word_len(&record_header[4..8])?;
+            // Lengths are read before the data, check them before allocating
+            if content_len > file_len.saturating_sub(offset + record_header.len()) {
+                return Err(invalid_data(
+                    "shapefile record runs past the end of the file",
+                ));
+            }
+            content.resize(content_len, 0);
+            reader.read_exact(&mut content)?;
+            read_record(&content, record, &mut points)?;
+            offset += record_header.len() + content_len;
+            record += 1;
+        }
+        Ok(ShapefilePoints { points })
+    }
+}
+
+fn read_record(
+    content: &[u8],
+    record: TIndex,
+    points: &mut Vec<(TIndex, f64, f64)>,
+) -> io::Result<()> {
+    let field = |start: usize, len: usize| {
+        content
+            .get(start..start + len)
+            .ok_or_else(|| invalid_data("truncated shapefile record"))
+    };
+    match le_i32(field(0, 4)?) {
+        NULL_SHAPE => {}
+        POINT | POINT_Z | POINT_M => {
+            points.push((record, le_f64(field(4, 8)?), le_f64(field(12, 8)?)));
+        }
+        MULTI_POINT | MULTI_POINT_Z | MULTI_POINT_M => {
+            // Shape type and bounding box precede the number of points
+            let num_points = le_i32(field(36, 4)?) as usize;
+            for i in 0..num_points {
+                let start = 40 + i * 16;
+                points.push((
+                    record,
+                    le_f64(field(start, 8)?),
+                    le_f64(field(start + 8, 8)?),
+                ));
+            }
+        }
+        _ => return Err(invalid_data("unexpected shape type in record")),
+    }
+    Ok(())
+}
+
+fn be_i32(bytes: &[u8]) -> i32 {
+    i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Length in bytes of a big-endian length in 16-bit words
+fn word_len(bytes: &[u8]) -> io::Result<usize> {
+    u32::try_from(be_i32(bytes))
+        .ok()
+        .and_then(|words| usize::try_from(words).ok()?.checked_mul(2))
+        .ok_or_else(|| invalid_data("invalid length in shapefile"))
+}
+
+fn le_i32(bytes: &[u8]) -> i32 {
+    i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn le_f64(bytes: &[u8]) -> f64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    f64::from_le_bytes(buf)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl PointReader for ShapefilePoints {
+    fn size_hint(&self) -> usize {
+        self.points.len()
+    }
+    fn visit_all<F>(&self, mut visitor: F)
+    where
+        F: FnMut(usize, f64, f64),
+    {
+        for &(id, x, y) in &self.points {
+            visitor(id, x, y);
+        }
+    }
+}
+
+impl KDBush {
+    /// Creates an index from the points of a shapefile
+    ///
+    /// Item ids are the record indices, see [`ShapefilePoints`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the `.shp` file
+    /// * `node_size` - Size of the KD-tree node
+    pub fn from_shapefile<P: AsRef<Path>>(path: P, node_size: u8) -> io::Result<KDBush> {
+        Ok(KDBush::create(ShapefilePoints::open(path)?, node_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_NODE_SIZE;
+
+    fn shp(shape_type: i32, records: &[Vec<u8>]) -> Vec<u8> {
+        let len: usize = HEADER_SIZE + records.iter().map(|r| 8 + r.len()).sum::<usize>();
+        let mut data = vec![0; HEADER_SIZE];
+        data[0..4].copy_from_slice(&FILE_CODE.to_be_bytes());
+        data[24..28].copy_from_slice(&(len as i32 / 2).to_be_bytes());
+        data[28..32].copy_from_slice(&1000i32.to_le_bytes());
+        data[32..36].copy_from_slice(&shape_type.to_le_bytes());
+        for (i, record) in records.iter().enumerate() {
+            data.extend_from_slice(&(i as i32 + 1).to_be_bytes());
+            data.extend_from_slice(&(record.len() as i32 / 2).to_be_bytes());
+            data.extend_from_slice(record);
+        }
+        data
+    }
+
+    fn record(shape_type: i32, values: &[f64]) -> Vec<u8> {
+        let mut record = shape_type.to_le_bytes().to_vec();
+        for v in values {
+            record.extend_from_slice(&v.to_le_bytes());
+        }
+        record
+    }
+
+    #[test]
+    fn test_points() {
+        let data = shp(
+            POINT,
+            &[
+                record(POINT, &[54.0, 1.0]),
+                record(NULL_SHAPE, &[]),
+                record(POINT, &[65.0, 35.0]),
+            ],
+        );
+        let points = ShapefilePoints::from_reader(&data[..]).unwrap();
+        assert_eq!(points.points, vec![(0, 54.0, 1.0), (2, 65.0, 35.0)]);
+        let index = KDBush::create(points, DEFAULT_NODE_SIZE);
+        let mut result = Vec::new();
+        index.range(60.0, 30.0, 70.0, 40.0, |id| result.push(id));
+        assert_eq!(result, vec![2]);
+    }
+
+    #[test]
+    fn test_multi_points() {
+        let mut multi = record(MULTI_POINT, &[1.0, 2.0, 3.0, 4.0]);
+        multi.extend_from_slice(&2i32.to_le_bytes());
+        for v in &[1.0f64, 2.0, 3.0, 4.0] {
+            multi.extend_from_slice(&v.to_le_bytes());
+        }
+        let data = shp(MULTI_POINT, &[record(NULL_SHAPE, &[]), multi]);
+        let points = ShapefilePoints::from_reader(&data[..]).unwrap();
+        assert_eq!(points.points, vec![(1, 1.0, 2.0), (1, 3.0, 4.0)]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let data = shp(5, &[]);
+        assert!(ShapefilePoints::from_reader(&data[..]).is_err());
+        assert!(ShapefilePoints::from_reader(&[0u8; 10][..]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let valid = shp(POINT, &[record(POINT, &[54.0, 1.0])]);
+
+        // Negative file and record lengths
+        let mut data = valid.clone();
+        data[24..28].copy_from_slice(&(-1i32).to_be_bytes());
+        assert!(ShapefilePoints::from_reader(&data[..]).is_err());
+        let mut data = valid.clone();
+        data[104..108].copy_from_slice(&(-1i32).to_be_bytes());
+        assert!(ShapefilePoints::from_reader(&data[..]).is_err());
+
+        // Record longer than the file
+        let mut data = valid;
+        data[104..108].copy_from_slice(&i32::MAX.to_be_bytes());
+        let err = ShapefilePoints::from_reader(&data[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, KdNum, TIndex, TNumber};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Spatial index over coordinates owned by the caller
+///
+/// Only the tree order of the points is allocated. Coordinates are read from the borrowed
+/// slice, e.g. a memory-mapped file, at the cost of an indirection per visited point.
+/// Results are the positions of the points in the slice.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{KDBushSlice, DEFAULT_NODE_SIZE};
+///
+/// let coords = [54.0, 1.0, 97.0, 21.0, 65.0, 35.0];
+/// let index = KDBushSlice::from_coords(&coords, DEFAULT_NODE_SIZE);
+/// let mut result = Vec::new();
+/// index.within(60.0, 30.0, 10.0, |id| result.push(id));
+/// assert_eq!(result, vec![2]);
+/// ```
+pub struct KDBushSlice<'a, T = TNumber> {
+    /// Interleaved x and y coordinates
+    coords: &'a [T],
+    /// Point positions in the order of the tree
+    ids: Vec<TIndex>,
+    node_size: u8,
+}
+
+impl<'a, T: KdNum> KDBushSlice<'a, T> {
+    /// Creates an index over interleaved coordinates `[x0, y0, x1, y1, ...]`
+    ///
+    /// # Arguments
+    ///
+    /// * `coords` - Interleaved coordinates
+    /// * `node_size` - Size of the KD-tree node
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of coordinates is odd.
+    pub fn from_coords(coords: &'a [T], node_size: u8) -> KDBushSlice<'a, T> {
+        assert!(coords.len() % 2 == 0, "odd number of coordinates");
+        let mut index = KDBushSlice {
+            coords,
+            ids: (0..coords.len() / 2).collect(),
+            node_size: node_size.max(1),
+        };
+        let mut ids = core::mem::take(&mut index.ids);
+        index.sort_kd(&mut ids, 0);
+        index.ids = ids;
+        index
+    }
+
+    /// Creates an index over points `[x, y]`
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points
+    /// * `node_size` - Size of the KD-tree node
+    pub fn from_points(points: &'a [[T; 2]], node_size: u8) -> KDBushSlice<'a, T> {
+        KDBushSlice::from_coords(points.as_flattened(), node_size)
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether the index has no points
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn coord(&self, id: TIndex, axis: usize) -> T {
+        self.coords[2 * id + axis]
+    }
+
+    /// Sorts point positions into a KD-tree, starting with a split along `axis`
+    fn sort_kd(&self, ids: &mut [TIndex], axis: usize) {
+        let mut stack = vec![(ids, axis)];
+        while let Some((ids, axis)) = stack.pop() {
+            let n = ids.len();
+            if n == 0 || n - 1 <= self.node_size as usize {
+                continue;
+            }
+            let m = (n - 1) >> 1;
+            ids.select_nth_unstable_by(m, |&a, &b| {
+                self.coord(a, axis).total_cmp(&self.coord(b, axis))
+            });
+            let (left, right) = ids.split_at_mut(m);
+            stack.push((&mut right[1..], 1 - axis));
+            stack.push((left, 1 - axis));
+        }
+    }
+
+    /// Finds all points within the given bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader
+    pub fn range<F>(&self, minx: T, miny: T, maxx: T, maxy: T, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.is_empty() || !is_valid_bbox(minx, miny, maxx, maxy) {
+            return;
+        }
+        let bbox = [minx, miny, maxx, maxy];
+        let inside = |id| {
+            let (x, y) = (self.coord(id, 0), self.coord(id, 1));
+            x >= bbox[0] && x <= bbox[2] && y >= bbox[1] && y <= bbox[3]
+        };
+        let mut stack = vec![(0, self.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                for &id in &self.ids[left..right + 1] {
+                    if inside(id) {
+                        visitor(id);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let id = self.ids[m];
+            if inside(id) {
+                visitor(id);
+            }
+            let split = self.coord(id, axis);
+            // Right child first, so the left child is visited next
+            if bbox[2 + axis] >= split {
+                stack.push((m + 1, right, 1 - axis));
+            }
+            if bbox[axis] <= split {
+                stack.push((left, m - 1, 1 - axis));
+            }
+        }
+    }
+
+    /// Finds all points within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader
+    pub fn within<F>(&self, qx: T, qy: T, r: T, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if self.is_empty() || !is_valid_radius(r) {
+            return;
+        }
+        let r2 = T::sq_diff(r, T::ZERO);
+        let q = [qx, qy];
+        let inside =
+            |id| T::sq_diff(self.coord(id, 0), qx) + T::sq_diff(self.coord(id, 1), qy) <= r2;
+        let mut stack = vec![(0, self.len() - 1, 0)];
+        while let Some((left, right, axis)) = stack.pop() {
+            if right - left <= self.node_size as usize {
+                for &id in &self.ids[left..right + 1] {
+                    if inside(id) {
+                        visitor(id);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let id = self.ids[m];
+            if inside(id) {
+                visitor(id);
+            }
+            let split = self.coord(id, axis);
+            let near = T::sq_diff(q[axis], split) <= r2;
+            // Right child first, so the left child is visited next
+            if q[axis] >= split || near {
+                stack.push((m + 1, right, 1 - axis));
+            }
+            if q[axis] <= split || near {
+                stack.push((left, m - 1, 1 - axis));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+    use crate::kdbush::KDBush;
+
+    #[test]
+    fn test_slice_queries() {
+        let points: Vec<[f64; 2]> = (0..1000)
+            .map(|i| [(i * 37 % 101) as f64, (i * 53 % 97) as f64])
+            .collect();
+        let slice = KDBushSlice::from_points(&points, 4);
+        let owned = KDBush::create(points.iter().map(|p| (p[0], p[1])).collect::<Vec<_>>(), 4);
+        assert_eq!(slice.len(), 1000);
+
+        let mut expected = Vec::new();
+        owned.range(10.0, 20.0, 60.0, 50.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        slice.range(10.0, 20.0, 60.0, 50.0, |id| result.push(id));
+        expected.sort();
+        result.sort();
+        assert_eq!(expected, result);
+
+        let mut expected = Vec::new();
+        owned.within(40.0, 50.0, 15.0, |id| expected.push(id));
+        let mut result = Vec::new();
+        slice.within(40.0, 50.0, 15.0, |id| result.push(id));
+        expected.sort();
+        result.sort();
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_slice_degenerate() {
+        let empty = KDBushSlice::<u32>::from_coords(&[], 4);
+        empty.within(0, 0, 1, |_| panic!());
+        let coords = [1i32, 1];
+        let index = KDBushSlice::from_coords(&coords, 4);
+        index.within(0, 0, -1, |_| panic!());
+        index.range(2, 0, 0, 2, |_| panic!());
+    }
+
+    #[test]
+    fn test_slice_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let coords: Vec<[f64; 2]> = points.iter().map(|&(x, y)| [x, y]).collect();
+            let slice = KDBushSlice::from_points(&coords, node_size);
+            let mut ids = Vec::new();
+            slice.within(0.0, 0.0, 5.0, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+            let mut ids = Vec::new();
+            slice.range(-1.0, -1.0, 5.0, 5.0, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use crate::kdbush::{KdId, KdNum, TIndex};
+use crate::math;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp;
+
+/// Minimal number of items of a subtree sorted on its own thread
+#[cfg(feature = "parallel")]
+const PARALLEL_CUTOFF: usize = 1 << 16;
+
+/// Options of the KD-tree construction
+#[derive(Clone, Copy)]
+pub(crate) struct SortOptions {
+    pub node_size: usize,
+    /// Sort the subtrees of large nodes on separate threads
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    pub parallel: bool,
+    /// Break ties between equal coordinates by id and sort leaves by id, so the order
+    /// doesn't depend on the selection algorithm or on floating point rounding
+    pub stable: bool,
+}
+
+/// Ids and coordinates of a range of items, sorted together
+pub(crate) struct Items<'a, T, I = TIndex> {
+    pub ids: &'a mut [I],
+    pub points: &'a mut [[T; 2]],
+}
+
+impl<'a, T: KdNum, I: KdId> Items<'a, T, I> {
+    /// Sorts the items into a KD-tree, starting with a split along `axis`
+    ///
+    /// Parallel sorting results in the same order as sequential sorting.
+    pub fn sort_kd(self, options: SortOptions, axis: usize) {
+        #[cfg(feature = "parallel")]
+        if options.parallel && self.ids.len() > PARALLEL_CUTOFF {
+            // Splitting this many levels gives each thread about two subtrees
+            let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+            let depth = threads.ilog2() as usize + 1;
+            std::thread::scope(|scope| self.sort_kd_parallel(options, axis, depth, scope));
+            return;
+        }
+        let mut stack = vec![(self, axis)];
+        while let Some((items, axis)) = stack.pop() {
+            if let Some((left, right)) = items.split(options, axis) {
+                stack.push((right, 1 - axis));
+                stack.push((left, 1 - axis));
+            }
+        }
+    }
+
+    /// Sorts the items into a KD-tree, spawning a thread for the left child of each large node
+    /// in the top `depth` levels, so at most `2^depth` threads run at once
+    #[cfg(feature = "parallel")]
+    fn sort_kd_parallel<'scope>(
+        self,
+        options: SortOptions,
+        axis: usize,
+        depth: usize,
+        scope: &'scope std::thread::Scope<'scope, '_>,
+    ) where
+        'a: 'scope,
+    {
+        let mut next = Some((self, axis, depth));
+        while let Some((items, axis, depth)) = next.take() {
+            if depth == 0 || items.ids.len() <= PARALLEL_CUTOFF {
+                let sequential = SortOptions {
+                    parallel: false,
+                    ..options
+                };
+                items.sort_kd(sequential, axis);
+            } else if let Some((left, right)) = items.split(options, axis) {
+                scope.spawn(move || left.sort_kd_parallel(options, 1 - axis, depth - 1, scope));
+                next = Some((right, 1 - axis, depth - 1));
+            }
+        }
+    }
+
+    /// Moves the median along `axis` into the middle and splits off the items on either side
+    ///
+    /// Returns `None` for leaf nodes, which are only sorted (by id) with stable ordering.
+    fn split(
+        mut self,
+        options: SortOptions,
+        axis: usize,
+    ) -> Option<(Items<'a, T, I>, Items<'a, T, I>)> {
+        let n = self.ids.len();
+        if n == 0 || n - 1 <= options.node_size {
+            if options.stable {
+                self.sort_by_id();
+            }
+            return None;
+        }
+        let m = (n - 1) >> 1;
+        if options.stable {
+            self.select_by_key(m, 0, n - 1, axis, true);
+        } else {
+            self.select(m, 0, n - 1, axis);
+        }
+
+        let (left_ids, right_ids) = self.ids.split_at_mut(m);
+        let (left_points, right_points) = self.points.split_at_mut(m);
+        let left = Items {
+            ids: left_ids,
+            points: left_points,
+        };
+        let right = Items {
+            ids: &mut right_ids[1..],
+            points: &mut right_points[1..],
+        };
+        Some((left, right))
+    }
+
+    /// Floyd-Rivest selection, rearranging items so that the `k`-th item is in its sorted position
+    ///
+    /// Each partitioning step is expected to shrink the range substantially. If this doesn't
+    /// happen within a logarithmic number of steps (e.g. with adversarially ordered input),
+    /// the selection falls back to an algorithm with a linear worst case.
+    pub fn select(&mut self, k: TIndex, mut left: TIndex, mut right: TIndex, axis: usize) {
+        let n = right - left + 1;
+        let mut budget = 2 * (usize::BITS - n.leading_zeros()) + 4;
+        while right > left {
+            if budget == 0 {
+                self.select_fallback(k, left, right, axis);
+                return;
+            }
+            budget -= 1;
+            if right - left > 600 {
+                let n = (right - left + 1) as f64;
+                let m = (k - left + 1) as f64;
+                let z = math::ln(n);
+                let s = 0.5 * math::exp(2.0 * z / 3.0);
+                let r = k as f64 - m * s / n
+                    + 0.5
+                        * math::sqrt(z * s * (1.0 - s / n))
+                        * (if 2.0 * m < n { -1.0 } else { 1.0 });
+                self.select(
+                    k,
+                    cmp::max(left, r as usize),
+                    cmp::min(right, (r + s) as usize),
+                    axis,
+                );
+            }
+
+            let t = self.points[k][axis];
+            let mut i = left;
+            let mut j = right;
+
+            self.swap_item(left, k);
+            if self.points[right][axis] > t {
+                self.swap_item(left, right);
+            }
+
+            while i < j {
+                self.swap_item(i, j);
+                i += 1;
+                j -= 1;
+                while self.points[i][axis] < t {
+                    i += 1;
+                }
+                while self.points[j][axis] > t {
+                    j -= 1;
+                }
+            }
+
+            if self.points[left][axis] == t {
+                self.swap_item(left, j);
+            } else {
+                j += 1;
+                self.swap_item(j, right);
+            }
+
+            if j <= k {
+                left = j + 1;
+            }
+            if k <= j {
+                right = j - 1;
+            }
+        }
+    }
+
+    /// Selection with linear worst-case time, using the standard library introselect
+    pub fn select_fallback(&mut self, k: TIndex, left: TIndex, right: TIndex, axis: usize) {
+        self.select_by_key(k, left, right, axis, false);
+    }
+
+    /// Selection using the standard library introselect, optionally breaking ties by id
+    fn select_by_key(&mut self, k: TIndex, left: TIndex, right: TIndex, axis: usize, by_id: bool) {
+        let mut items: Vec<(I, [T; 2])> = self.ids[left..right + 1]
+            .iter()
+            .cloned()
+            .zip(self.points[left..right + 1].iter().cloned())
+            .collect();
+        items.select_nth_unstable_by(k - left, |a, b| {
+            let ord = a.1[axis].total_cmp(&b.1[axis]);
+            if by_id {
+                ord.then(a.0.to_usize().cmp(&b.0.to_usize()))
+            } else {
+                ord
+            }
+        });
+        self.write(left, items);
+    }
+
+    /// Sorts the items by id
+    fn sort_by_id(&mut self) {
+        let mut items: Vec<(I, [T; 2])> = self
+            .ids
+            .iter()
+            .cloned()
+            .zip(self.points.iter().cloned())
+            .collect();
+        items.sort_unstable_by_key(|item| item.0.to_usize());
+        self.write(0, items);
+    }
+
+    /// Replaces the items from position `start` on
+    fn write(&mut self, start: TIndex, items: Vec<(I, [T; 2])>) {
+        for (i, (id, p)) in items.into_iter().enumerate() {
+            self.ids[start + i] = id;
+            self.points[start + i] = p;
+        }
+    }
+
+    fn swap_item(&mut self, i: TIndex, j: TIndex) {
+        self.ids.swap(i, j);
+        self.points.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kdbush::KDBush;
+
+    #[test]
+    fn test_parallel_sort() {
+        let points: Vec<(f64, f64)> = (0..300_000u64)
+            .map(|i| ((i * 7919 % 100_003) as f64, (i * 104_729 % 99_991) as f64))
+            .collect();
+        let mut sequential = KDBush::new(points.len(), 16);
+        let mut parallel = KDBush::new(points.len(), 16);
+        for (i, p) in points.iter().enumerate() {
+            sequential.add_point(i, p.0, p.1);
+            parallel.add_point(i, p.0, p.1);
+        }
+        sequential.build_index_with(false, false);
+        parallel.build_index_with(true, false);
+        assert_eq!(sequential.ids, parallel.ids);
+        assert_eq!(sequential.points, parallel.points);
+
+        // Capped spawn depths sort the lower levels sequentially, with the same result
+        #[cfg(feature = "parallel")]
+        for depth in 0..3 {
+            let mut ids: Vec<usize> = (0..points.len()).collect();
+            let mut coords: Vec<[f64; 2]> = points.iter().map(|p| [p.0, p.1]).collect();
+            let options = super::SortOptions {
+                node_size: 16,
+                parallel: true,
+                stable: false,
+            };
+            let items = super::Items {
+                ids: &mut ids,
+                points: &mut coords,
+            };
+            std::thread::scope(|scope| items.sort_kd_parallel(options, 0, depth, scope));
+            assert_eq!(ids, sequential.ids);
+        }
+    }
+
+    #[test]
+    fn test_stable_sort() {
+        // Heavily duplicated coordinates, added in two different orders
+        let points: Vec<(usize, f64, f64)> = (0..5000)
+            .map(|i| (i, (i % 7) as f64, (i % 5) as f64))
+            .collect();
+        let build = |points: &[(usize, f64, f64)], stable| {
+            let mut index = KDBush::new(points.len(), 8);
+            for &(id, x, y) in points {
+                index.add_point(id, x, y);
+            }
+            index.build_index_with(false, stable);
+            index
+        };
+        let reversed: Vec<_> = points.iter().rev().cloned().collect();
+        let a = build(&points, true);
+        let b = build(&reversed, true);
+        assert_eq!(a.ids, b.ids);
+        assert_eq!(a.points, b.points);
+
+        let unstable = build(&points, false);
+        let query = |index: &KDBush| {
+            let mut result = Vec::new();
+            index.range(1.0, 1.0, 3.0, 2.0, |id| result.push(id));
+            result.sort();
+            result
+        };
+        assert_eq!(query(&a), query(&unstable));
+    }
+}
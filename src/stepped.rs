@@ -0,0 +1,220 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, Bbox, KDBush, TIndex, TNumber};
+use alloc::vec::Vec;
+
+/// Query area of a stepped or paged query
+pub(crate) enum Shape {
+    Range(Bbox),
+    Within {
+        qx: TNumber,
+        qy: TNumber,
+        r2: TNumber,
+    },
+}
+
+impl Shape {
+    pub(crate) fn contains(&self, x: TNumber, y: TNumber) -> bool {
+        match *self {
+            Shape::Range(b) => x >= b[0] && x <= b[2] && y >= b[1] && y <= b[3],
+            Shape::Within { qx, qy, r2 } => KDBush::sq_dist(x, y, qx, qy) <= r2,
+        }
+    }
+
+    /// Center of the query area
+    #[cfg(feature = "parallel")]
+    pub(crate) fn center(&self) -> (TNumber, TNumber) {
+        match *self {
+            Shape::Range(b) => ((b[0] + b[2]) / 2.0, (b[1] + b[3]) / 2.0),
+            Shape::Within { qx, qy, .. } => (qx, qy),
+        }
+    }
+
+    /// Whether the query reaches below and above a split value
+    pub(crate) fn reaches(&self, split: TNumber, axis: usize) -> (bool, bool) {
+        match *self {
+            Shape::Range(b) => (b[axis] <= split, b[2 + axis] >= split),
+            Shape::Within { qx, qy, r2 } => {
+                let d = if axis == 0 { qx - split } else { qy - split };
+                (d <= 0.0 || d * d <= r2, d >= 0.0 || d * d <= r2)
+            }
+        }
+    }
+}
+
+/// Query which is executed in bounded steps and can be resumed
+///
+/// Each call of [`SteppedQuery::step`] visits at most a given number of tree nodes, so
+/// long-running queries can be interleaved with other work, e.g. in game frames or
+/// cooperative schedulers. The caller decides when work happens.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{KDBush, SteppedQuery, DEFAULT_NODE_SIZE};
+///
+/// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+/// let mut query = SteppedQuery::within(&index, 60.0, 30.0, 10.0);
+/// let mut result = Vec::new();
+/// while !query.step(16, |id| result.push(id)) {
+///     // do other work
+/// }
+/// assert_eq!(result, vec![2]);
+/// ```
+pub struct SteppedQuery<'a> {
+    index: &'a KDBush,
+    shape: Shape,
+    /// Nodes still to visit, as position ranges with their split axis
+    stack: Vec<(TIndex, TIndex, usize)>,
+}
+
+impl<'a> SteppedQuery<'a> {
+    /// Creates a query for all items within the given bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Queried index
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    pub fn range(
+        index: &'a KDBush,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+    ) -> SteppedQuery<'a> {
+        let valid = is_valid_bbox(minx, miny, maxx, maxy);
+        SteppedQuery::new(index, Shape::Range([minx, miny, maxx, maxy]), valid)
+    }
+
+    /// Creates a query for all items within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Queried index
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    pub fn within(index: &'a KDBush, qx: TNumber, qy: TNumber, r: TNumber) -> SteppedQuery<'a> {
+        let valid = is_valid_radius(r);
+        SteppedQuery::new(index, Shape::Within { qx, qy, r2: r * r }, valid)
+    }
+
+    fn new(index: &'a KDBush, shape: Shape, valid: bool) -> SteppedQuery<'a> {
+        let mut stack = Vec::new();
+        if valid && !index.ids.is_empty() {
+            stack.push((0, index.ids.len() - 1, 0));
+        }
+        SteppedQuery {
+            index,
+            shape,
+            stack,
+        }
+    }
+
+    /// Whether all results have been reported
+    pub fn is_done(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Continues the query, visiting at most `max_nodes` tree nodes
+    ///
+    /// Returns `true` when the query is complete. A step with `max_nodes` of zero does no work.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_nodes` - Maximal number of nodes visited in this step
+    /// * `visitor` - Result reader
+    pub fn step<F>(&mut self, max_nodes: usize, mut visitor: F) -> bool
+    where
+        F: FnMut(TIndex),
+    {
+        let index = self.index;
+        for _ in 0..max_nodes {
+            let (left, right, axis) = match self.stack.pop() {
+                Some(node) => node,
+                None => break,
+            };
+            if right - left <= index.node_size as usize {
+                for i in left..right + 1 {
+                    if self.shape.contains(index.points[i][0], index.points[i][1]) {
+                        visitor(index.ids[i]);
+                    }
+                }
+                continue;
+            }
+
+            let m = (left + right) >> 1;
+            let p = index.points[m];
+            if self.shape.contains(p[0], p[1]) {
+                visitor(index.ids[m]);
+            }
+            let (below, above) = self.shape.reaches(p[axis], axis);
+            // Right child first, so the left child is visited next
+            if above {
+                self.stack.push((m + 1, right, (axis + 1) % 2));
+            }
+            if below {
+                self.stack.push((left, m - 1, (axis + 1) % 2));
+            }
+        }
+        self.is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_stepped_query() {
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points, 4);
+
+        let mut expected = Vec::new();
+        index.range(10.0, 20.0, 60.0, 50.0, |id| expected.push(id));
+        let mut query = SteppedQuery::range(&index, 10.0, 20.0, 60.0, 50.0);
+        let mut result = Vec::new();
+        let mut steps = 0;
+        while !query.step(3, |id| result.push(id)) {
+            steps += 1;
+        }
+        assert!(steps > 10);
+        expected.sort();
+        result.sort();
+        assert_eq!(expected, result);
+
+        let mut expected = Vec::new();
+        index.within(40.0, 50.0, 15.0, |id| expected.push(id));
+        let mut query = SteppedQuery::within(&index, 40.0, 50.0, 15.0);
+        let mut result = Vec::new();
+        assert!(!query.step(0, |id| result.push(id)));
+        assert!(result.is_empty());
+        assert!(query.step(usize::MAX, |id| result.push(id)));
+        assert!(query.is_done());
+        expected.sort();
+        result.sort();
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_stepped_query_degenerate() {
+        let index = KDBush::create(vec![(1.0, 1.0)], 4);
+        assert!(SteppedQuery::within(&index, 0.0, 0.0, -1.0).is_done());
+        assert!(SteppedQuery::range(&index, 2.0, 0.0, 0.0, 2.0).is_done());
+        let empty = KDBush::create(vec![], 4);
+        assert!(SteppedQuery::within(&empty, 0.0, 0.0, 1.0).is_done());
+    }
+
+    #[test]
+    fn test_stepped_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut ids = Vec::new();
+            let mut query = SteppedQuery::within(&index, 0.0, 0.0, 5.0);
+            while !query.step(1, &mut |id| ids.push(id)) {}
+            ids.sort();
+            assert_eq!(ids, all);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::kdbush::{KDBush, TIndex, TNumber};
+use std::io::{self, Write};
+
+/// Size of the chunks written to the output
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Record format for streamed query results
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One id per line
+    Ids,
+    /// One JSON object per line with `id`, `x` and `y`
+    Json,
+}
+
+/// Buffers formatted records and writes them in chunks
+struct ChunkWriter<W: Write> {
+    writer: W,
+    format: OutputFormat,
+    buf: Vec<u8>,
+    count: usize,
+}
+
+impl<W: Write> ChunkWriter<W> {
+    fn new(writer: W, format: OutputFormat) -> Self {
+        ChunkWriter {
+            writer,
+            format,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            count: 0,
+        }
+    }
+
+    fn push(&mut self, id: TIndex, x: TNumber, y: TNumber) -> io::Result<()> {
+        // Writing into a Vec can't fail
+        let _ = match self.format {
+            OutputFormat::Ids => writeln!(self.buf, "{}", id),
+            OutputFormat::Json => writeln!(
+                self.buf,
+                "{{\"id\":{},\"x\":{},\"y\":{}}}",
+                id,
+                JsonNumber(x),
+                JsonNumber(y)
+            ),
+        };
+        self.count += 1;
+        if self.buf.len() >= CHUNK_SIZE {
+            self.write_chunk()?;
+        }
+        Ok(())
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<usize> {
+        self.write_chunk()?;
+        self.writer.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// Number formatted as JSON, with `null` for NaN and infinite values
+struct JsonNumber(TNumber);
+
+impl std::fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.0.is_finite() {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "null")
+        }
+    }
+}
+
+impl KDBush {
+    /// Writes all items within the given bounding box, without collecting them in memory
+    ///
+    /// Returns the number of written records. The query stops at the first write error,
+    /// which is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `format` - Record format
+    /// * `writer` - Output
+    pub fn write_range<W: Write>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        format: OutputFormat,
+        writer: W,
+    ) -> io::Result<usize> {
+        let mut out = ChunkWriter::new(writer, format);
+        self.try_range_pos([minx, miny, maxx, maxy], |i| {
+            out.push(self.ids[i], self.points[i][0], self.points[i][1])
+        })?;
+        out.finish()
+    }
+
+    /// Writes all items within a given radius from the query point, without collecting them in memory
+    ///
+    /// Returns the number of written records. The query stops at the first write error,
+    /// which is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `format` - Record format
+    /// * `writer` - Output
+    pub fn write_within<W: Write>(
+        &self,
+        qx: TNumber,
+        qy: TNumber,
+        r: TNumber,
+        format: OutputFormat,
+        writer: W,
+    ) -> io::Result<usize> {
+        let mut out = ChunkWriter::new(writer, format);
+        self.try_within_pos(qx, qy, r, |i| {
+            out.push(self.ids[i], self.points[i][0], self.points[i][1])
+        })?;
+        out.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_ids() {
+        let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], 10);
+        let mut out = Vec::new();
+        let count = index
+            .write_range(50.0, 0.0, 70.0, 40.0, OutputFormat::Ids, &mut out)
+            .unwrap();
+        assert_eq!(count, 2);
+        let mut lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+        lines.sort();
+        assert_eq!(lines, vec!["0", "2"]);
+    }
+
+    #[test]
+    fn test_write_json() {
+        let index = KDBush::create(vec![(54.0, 1.5), (97.0, 21.0)], 10);
+        let mut out = Vec::new();
+        let count = index
+            .write_within(50.0, 0.0, 10.0, OutputFormat::Json, &mut out)
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(out, b"{\"id\":0,\"x\":54,\"y\":1.5}\n");
+    }
+
+    #[test]
+    fn test_write_chunks() {
+        let points: Vec<(f64, f64)> = (0..20000).map(|i| (i as f64, 0.0)).collect();
+        let index = KDBush::create(points, 64);
+        let mut out = Vec::new();
+        let count = index
+            .write_range(0.0, 0.0, 20000.0, 0.0, OutputFormat::Json, &mut out)
+            .unwrap();
+        assert_eq!(count, 20000);
+        assert_eq!(std::str::from_utf8(&out).unwrap().lines().count(), 20000);
+    }
+
+    /// Output failing after a number of bytes, counting the write calls
+    struct FailingWriter {
+        remaining: usize,
+        writes: usize,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            if self.remaining == 0 {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+            }
+            let n = buf.len().min(self.remaining);
+            self.remaining -= n;
+            Ok(n)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_error_stops_query() {
+        let points: Vec<(f64, f64)> = (0..200_000).map(|i| (i as f64, 0.0)).collect();
+        let index = KDBush::create(points, 64);
+        let mut out = FailingWriter {
+            remaining: 10,
+            writes: 0,
+        };
+        let err = index
+            .write_within(0.0, 0.0, f64::INFINITY, OutputFormat::Json, &mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(out.writes, 2);
+
+        let mut out = FailingWriter {
+            remaining: 0,
+            writes: 0,
+        };
+        let err = index
+            .write_range(0.0, -1.0, 1e6, 1.0, OutputFormat::Ids, &mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(out.writes, 1);
+    }
+}
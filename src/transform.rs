@@ -0,0 +1,102 @@
+use crate::kdbush::{KDBush, Point, TIndex};
+
+impl KDBush {
+    /// Creates a new index containing only the items matching a predicate
+    ///
+    /// Items keep their ids. Node bounding boxes and the coordinate lookup are built
+    /// if this index has them, the CRS tag is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `pred` - Returns whether to keep an item, called with its id and coordinates
+    pub fn retain<P>(&self, pred: P) -> KDBush
+    where
+        P: Fn(TIndex, Point) -> bool,
+    {
+        let mut kdbush = KDBush::new(self.ids.len(), self.node_size);
+        for (&id, &p) in self.ids.iter().zip(&self.points) {
+            if pred(id, p) {
+                kdbush.add_point(id, p[0], p[1]);
+            }
+        }
+        self.build_like(&mut kdbush);
+        kdbush
+    }
+
+    /// Creates a new index with transformed coordinates
+    ///
+    /// Items keep their ids. Node bounding boxes and the coordinate lookup are built
+    /// if this index has them, the CRS tag is kept. Use [`KDBush::reproject`] for
+    /// transformations into another CRS.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Transformation of the item coordinates
+    pub fn map_points<M>(&self, f: M) -> KDBush
+    where
+        M: Fn(Point) -> Point,
+    {
+        let mut kdbush = KDBush::new(self.ids.len(), self.node_size);
+        for (&id, &p) in self.ids.iter().zip(&self.points) {
+            let [x, y] = f(p);
+            kdbush.add_point(id, x, y);
+        }
+        self.build_like(&mut kdbush);
+        kdbush
+    }
+
+    /// Builds an index with the same options as this index
+    pub(crate) fn build_like(&self, kdbush: &mut KDBush) {
+        kdbush.build_index();
+        kdbush.crs = self.crs;
+        if self.bboxes.is_some() {
+            kdbush.build_bboxes();
+        }
+        if self.lookup.is_some() {
+            kdbush.build_lookup();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_retain() {
+        let points = vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0), (33.0, 54.0)];
+        let index = KDBush::create_with_bboxes(points, 1);
+        let subset = index.retain(|id, p| id != 2 && p[0] > 50.0);
+        assert!(subset.bboxes.is_some());
+        let mut result = Vec::new();
+        subset.range(0.0, 0.0, 100.0, 100.0, |id| result.push(id));
+        result.sort();
+        assert_eq!(result, vec![0, 1]);
+
+        let empty = index.retain(|_, _| false);
+        assert!(empty.ids.is_empty());
+    }
+
+    #[test]
+    fn test_map_points() {
+        let points = vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0), (33.0, 54.0)];
+        let index = KDBush::create_with_lookup(points, 1);
+        let scaled = index.map_points(|p| [p[0] * 10.0, p[1] * 10.0]);
+        assert_eq!(scaled.ids_at(650.0, 350.0), vec![2]);
+        let mut result = Vec::new();
+        scaled.range(500.0, 0.0, 1000.0, 300.0, |id| result.push(id));
+        result.sort();
+        assert_eq!(result, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_transform_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let index = KDBush::create(points, node_size);
+            assert_eq!(index.retain(|_, _| true).len(), n);
+            assert_eq!(index.map_points(|p| p).len(), n);
+        }
+    }
+}
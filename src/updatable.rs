@@ -0,0 +1,233 @@
+use crate::kdbush::{is_valid_bbox, is_valid_radius, KDBush, Point, PointReader, TIndex, TNumber};
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+/// Spatial index which buffers changes until it is rebuilt
+///
+/// Inserted points are kept in a buffer and removed ids are masked, so updates are cheap.
+/// Queries merge the results of the tree with a linear scan of the buffer. Calling
+/// [`UpdatableKDBush::rebuild`] folds the changes into a new tree, e.g. when
+/// [`UpdatableKDBush::pending`] grows large.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{UpdatableKDBush, DEFAULT_NODE_SIZE};
+///
+/// let mut index = UpdatableKDBush::create(vec![(54.0, 1.0), (97.0, 21.0)], DEFAULT_NODE_SIZE);
+/// index.update(0, 65.0, 35.0);
+/// let mut result = Vec::new();
+/// index.within(60.0, 30.0, 10.0, |id| result.push(id));
+/// assert_eq!(result, vec![0]);
+/// index.rebuild();
+/// assert_eq!(index.pending(), 0);
+/// ```
+pub struct UpdatableKDBush {
+    tree: KDBush,
+    /// Ids masked in the tree
+    removed: BTreeSet<TIndex>,
+    /// Points inserted since the last rebuild
+    added: Vec<(TIndex, Point)>,
+}
+
+impl UpdatableKDBush {
+    /// Creates an index from the given points
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points reader
+    /// * `node_size` - Size of the KD-tree node
+    pub fn create<R: PointReader>(points: R, node_size: u8) -> UpdatableKDBush {
+        UpdatableKDBush::from_index(KDBush::create(points, node_size))
+    }
+
+    /// Creates an updatable index from a built index
+    pub fn from_index(tree: KDBush) -> UpdatableKDBush {
+        UpdatableKDBush {
+            tree,
+            removed: BTreeSet::new(),
+            added: Vec::new(),
+        }
+    }
+
+    /// Index of the points as of the last rebuild
+    pub fn index(&self) -> &KDBush {
+        &self.tree
+    }
+
+    /// Number of buffered insertions and removals
+    pub fn pending(&self) -> usize {
+        self.added.len() + self.removed.len()
+    }
+
+    /// Adds a point
+    pub fn insert(&mut self, id: TIndex, x: TNumber, y: TNumber) {
+        self.added.push((id, [x, y]));
+    }
+
+    /// Removes all points with the given id
+    pub fn remove(&mut self, id: TIndex) {
+        self.added.retain(|&(added, _)| added != id);
+        self.removed.insert(id);
+    }
+
+    /// Moves the points with the given id to a new location
+    pub fn update(&mut self, id: TIndex, x: TNumber, y: TNumber) {
+        self.remove(id);
+        self.insert(id, x, y);
+    }
+
+    /// Builds a new tree containing all changes and clears the buffers
+    ///
+    /// Node bounding boxes and the coordinate lookup are built if the tree has them.
+    pub fn rebuild(&mut self) {
+        if self.added.is_empty() && self.removed.is_empty() {
+            return;
+        }
+        let tree = &self.tree;
+        let mut kdbush = KDBush::new(tree.ids.len() + self.added.len(), tree.node_size);
+        for (&id, &p) in tree.ids.iter().zip(&tree.points) {
+            if !self.removed.contains(&id) {
+                kdbush.add_point(id, p[0], p[1]);
+            }
+        }
+        for &(id, p) in &self.added {
+            kdbush.add_point(id, p[0], p[1]);
+        }
+        tree.build_like(&mut kdbush);
+        self.tree = kdbush;
+        self.removed.clear();
+        self.added.clear();
+    }
+
+    /// Finds all points within the given bounding box
+    ///
+    /// # Arguments
+    ///
+    /// * `minx`, `miny`, `maxx`, `maxy` - Bounding box
+    /// * `visitor` - Result reader
+    pub fn range<F>(
+        &self,
+        minx: TNumber,
+        miny: TNumber,
+        maxx: TNumber,
+        maxy: TNumber,
+        mut visitor: F,
+    ) where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_bbox(minx, miny, maxx, maxy) {
+            return;
+        }
+        if !self.tree.ids.is_empty() {
+            self.tree.range(minx, miny, maxx, maxy, |id| {
+                if !self.removed.contains(&id) {
+                    visitor(id)
+                }
+            });
+        }
+        for &(id, [x, y]) in &self.added {
+            if x >= minx && x <= maxx && y >= miny && y <= maxy {
+                visitor(id);
+            }
+        }
+    }
+
+    /// Finds all points within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader
+    pub fn within<F>(&self, qx: TNumber, qy: TNumber, r: TNumber, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_radius(r) {
+            return;
+        }
+        if !self.tree.ids.is_empty() {
+            self.tree.within(qx, qy, r, |id| {
+                if !self.removed.contains(&id) {
+                    visitor(id)
+                }
+            });
+        }
+        let r2 = r * r;
+        for &(id, [x, y]) in &self.added {
+            if KDBush::sq_dist(x, y, qx, qy) <= r2 {
+                visitor(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut ids: Vec<TIndex>) -> Vec<TIndex> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_updatable() {
+        let points: Vec<(f64, f64)> = (0..200)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let mut index = UpdatableKDBush::create(points.clone(), 8);
+        let mut moved = points.clone();
+        for id in (0..200).step_by(3) {
+            moved[id] = (moved[id].1, moved[id].0);
+            index.update(id, moved[id].0, moved[id].1);
+        }
+        index.remove(7);
+        index.insert(200, 40.0, 50.0);
+        index.insert(201, 0.0, 0.0);
+        index.remove(201);
+        // 67 moved points, one removed and two inserted
+        assert_eq!(index.pending(), 68 + 69);
+
+        let query = |index: &UpdatableKDBush| {
+            let mut within = Vec::new();
+            index.within(40.0, 50.0, 20.0, |id| within.push(id));
+            let mut range = Vec::new();
+            index.range(10.0, 20.0, 60.0, 50.0, |id| range.push(id));
+            (sorted(within), sorted(range))
+        };
+        let mut expected_within = Vec::new();
+        let mut expected_range = Vec::new();
+        for (id, &(x, y)) in moved.iter().enumerate().chain(Some((200, &(40.0, 50.0)))) {
+            if id == 7 {
+                continue;
+            }
+            if KDBush::sq_dist(x, y, 40.0, 50.0) <= 400.0 {
+                expected_within.push(id);
+            }
+            if (10.0..=60.0).contains(&x) && (20.0..=50.0).contains(&y) {
+                expected_range.push(id);
+            }
+        }
+        let expected = (sorted(expected_within), sorted(expected_range));
+        assert_eq!(query(&index), expected);
+        index.rebuild();
+        assert_eq!(index.pending(), 0);
+        assert_eq!(index.index().ids.len(), 200);
+        assert_eq!(query(&index), expected);
+    }
+
+    #[test]
+    fn test_updatable_empty() {
+        let mut index = UpdatableKDBush::create(Vec::<(f64, f64)>::new(), 8);
+        index.within(0.0, 0.0, 1.0, |_| panic!());
+        index.insert(3, 0.5, 0.5);
+        let mut result = Vec::new();
+        index.range(0.0, 0.0, 1.0, 1.0, |id| result.push(id));
+        assert_eq!(result, vec![3]);
+        index.remove(3);
+        index.rebuild();
+        index.range(0.0, 0.0, 1.0, 1.0, |_| panic!());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::builder::KDBushBuilder;
+use crate::kdbush::{KDBush, PointReader, TIndex, TNumber};
+use core::error::Error;
+use core::fmt;
+
+/// Handling of NaN or infinite coordinates in [`KDBush::try_fill`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidPolicy {
+    /// Fails with the first invalid point
+    Reject,
+    /// Leaves out invalid points
+    Skip,
+}
+
+/// Reasons for failing to build an index
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuildError {
+    /// Point with a NaN or infinite coordinate
+    NonFinite { id: TIndex, x: TNumber, y: TNumber },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::NonFinite { id, x, y } => {
+                write!(f, "point {} has non-finite coordinates ({}, {})", id, x, y)
+            }
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+impl KDBush {
+    /// Creates an index from the given points, checking that all coordinates are finite
+    ///
+    /// NaN coordinates break the ordering of the tree, so queries would silently miss items.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Input points reader
+    /// * `node_size` - Size of the KD-tree node
+    /// * `policy` - Handling of points with NaN or infinite coordinates
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use kdbush::{BuildError, InvalidPolicy, KDBush, DEFAULT_NODE_SIZE};
+    ///
+    /// let points = vec![(54.0, 1.0), (f64::NAN, 21.0), (65.0, 35.0)];
+    /// let result = KDBush::try_fill(points.clone(), DEFAULT_NODE_SIZE, InvalidPolicy::Reject);
+    /// assert!(matches!(result, Err(BuildError::NonFinite { id: 1, .. })));
+    /// let index = KDBush::try_fill(points, DEFAULT_NODE_SIZE, InvalidPolicy::Skip)?;
+    /// # Ok::<(), BuildError>(())
+    /// ```
+    pub fn try_fill<R: PointReader>(
+        points: R,
+        node_size: u8,
+        policy: InvalidPolicy,
+    ) -> Result<KDBush, BuildError> {
+        let mut builder = KDBushBuilder::new().node_size(node_size).validate(policy);
+        builder.add_points(points);
+        builder.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    #[test]
+    fn test_try_fill() {
+        let points = vec![
+            (1.0, 1.0),
+            (f64::INFINITY, 2.0),
+            (3.0, 3.0),
+            (4.0, f64::NAN),
+            (5.0, 5.0),
+        ];
+        let error = KDBush::try_fill(points.clone(), 1, InvalidPolicy::Reject).err();
+        assert_eq!(
+            error,
+            Some(BuildError::NonFinite {
+                id: 1,
+                x: f64::INFINITY,
+                y: 2.0
+            })
+        );
+        assert_eq!(
+            error.unwrap().to_string(),
+            "point 1 has non-finite coordinates (inf, 2)"
+        );
+
+        let index = KDBush::try_fill(points, 1, InvalidPolicy::Skip).unwrap();
+        let mut result = Vec::new();
+        index.range(0.0, 0.0, 10.0, 10.0, |id| result.push(id));
+        result.sort();
+        assert_eq!(result, vec![0, 2, 4]);
+
+        let index = KDBush::try_fill(vec![(1.0, 2.0)], 1, InvalidPolicy::Reject).unwrap();
+        assert_eq!(index.ids, vec![0]);
+    }
+
+    #[test]
+    fn test_try_fill_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let n = points.len();
+            let filled = KDBush::try_fill(points, node_size, InvalidPolicy::Reject);
+            assert_eq!(filled.unwrap().len(), n);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::kdbush::{is_valid_radius, KDBush, Point, TIndex, TNumber};
+use crate::math;
+use alloc::vec::Vec;
+
+/// Query object for query points which move only slightly between queries
+///
+/// Items around the query point are fetched with an additional margin and cached.
+/// Subsequent queries which are covered by the cached neighborhood are answered from the
+/// cache without descending the tree.
+///
+/// # Example
+///
+/// ```
+/// use kdbush::{KDBush, WarmQuery, DEFAULT_NODE_SIZE};
+///
+/// let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0), (65.0, 35.0)], DEFAULT_NODE_SIZE);
+/// let mut query = WarmQuery::new(&index, 10.0);
+/// for step in 0..60 {
+///     let x = 60.0 + step as f64 * 0.1;
+///     query.within(x, 30.0, 20.0, |id| print!("{} ", id));
+///     println!("nearest: {:?}", query.nearest(x, 30.0));
+/// }
+/// ```
+pub struct WarmQuery<'a> {
+    index: &'a KDBush,
+    margin: TNumber,
+    center: Point,
+    radius: TNumber,
+    candidates: Vec<TIndex>,
+}
+
+impl<'a> WarmQuery<'a> {
+    /// Creates a query object with an empty cache
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Queried index
+    /// * `margin` - Additional radius fetched around the query, i.e. how far the query point may move before the cache is refreshed.
+    ///   Negative and NaN margins are treated as 0.
+    pub fn new(index: &'a KDBush, margin: TNumber) -> WarmQuery<'a> {
+        WarmQuery {
+            index,
+            margin: if margin >= 0.0 { margin } else { 0.0 },
+            center: [0.0, 0.0],
+            radius: f64::NEG_INFINITY,
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Finds all items within a given radius from the query point
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    /// * `r` - Radius
+    /// * `visitor` - Result reader
+    pub fn within<F>(&mut self, qx: TNumber, qy: TNumber, r: TNumber, mut visitor: F)
+    where
+        F: FnMut(TIndex),
+    {
+        if !is_valid_radius(r) {
+            return;
+        }
+        if !self.covers(qx, qy, r) {
+            self.refresh(qx, qy, r + self.margin);
+        }
+        let r2 = r * r;
+        for &i in &self.candidates {
+            let p = self.index.points[i];
+            if KDBush::sq_dist(p[0], p[1], qx, qy) <= r2 {
+                visitor(self.index.ids[i]);
+            }
+        }
+    }
+
+    /// Finds the item nearest to the query point
+    ///
+    /// Returns `None` for an empty index and for non-finite query points.
+    ///
+    /// # Arguments
+    ///
+    /// * `qx`, `qy` - Query point
+    pub fn nearest(&mut self, qx: TNumber, qy: TNumber) -> Option<TIndex> {
+        if self.index.ids.is_empty() || !qx.is_finite() || !qy.is_finite() {
+            return None;
+        }
+        loop {
+            let mut best: Option<(TNumber, TIndex)> = None;
+            for &i in &self.candidates {
+                let p = self.index.points[i];
+                let d2 = KDBush::sq_dist(p[0], p[1], qx, qy);
+                if best.map_or(true, |(best_d2, _)| d2 < best_d2) {
+                    best = Some((d2, i));
+                }
+            }
+            match best {
+                // The nearest item is certain if the circle through the best candidate is cached
+                Some((d2, i))
+                    if self.covers(qx, qy, math::sqrt(d2))
+                        || self.candidates.len() == self.index.ids.len() =>
+                {
+                    return Some(self.index.ids[i]);
+                }
+                Some((d2, _)) => self.refresh(qx, qy, math::sqrt(d2) + self.margin),
+                // Nothing within an infinite radius, e.g. only NaN points
+                None if self.radius == f64::INFINITY => return None,
+                None => {
+                    let radius = (self.radius * 2.0).max(self.margin);
+                    self.refresh(qx, qy, if radius > 0.0 { radius } else { 1.0 });
+                }
+            }
+        }
+    }
+
+    fn covers(&self, qx: TNumber, qy: TNumber, r: TNumber) -> bool {
+        math::sqrt(KDBush::sq_dist(self.center[0], self.center[1], qx, qy)) + r <= self.radius
+    }
+
+    fn refresh(&mut self, qx: TNumber, qy: TNumber, radius: TNumber) {
+        self.candidates.clear();
+        self.center = [qx, qy];
+        self.radius = radius;
+        if self.index.ids.is_empty() {
+            return;
+        }
+        let candidates = &mut self.candidates;
+        self.index.within_idx(
+            qx,
+            qy,
+            radius * radius,
+            &mut |i| candidates.push(i),
+            0,
+            self.index.ids.len() - 1,
+            0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kdbush::tiny_inputs;
+
+    fn brute_force(points: &[(f64, f64)], qx: f64, qy: f64, r: f64) -> Vec<usize> {
+        (0..points.len())
+            .filter(|&i| KDBush::sq_dist(points[i].0, points[i].1, qx, qy) <= r * r)
+            .collect()
+    }
+
+    #[test]
+    fn test_warm_within_and_nearest() {
+        let points: Vec<(f64, f64)> = (0..400)
+            .map(|i| ((i * 37 % 101) as f64, (i * 53 % 97) as f64))
+            .collect();
+        let index = KDBush::create(points.clone(), 8);
+        let mut query = WarmQuery::new(&index, 5.0);
+        for step in 0..100 {
+            let (qx, qy) = (20.0 + step as f64 * 0.3, 40.0 + step as f64 * 0.2);
+            let mut result = Vec::new();
+            query.within(qx, qy, 7.0, |id| result.push(id));
+            result.sort();
+            assert_eq!(result, brute_force(&points, qx, qy, 7.0));
+
+            let nearest = query.nearest(qx, qy).unwrap();
+            let d2 = KDBush::sq_dist(points[nearest].0, points[nearest].1, qx, qy);
+            assert!(points
+                .iter()
+                .all(|p| KDBush::sq_dist(p.0, p.1, qx, qy) >= d2));
+        }
+    }
+
+    #[test]
+    fn test_warm_nearest_far_away() {
+        let index = KDBush::create(vec![(54.0, 1.0), (97.0, 21.0)], 10);
+        let mut query = WarmQuery::new(&index, 0.0);
+        assert_eq!(query.nearest(1000.0, 1000.0), Some(1));
+        let empty = KDBush::create(vec![], 10);
+        assert_eq!(WarmQuery::new(&empty, 1.0).nearest(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_warm_invalid_input() {
+        let points: Vec<(f64, f64)> = (0..100)
+            .map(|i| ((i % 10) as f64, (i / 10) as f64))
+            .collect();
+        let index = KDBush::create(points.clone(), 8);
+        let mut query = WarmQuery::new(&index, 5.0);
+        assert_eq!(query.nearest(f64::NAN, 1.0), None);
+        assert_eq!(query.nearest(1.0, f64::INFINITY), None);
+        assert!(query.nearest(3.2, 4.1).is_some());
+
+        // A negative margin must not shrink the cached circle below the queried one
+        for margin in [-2.0, f64::NAN] {
+            let mut query = WarmQuery::new(&index, margin);
+            for step in 0..20 {
+                let qx = 2.0 + step as f64 * 0.25;
+                let mut result = Vec::new();
+                query.within(qx, 5.0, 3.0, |id| result.push(id));
+                result.sort();
+                assert_eq!(result, brute_force(&points, qx, 5.0, 3.0));
+            }
+        }
+
+        let nan = KDBush::create(vec![(f64::NAN, f64::NAN)], 8);
+        assert_eq!(WarmQuery::new(&nan, 1.0).nearest(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_warm_empty_and_tiny() {
+        for (points, node_size) in tiny_inputs() {
+            let all: Vec<TIndex> = (0..points.len()).collect();
+            let index = KDBush::create(points, node_size);
+            let mut warm = WarmQuery::new(&index, 1.0);
+            let mut ids = Vec::new();
+            warm.within(0.0, 0.0, 5.0, |id| ids.push(id));
+            ids.sort();
+            assert_eq!(ids, all);
+            assert_eq!(warm.nearest(0.0, 0.0).is_some(), !all.is_empty());
+        }
+    }
+}